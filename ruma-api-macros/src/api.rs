@@ -3,14 +3,17 @@
 use std::convert::{TryFrom, TryInto as _};
 
 use proc_macro2::{Span, TokenStream};
-use quote::{quote, ToTokens};
+use quote::{format_ident, quote, ToTokens};
 use syn::{
     braced,
+    ext::IdentExt as _,
     parse::{Parse, ParseStream},
-    Field, FieldValue, Ident, Token,
+    parse_quote, Field, FieldValue, GenericArgument, Ident, Lit, LitInt, Meta, MetaList,
+    MetaNameValue, NestedMeta, PathArguments, Token, Type,
 };
 
 mod attribute;
+mod fast_serde;
 mod metadata;
 mod request;
 mod response;
@@ -24,6 +27,186 @@ pub fn strip_serde_attrs(field: &Field) -> Field {
     field
 }
 
+/// Adds `#[serde(skip_serializing_if = "Option::is_none")]` to `Option` body fields, so `None` is
+/// left out of the JSON body instead of being sent as `null`.
+///
+/// Fields listed in `explicit_null_fields` and fields that already have a `skip` serde attribute
+/// are returned unchanged.
+pub fn skip_serializing_none(field: &Field, explicit_null_fields: &[Ident]) -> Field {
+    let mut field = field.clone();
+
+    let is_explicit_null =
+        explicit_null_fields.iter().any(|ident| field.ident.as_ref() == Some(ident));
+    if is_option(&field.ty) && !is_explicit_null && !has_serde_skip_attr(&field) {
+        field.attrs.push(parse_quote!(#[serde(skip_serializing_if = "Option::is_none")]));
+    }
+
+    field
+}
+
+/// Whether the given field has a `skip`, `skip_serializing` or `skip_serializing_if` serde
+/// attribute.
+fn has_serde_skip_attr(field: &Field) -> bool {
+    has_serde_attr(field, &["skip", "skip_serializing", "skip_serializing_if"])
+}
+
+/// Whether the given field has a serde attribute with one of the given names, like `flatten` in
+/// `#[serde(flatten)]` or `default` in `#[serde(default = "...")]`.
+pub fn has_serde_attr(field: &Field, names: &[&str]) -> bool {
+    let is_one_of_names = |path: &syn::Path| names.iter().any(|name| path.is_ident(name));
+
+    field.attrs.iter().filter(|attr| attr.path.is_ident("serde")).any(|attr| {
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            _ => return false,
+        };
+
+        list.nested.iter().any(|meta| match meta {
+            NestedMeta::Meta(Meta::Path(path))
+            | NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, .. })) => {
+                is_one_of_names(path)
+            }
+            _ => false,
+        })
+    })
+}
+
+/// The name of the given field, for use in error messages.
+pub fn field_name(field: &Field) -> String {
+    field.ident.as_ref().map_or_else(|| "_".to_owned(), ToString::to_string)
+}
+
+/// The name of the given body field in the serialized JSON object, taking `#[serde(rename)]` into
+/// account.
+///
+/// Returns `None` for fields that are skipped during serialization or flattened into the
+/// surrounding object, since they don't correspond to a single key.
+pub fn serialized_field_name(field: &Field) -> Option<String> {
+    let mut name = field.ident.as_ref()?.unraw().to_string();
+
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("serde")) {
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            _ => continue,
+        };
+
+        for meta in list.nested {
+            match meta {
+                NestedMeta::Meta(Meta::Path(path))
+                    if path.is_ident("flatten")
+                        || path.is_ident("skip")
+                        || path.is_ident("skip_serializing") =>
+                {
+                    return None;
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(rename),
+                    ..
+                })) if path.is_ident("rename") => name = rename.value(),
+                NestedMeta::Meta(Meta::List(MetaList { path, nested, .. }))
+                    if path.is_ident("rename") =>
+                {
+                    for meta in nested {
+                        if let NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                            path,
+                            lit: Lit::Str(rename),
+                            ..
+                        })) = meta
+                        {
+                            if path.is_ident("serialize") {
+                                name = rename.value();
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Some(name)
+}
+
+/// Whether the given type is an `Option`, judging by the last segment of its path.
+pub fn is_option(ty: &Type) -> bool {
+    last_path_segment_is(ty, "Option")
+}
+
+/// The type wrapped by the given type if it is an `Option`, judging by the last segment of its
+/// path.
+pub fn option_inner_type(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Path(type_path) => match type_path.path.segments.last() {
+            Some(segment) if segment.ident == "Option" => match &segment.arguments {
+                PathArguments::AngleBracketed(args) => match args.args.first() {
+                    Some(GenericArgument::Type(inner)) => Some(inner),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether the given type is an `Option` of an `Option`, judging by the last segments of their
+/// paths.
+pub fn is_nested_option(ty: &Type) -> bool {
+    matches!(option_inner_type(ty), Some(inner) if is_option(inner))
+}
+
+/// Whether the given type is a `Duration`, judging by the last segment of its path.
+pub fn is_duration(ty: &Type) -> bool {
+    last_path_segment_is(ty, "Duration")
+}
+
+/// Whether the given type is a `Vec`, judging by the last segment of its path.
+pub fn is_vec(ty: &Type) -> bool {
+    last_path_segment_is(ty, "Vec")
+}
+
+fn last_path_segment_is(ty: &Type, name: &str) -> bool {
+    match ty {
+        Type::Path(type_path) => match type_path.path.segments.last() {
+            Some(segment) => segment.ident == name,
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Produces a `Debug` implementation for the struct `ident` that prints `<redacted>` instead of
+/// the values of the fields named in `sensitive_fields`.
+pub fn impl_debug_with_redaction<'a>(
+    ident: &Ident,
+    fields: impl Iterator<Item = &'a Field>,
+    sensitive_fields: &[Ident],
+) -> TokenStream {
+    let ident_str = ident.to_string();
+    let field_calls = fields.map(|field| {
+        let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+        let field_name_str = field_name.to_string();
+
+        if sensitive_fields.contains(field_name) {
+            quote!(.field(#field_name_str, &format_args!("<redacted>")))
+        } else {
+            quote!(.field(#field_name_str, &self.#field_name))
+        }
+    });
+
+    quote! {
+        impl std::fmt::Debug for #ident {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(#ident_str)
+                    #(#field_calls)*
+                    .finish()
+            }
+        }
+    }
+}
+
 /// The result of processing the `ruma_api` macro, ready for output back to source code.
 pub struct Api {
     /// The `metadata` section of the macro.
@@ -32,25 +215,93 @@ pub struct Api {
     request: Request,
     /// The `response` section of the macro.
     response: Response,
+    /// The error body types of the `errors` section of the macro, by HTTP status.
+    errors: Vec<(u16, Type)>,
 }
 
 impl TryFrom<RawApi> for Api {
     type Error = syn::Error;
 
     fn try_from(raw_api: RawApi) -> syn::Result<Self> {
-        let res = Self {
+        let mut res = Self {
             metadata: raw_api.metadata.try_into()?,
             request: raw_api.request.try_into()?,
             response: raw_api.response.try_into()?,
+            errors: Vec::new(),
+        };
+
+        for entry in raw_api.errors.map(|errors| errors.entries).unwrap_or_default() {
+            let status = entry.status.base10_parse::<u16>()?;
+            if !(400..600).contains(&status) {
+                return Err(syn::Error::new_spanned(
+                    &entry.status,
+                    "expected an error (4xx or 5xx) HTTP status code",
+                ));
+            }
+            if res.errors.iter().any(|(s, _)| *s == status) {
+                return Err(syn::Error::new_spanned(&entry.status, "duplicate status code"));
+            }
+
+            res.errors.push((status, entry.ty));
+        }
+
+        if res.metadata.fast {
+            res.request.enable_fast_serde()?;
+            res.response.enable_fast_serde()?;
+        }
+
+        if res.metadata.deny_unknown_fields {
+            res.request.enable_deny_unknown_fields()?;
+        }
+
+        let response_body_error = if res.metadata.method == "HEAD" {
+            Some("HEAD endpoints can't have response body fields")
+        } else if res.metadata.method == "OPTIONS" {
+            Some("OPTIONS endpoints can't have response body fields")
+        } else if res.metadata.status == "NO_CONTENT" || res.metadata.status == "RESET_CONTENT" {
+            Some("responses with this status can't have body fields")
+        } else {
+            None
         };
 
+        if let Some(message) = response_body_error {
+            let mut body_fields = res.response.body_fields();
+            if let Some(field) = body_fields.next() {
+                let mut error = syn::Error::new_spanned(field, message);
+                for field in body_fields {
+                    error.combine(syn::Error::new_spanned(field, message));
+                }
+
+                return Err(error);
+            }
+        }
+
+        if let Some((field, _)) = res.request.path_default() {
+            let path = res.metadata.path.value();
+            let last_segment = path.rsplit('/').next().unwrap_or_default();
+            let is_last = matches!(
+                (last_segment.strip_prefix(':'), &field.ident),
+                (Some(path_var), Some(ident)) if ident == path_var
+            );
+
+            if !is_last {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "`path_default` can only be used on the last segment of the path",
+                ));
+            }
+        }
+
         let newtype_body_field = res.request.newtype_body_field();
-        if res.metadata.method == "GET"
-            && (res.request.has_body_fields() || newtype_body_field.is_some())
+        if (res.metadata.method == "GET" || res.metadata.method == "HEAD")
+            && (res.request.has_body_fields()
+                || newtype_body_field.is_some()
+                || res.request.has_multipart_fields())
         {
+            let message = format!("{} endpoints can't have body fields", res.metadata.method);
             let mut combined_error: Option<syn::Error> = None;
             let mut add_error = |field| {
-                let error = syn::Error::new_spanned(field, "GET endpoints can't have body fields");
+                let error = syn::Error::new_spanned(field, &message);
                 if let Some(combined_error_ref) = &mut combined_error {
                     combined_error_ref.combine(error);
                 } else {
@@ -58,7 +309,7 @@ impl TryFrom<RawApi> for Api {
                 }
             };
 
-            for field in res.request.body_fields() {
+            for field in res.request.body_fields().chain(res.request.multipart_fields()) {
                 add_error(field);
             }
 
@@ -84,6 +335,63 @@ impl ToTokens for Api {
         let path = &self.metadata.path;
         let rate_limited = &self.metadata.rate_limited;
         let requires_authentication = &self.metadata.requires_authentication;
+        let status = &self.metadata.status;
+        let appservice = &self.metadata.appservice;
+        let api = &self.metadata.api;
+        let added_in = match &self.metadata.added_in {
+            Some(version) => quote! { Some(#version) },
+            None => quote! { None },
+        };
+        let removed_in = match &self.metadata.removed_in {
+            Some(version) => quote! { Some(#version) },
+            None => quote! { None },
+        };
+        let (error_ty, response_error) = if self.errors.is_empty() {
+            (self.metadata.error.clone(), TokenStream::new())
+        } else {
+            let default_error_ty = &self.metadata.error;
+            let variants: Vec<_> = self
+                .errors
+                .iter()
+                .map(|(status, _)| format_ident!("Status{}", status, span = Span::call_site()))
+                .collect();
+            let variant_docs = self
+                .errors
+                .iter()
+                .map(|(status, _)| format!(" The body of a `{}` response.", status));
+            let statuses = self.errors.iter().map(|(status, _)| status);
+            let types = self.errors.iter().map(|(_, ty)| ty);
+
+            let response_error = quote! {
+                /// The error bodies of this endpoint, by HTTP status.
+                #[derive(Debug)]
+                pub enum ResponseError {
+                    #(
+                        #[doc = #variant_docs]
+                        #variants(#types),
+                    )*
+                    /// The body of an error response with any other status.
+                    Other(#default_error_ty),
+                }
+
+                impl ruma_api::error::ErrorBody for ResponseError {
+                    fn from_http_response(
+                        response: &ruma_api::exports::http::Response<Vec<u8>>,
+                    ) -> Option<Self> {
+                        match response.status().as_u16() {
+                            #(
+                                #statuses => ruma_api::error::ErrorBody::from_http_response(response)
+                                    .map(Self::#variants),
+                            )*
+                            _ => ruma_api::error::ErrorBody::from_http_response(response)
+                                .map(Self::Other),
+                        }
+                    }
+                }
+            };
+
+            (parse_quote!(ResponseError), response_error)
+        };
 
         let request_type = &self.request;
         let response_type = &self.response;
@@ -102,167 +410,498 @@ impl ToTokens for Api {
 
         let extract_request_path = if self.request.has_path_fields() {
             quote! {
-                let path_segments: Vec<&str> = request.uri().path()[1..].split('/').collect();
+                let path_segments: Vec<&str> = parts.uri.path()[1..].split('/').collect();
             }
         } else {
             TokenStream::new()
         };
 
-        let (url_set_path, parse_request_path) = if self.request.has_path_fields() {
-            let path_str = path.value();
+        let path_str = path.value();
+        assert!(path_str.starts_with('/'), "path needs to start with '/'");
 
-            assert!(path_str.starts_with('/'), "path needs to start with '/'");
-            assert!(
-                path_str.chars().filter(|c| *c == ':').count() == self.request.path_field_count(),
-                "number of declared path parameters needs to match amount of placeholders in path"
-            );
+        let path_segments = path_str[1..].split('/');
+        let is_transaction_id = |path_var: &str| {
+            matches!(
+                self.request.transaction_id_field().and_then(|field| field.ident.as_ref()),
+                Some(ident) if ident == path_var
+            )
+        };
+
+        let path_default_of = |path_var: &str| match self.request.path_default() {
+            Some((field, default)) if field.ident.as_ref().unwrap() == path_var => Some(default),
+            _ => None,
+        };
+
+        let segment_arg = |segment: &str| {
+            if let Some(path_var) = segment.strip_prefix(':') {
+                let path_var_ident = Ident::new(path_var, Span::call_site());
+
+                if is_transaction_id(path_var) {
+                    quote! {
+                        &match &request.#path_var_ident {
+                            Some(val) => ruma_api::path::PathParam::to_path_segment(val),
+                            None => {
+                                return Err(
+                                    ruma_api::error::IntoHttpError::missing_transaction_id(),
+                                );
+                            }
+                        }
+                    }
+                } else if let Some(default) = path_default_of(path_var) {
+                    quote! {
+                        &match &request.#path_var_ident {
+                            Some(val) => ruma_api::path::PathParam::to_path_segment(val),
+                            None => #default.to_owned(),
+                        }
+                    }
+                } else {
+                    quote! {
+                        &ruma_api::path::PathParam::to_path_segment(&request.#path_var_ident)
+                    }
+                }
+            } else {
+                quote!(#segment)
+            }
+        };
 
-            let request_path_init_fields = self.request.request_path_init_fields();
+        let is_comma_separated = |segment: &str| {
+            segment
+                .strip_prefix(':')
+                .is_some_and(|path_var| self.request.is_comma_separated(path_var))
+        };
 
-            let path_segments = path_str[1..].split('/');
+        let url_set_path = if path_segments.clone().any(is_comma_separated) {
             let path_segment_push = path_segments.clone().map(|segment| {
-                let arg = if segment.starts_with(':') {
-                    let path_var = &segment[1..];
-                    let path_var_ident = Ident::new(path_var, Span::call_site());
-                    quote!(&request_path.#path_var_ident.to_string())
+                let encoded = if is_comma_separated(segment) {
+                    let path_var_ident = Ident::new(&segment[1..], Span::call_site());
+                    quote!(ruma_api::path::join_comma_separated(&request.#path_var_ident))
                 } else {
-                    quote!(#segment)
+                    let arg = segment_arg(segment);
+                    quote!(ruma_api::path::encode_segment(#arg))
                 };
 
                 quote! {
-                    path_segments.push(#arg);
+                    path.push('/');
+                    path.push_str(&#encoded);
                 }
             });
 
-            let set_tokens = quote! {
-                let request_path = RequestPath {
-                    #request_path_init_fields
-                };
+            // `url` percent-encodes `%` in the segments pushed to a URL, which would double-encode
+            // the commas within the elements of comma-separated segments, so the path is built
+            // manually instead.
+            quote! {
+                if url.cannot_be_a_base() {
+                    return Err(ruma_api::error::IntoHttpError::cannot_be_a_base_url());
+                }
 
-                // This `unwrap()` can only fail when the url is a
-                // cannot-be-base url like `mailto:` or `data:`, which is not
-                // the case for our placeholder url.
-                let mut path_segments = url.path_segments_mut().unwrap();
+                let mut path =
+                    url.path().strip_suffix('/').unwrap_or_else(|| url.path()).to_owned();
                 #(#path_segment_push)*
-            };
+                url.set_path(&path);
+            }
+        } else {
+            let path_segment_push = path_segments.clone().map(|segment| {
+                let arg = segment_arg(segment);
+
+                quote! {
+                    path_segments.push(#arg);
+                }
+            });
+
+            quote! {
+                let mut path_segments = url
+                    .path_segments_mut()
+                    .map_err(|_| ruma_api::error::IntoHttpError::cannot_be_a_base_url())?;
+                path_segments.pop_if_empty();
+                #(#path_segment_push)*
+            }
+        };
+
+        let lossy_path_decoding = self.metadata.lossy_path_decoding;
+        let parse_request_path = if self.request.has_path_fields() {
+            assert!(
+                path_str.chars().filter(|c| *c == ':').count() == self.request.path_field_count(),
+                "number of declared path parameters needs to match amount of placeholders in path"
+            );
 
             let path_fields = path_segments.enumerate().filter(|(_, s)| s.starts_with(':')).map(
                 |(i, segment)| {
                     let path_var = &segment[1..];
                     let path_var_ident = Ident::new(path_var, Span::call_site());
-
-                    quote! {
-                        #path_var_ident: {
-                            use std::ops::Deref as _;
-                            use ruma_api::error::RequestDeserializationError;
-
-                            let segment = path_segments.get(#i).unwrap().as_bytes();
-                            let decoded =
-                                ruma_api::exports::percent_encoding::percent_decode(segment)
-                                .decode_utf8_lossy();
-                            match ruma_api::exports::serde_json::from_str(decoded.deref()) {
+                    let is_optional =
+                        is_transaction_id(path_var) || path_default_of(path_var).is_some();
+                    let value = if is_optional { quote!(Some(val)) } else { quote!(val) };
+
+                    let parse_segment = if self.request.is_comma_separated(path_var) {
+                        quote! {
+                            match ruma_api::path::split_comma_separated(
+                                segment,
+                                #lossy_path_decoding,
+                            ) {
                                 Ok(val) => val,
                                 Err(err) => {
                                     return Err(
-                                        RequestDeserializationError::new(err, request).into()
+                                        RequestDeserializationError::from_parts(
+                                            DeserializationError::Path(err),
+                                            parts,
+                                        )
+                                        .into()
                                     );
                                 }
                             }
                         }
+                    } else {
+                        quote! {
+                            let decoded =
+                                match ruma_api::path::decode_segment(segment, #lossy_path_decoding) {
+                                    Ok(decoded) => decoded,
+                                    Err(err) => {
+                                        return Err(
+                                            RequestDeserializationError::from_parts(
+                                                DeserializationError::Path(err),
+                                                parts,
+                                            )
+                                            .into()
+                                        );
+                                    }
+                                };
+                            match ruma_api::path::PathParam::from_path_segment(&decoded) {
+                                Ok(val) => #value,
+                                Err(err) => {
+                                    return Err(
+                                        RequestDeserializationError::from_parts(
+                                            DeserializationError::Path(err.to_string()),
+                                            parts,
+                                        )
+                                        .into()
+                                    );
+                                }
+                            }
+                        }
+                    };
+
+                    // A trailing segment with a default may be left out of the path entirely.
+                    let value = if path_default_of(path_var).is_some() {
+                        quote! {
+                            match path_segments.get(#i).filter(|segment| !segment.is_empty()) {
+                                Some(segment) => {
+                                    #parse_segment
+                                }
+                                None => None,
+                            }
+                        }
+                    } else {
+                        quote! {
+                            let segment = path_segments.get(#i).unwrap();
+                            #parse_segment
+                        }
+                    };
+
+                    quote! {
+                        #path_var_ident: {
+                            use ruma_api::error::{
+                                DeserializationError, RequestDeserializationError,
+                            };
+
+                            #value
+                        }
                     }
                 },
             );
 
-            let parse_tokens = quote! {
+            quote! {
                 #(#path_fields,)*
-            };
-
-            (set_tokens, parse_tokens)
+            }
         } else {
-            let set_tokens = quote! {
-                url.set_path(metadata.path);
-            };
-            let parse_tokens = TokenStream::new();
-            (set_tokens, parse_tokens)
+            TokenStream::new()
         };
 
-        let url_set_querystring = if let Some(field) = self.request.query_map_field() {
-            let field_name = field.ident.as_ref().expect("expected field to have identifier");
-            let field_type = &field.ty;
+        // If `borrowed` is set, `request` is a reference to the request, so query fields that are
+        // serialized by value are cloned instead of being moved out.
+        let url_set_querystring = |borrowed: bool| {
+            if let Some(field) = self.request.query_map_field() {
+                let field_name = field.ident.as_ref().expect("expected field to have identifier");
+                let field_type = &field.ty;
+                let query_map = if borrowed {
+                    quote!(::std::clone::Clone::clone(&request.#field_name))
+                } else {
+                    quote!(request.#field_name)
+                };
 
-            quote! {
-                // This function exists so that the compiler will throw an
-                // error when the type of the field with the query_map
-                // attribute doesn't implement IntoIterator<Item = (String, String)>
-                //
-                // This is necessary because the serde_urlencoded::to_string
-                // call will result in a runtime error when the type cannot be
-                // encoded as a list key-value pairs (?key1=value1&key2=value2)
-                //
-                // By asserting that it implements the iterator trait, we can
-                // ensure that it won't fail.
-                fn assert_trait_impl<T>()
-                where
-                    T: std::iter::IntoIterator<Item = (std::string::String, std::string::String)>,
-                {}
-                assert_trait_impl::<#field_type>();
+                quote! {
+                    // This function exists so that the compiler will throw an
+                    // error when the type of the field with the query_map
+                    // attribute doesn't implement IntoIterator<Item = (String, V)>
+                    // with V: Display.
+                    //
+                    // This is necessary because the serde_urlencoded::to_string
+                    // call will result in a runtime error when the type cannot be
+                    // encoded as a list key-value pairs (?key1=value1&key2=value2)
+                    //
+                    // By asserting that it implements the iterator trait, we can
+                    // ensure that it won't fail.
+                    fn assert_trait_impl<T, V>()
+                    where
+                        T: std::iter::IntoIterator<Item = (std::string::String, V)>,
+                        V: std::fmt::Display,
+                    {}
+                    assert_trait_impl::<#field_type, _>();
+
+                    let request_query: Vec<(String, String)> =
+                        #query_map
+                            .into_iter()
+                            .map(|(key, value)| (key, value.to_string()))
+                            .collect();
+                    let query_str = ruma_api::query::to_string(request_query)?;
+
+                    let query_opt: Option<&str> = if query_str.is_empty() {
+                        None
+                    } else {
+                        Some(&query_str)
+                    };
 
-                let request_query = RequestQuery(request.#field_name);
-                let query_str = ruma_api::exports::serde_urlencoded::to_string(
-                    request_query,
-                )?;
+                    url.set_query(query_opt);
+                }
+            } else if self.request.has_query_fields() {
+                let init_query_str = if self.request.has_query_struct_fields() {
+                    let request_query_init_fields =
+                        self.request.request_query_init_fields(borrowed);
+
+                    quote! {
+                        let request_query = RequestQuery {
+                            #request_query_init_fields
+                        };
 
-                let query_opt: Option<&str> = if query_str.is_empty() {
-                    None
+                        let mut query_str = ruma_api::query::to_string(request_query)?;
+                    }
                 } else {
-                    Some(&query_str)
+                    quote! {
+                        let mut query_str = String::new();
+                    }
                 };
 
-                url.set_query(query_opt);
-            }
-        } else if self.request.has_query_fields() {
-            let request_query_init_fields = self.request.request_query_init_fields();
+                let append_repeated = self.request.repeated_query_fields().map(|field| {
+                    let field_name =
+                        field.ident.as_ref().expect("expected field to have an identifier");
+                    let key = field_name.unraw().to_string();
 
-            quote! {
-                let request_query = RequestQuery {
-                    #request_query_init_fields
-                };
+                    quote! {
+                        ruma_api::query::append_repeated(
+                            &mut query_str,
+                            #key,
+                            &request.#field_name,
+                        )?;
+                    }
+                });
+
+                let append_flattened = self.request.flattened_query_fields().map(|field| {
+                    let field_name =
+                        field.ident.as_ref().expect("expected field to have an identifier");
+
+                    quote! {
+                        let flattened_query_str = ruma_api::query::to_string(&request.#field_name)?;
+                        if !flattened_query_str.is_empty() {
+                            if !query_str.is_empty() {
+                                query_str.push('&');
+                            }
+                            query_str.push_str(&flattened_query_str);
+                        }
+                    }
+                });
+
+                let append_prefixed =
+                    self.request.prefixed_query_map_field().map(|(field, prefix)| {
+                        let field_name =
+                            field.ident.as_ref().expect("expected field to have an identifier");
+
+                        let query_map = if borrowed {
+                            quote!(::std::clone::Clone::clone(&request.#field_name))
+                        } else {
+                            quote!(request.#field_name)
+                        };
+
+                        quote! {
+                            ruma_api::query::append_prefixed(&mut query_str, #prefix, #query_map)?;
+                        }
+                    });
+
+                quote! {
+                    #init_query_str
+                    #(#append_flattened)*
+                    #(#append_repeated)*
+                    #append_prefixed
+
+                    let query_opt: Option<&str> = if query_str.is_empty() {
+                        None
+                    } else {
+                        Some(&query_str)
+                    };
 
-                url.set_query(Some(&ruma_api::exports::serde_urlencoded::to_string(
-                    request_query,
-                )?));
+                    url.set_query(query_opt);
+                }
+            } else {
+                TokenStream::new()
             }
-        } else {
-            TokenStream::new()
         };
 
-        let extract_request_query = if self.request.query_map_field().is_some() {
+        let url_set_borrowed_querystring = url_set_querystring(true);
+        let url_set_querystring = url_set_querystring(false);
+
+        let extract_request_query = if let Some(field) = self.request.query_map_field() {
+            let field_type = &field.ty;
+
             quote! {
-                let request_query = match ruma_api::exports::serde_urlencoded::from_str(
-                    &request.uri().query().unwrap_or("")
-                ) {
+                let request_query: Vec<(String, String)> =
+                    match ruma_api::exports::serde_urlencoded::from_str(
+                        parts.uri.query().unwrap_or("")
+                    ) {
+                        Ok(query) => query,
+                        Err(err) => {
+                            return Err(
+                                ruma_api::error::RequestDeserializationError::from_parts(err, parts)
+                                    .into()
+                            );
+                        }
+                    };
+
+                // Parses the values with the `FromStr` implementation of the query_map field's
+                // value type, which is inferred from its `IntoIterator` implementation.
+                fn parse_query_map<T, V>(
+                    pairs: Vec<(String, String)>,
+                ) -> Result<T, ruma_api::exports::serde_urlencoded::de::Error>
+                where
+                    T: std::iter::IntoIterator<Item = (std::string::String, V)>
+                        + std::iter::FromIterator<(std::string::String, V)>,
+                    V: std::str::FromStr,
+                    V::Err: std::fmt::Display,
+                {
+                    pairs
+                        .into_iter()
+                        .map(|(key, value)| match value.parse() {
+                            Ok(value) => Ok((key, value)),
+                            Err(err) => Err(ruma_api::exports::serde::de::Error::custom(format!(
+                                "invalid value for query parameter `{}`: {}",
+                                key, err,
+                            ))),
+                        })
+                        .collect()
+                }
+
+                let request_query: #field_type = match parse_query_map(request_query) {
                     Ok(query) => query,
                     Err(err) => {
                         return Err(
-                            ruma_api::error::RequestDeserializationError::new(err, request).into()
+                            ruma_api::error::RequestDeserializationError::from_parts(err, parts).into()
                         );
                     }
                 };
             }
         } else if self.request.has_query_fields() {
-            quote! {
-                let request_query: RequestQuery =
-                    match ruma_api::exports::serde_urlencoded::from_str(
-                        &request.uri().query().unwrap_or("")
+            let extract_query_struct = if self.request.has_query_struct_fields() {
+                quote! {
+                    let request_query: RequestQuery =
+                        match ruma_api::exports::serde_urlencoded::from_str(
+                            parts.uri.query().unwrap_or("")
+                        ) {
+                            Ok(query) => query,
+                            Err(err) => {
+                                return Err(
+                                    ruma_api::error::RequestDeserializationError::from_parts(err, parts)
+                                        .into()
+                                );
+                            }
+                        };
+                }
+            } else {
+                TokenStream::new()
+            };
+
+            let has_pairs = self.request.repeated_query_fields().next().is_some()
+                || self.request.prefixed_query_map_field().is_some();
+            let extract_pairs = if has_pairs {
+                let parse_fields = self.request.repeated_query_fields().map(|field| {
+                    let key = field
+                        .ident
+                        .as_ref()
+                        .expect("expected field to have an identifier")
+                        .unraw()
+                        .to_string();
+                    let var = request::repeated_query_var(field);
+
+                    quote! {
+                        let #var = match ruma_api::query::parse_repeated(&request_query_pairs, #key) {
+                            Ok(values) => values,
+                            Err(err) => {
+                                return Err(
+                                    ruma_api::error::RequestDeserializationError::from_parts(err, parts)
+                                        .into()
+                                );
+                            }
+                        };
+                    }
+                });
+
+                let parse_prefixed = self.request.prefixed_query_map_field().map(|(field, prefix)| {
+                    let var = request::prefixed_query_map_var(field);
+
+                    quote! {
+                        let #var = match ruma_api::query::parse_prefixed(&request_query_pairs, #prefix) {
+                            Ok(map) => map,
+                            Err(err) => {
+                                return Err(
+                                    ruma_api::error::RequestDeserializationError::from_parts(err, parts)
+                                        .into()
+                                );
+                            }
+                        };
+                    }
+                });
+
+                quote! {
+                    let request_query_pairs: Vec<(String, String)> =
+                        match ruma_api::exports::serde_urlencoded::from_str(
+                            parts.uri.query().unwrap_or("")
+                        ) {
+                            Ok(pairs) => pairs,
+                            Err(err) => {
+                                return Err(
+                                    ruma_api::error::RequestDeserializationError::from_parts(err, parts)
+                                        .into()
+                                );
+                            }
+                        };
+
+                    #(#parse_fields)*
+                    #parse_prefixed
+                }
+            } else {
+                TokenStream::new()
+            };
+
+            // Flattened fields are parsed from the whole query string, ignoring the parameters
+            // that belong to other fields.
+            let extract_flattened = self.request.flattened_query_fields().map(|field| {
+                let var = request::flattened_query_var(field);
+
+                quote! {
+                    let #var = match ruma_api::exports::serde_urlencoded::from_str(
+                        parts.uri.query().unwrap_or("")
                     ) {
                         Ok(query) => query,
                         Err(err) => {
                             return Err(
-                                ruma_api::error::RequestDeserializationError::new(err, request)
+                                ruma_api::error::RequestDeserializationError::from_parts(err, parts)
                                     .into()
                             );
                         }
                     };
+                }
+            });
+
+            quote! {
+                #extract_query_struct
+                #(#extract_flattened)*
+                #extract_pairs
             }
         } else {
             TokenStream::new()
@@ -279,7 +918,7 @@ impl ToTokens for Api {
         };
 
         let add_headers_to_request = if self.request.has_header_fields() {
-            let add_headers = self.request.add_headers_to_request();
+            let add_headers = self.request.add_headers_to_request(false);
             quote! {
                 let headers = http_request.headers_mut();
                 #add_headers
@@ -288,39 +927,127 @@ impl ToTokens for Api {
             TokenStream::new()
         };
 
+        let validate_headers = if self.request.has_header_fields() {
+            let add_headers = self.request.add_headers_to_request(true);
+            quote! {
+                let request = self;
+                let headers = &mut ruma_api::exports::http::HeaderMap::new();
+                #add_headers
+            }
+        } else {
+            TokenStream::new()
+        };
+
         let extract_request_headers = if self.request.has_header_fields() {
             quote! {
-                let headers = request.headers();
+                let headers = &parts.headers;
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let parse_multipart_boundary = if self.request.has_multipart_fields() {
+            quote! {
+                multipart_boundary: match ruma_api::multipart::boundary_from_headers(&parts.headers) {
+                    Ok(boundary) => boundary,
+                    Err(err) => {
+                        return Err(
+                            ruma_api::error::RequestDeserializationError::from_parts(err, parts)
+                                .into()
+                        );
+                    }
+                },
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let extract_request_body = if self.request.has_multipart_fields() {
+            quote! {
+                let mut multipart_parts =
+                    match ruma_api::multipart::decode(&self.multipart_boundary, body) {
+                        Ok(parts) => parts,
+                        Err(err) => {
+                            let err = ruma_api::error::RequestDeserializationError::new(
+                                err,
+                                ruma_api::exports::http::Request::new(body.to_vec()),
+                            );
+                            return Err(
+                                ruma_api::error::FromHttpRequestError::from(err)
+                                    .with_endpoint_name(#name)
+                            );
+                        }
+                    };
+            }
+        } else if self.request.has_body_fields() || self.request.newtype_body_field().is_some() {
+            quote! {
+                let request_body: <RequestBody as ruma_api::Outgoing>::Incoming =
+                    match ruma_api::exports::serde_json::from_slice(body) {
+                        Ok(body) => body,
+                        Err(err) => {
+                            let err = ruma_api::error::RequestDeserializationError::new(
+                                err,
+                                ruma_api::exports::http::Request::new(body.to_vec()),
+                            );
+                            return Err(
+                                ruma_api::error::FromHttpRequestError::from(err)
+                                    .with_endpoint_name(#name)
+                            );
+                        }
+                    };
             }
         } else {
             TokenStream::new()
         };
 
-        let extract_request_body =
-            if self.request.has_body_fields() || self.request.newtype_body_field().is_some() {
-                quote! {
-                    let request_body: <RequestBody as ruma_api::Outgoing>::Incoming =
-                        match ruma_api::exports::serde_json::from_slice(request.body().as_slice()) {
-                            Ok(body) => body,
-                            Err(err) => {
-                                return Err(
-                                    ruma_api::error::RequestDeserializationError::new(err, request)
-                                        .into()
-                                );
-                            }
-                        };
-                }
-            } else {
-                TokenStream::new()
-            };
-
         let parse_request_headers = if self.request.has_header_fields() {
             self.request.parse_headers_from_request()
         } else {
             TokenStream::new()
         };
 
-        let request_body = if let Some(field) = self.request.newtype_raw_body_field() {
+        let serialize_body = if self.metadata.canonical_json {
+            quote!(ruma_api::canonical_json::to_vec)
+        } else {
+            quote!(ruma_api::exports::serde_json::to_vec)
+        };
+
+        let (encode_multipart_body, set_multipart_content_type) =
+            if self.request.has_multipart_fields() {
+                let parts = self.request.multipart_fields().map(|field| {
+                    let field_name =
+                        field.ident.as_ref().expect("expected field to have an identifier");
+                    let part_name = field_name.unraw().to_string();
+
+                    quote! {
+                        (
+                            #part_name,
+                            ruma_api::multipart::MultipartField::to_part(&request.#field_name)?,
+                        )
+                    }
+                });
+
+                let encode = quote! {
+                    let (multipart_content_type, multipart_body) = ruma_api::multipart::encode(
+                        &ruma_api::multipart::generate_boundary(),
+                        vec![#(#parts),*],
+                    );
+                };
+                let set_content_type = quote! {
+                    http_request.headers_mut().insert(
+                        ruma_api::exports::http::header::CONTENT_TYPE,
+                        ruma_api::header::to_value(&multipart_content_type)?,
+                    );
+                };
+
+                (encode, set_content_type)
+            } else {
+                (TokenStream::new(), TokenStream::new())
+            };
+
+        let request_body = if self.request.has_multipart_fields() {
+            quote!(multipart_body)
+        } else if let Some(field) = self.request.newtype_raw_body_field() {
             let field_name = field.ident.as_ref().expect("expected field to have an identifier");
             quote!(request.#field_name)
         } else if self.request.has_body_fields() || self.request.newtype_body_field().is_some() {
@@ -333,10 +1060,20 @@ impl ToTokens for Api {
                 quote! { { #initializers } }
             };
 
+            let check = if self.request.uses_wrap_incoming() {
+                quote!(check_deserializes)
+            } else {
+                quote!(check)
+            };
+
             quote! {
                 {
                     let request_body = RequestBody #request_body_initializers;
-                    ruma_api::exports::serde_json::to_vec(&request_body)?
+                    let body = #serialize_body(&request_body)?;
+                    ruma_api::roundtrip::#check::<<RequestBody as ruma_api::Outgoing>::Incoming>(
+                        &body,
+                    );
+                    body
                 }
             }
         } else {
@@ -351,44 +1088,150 @@ impl ToTokens for Api {
         } else if let Some(field) = self.request.newtype_raw_body_field() {
             let field_name = field.ident.as_ref().expect("expected field to have an identifier");
             quote! {
-                #field_name: request.into_body(),
+                #field_name: body,
             }
+        } else if self.request.has_multipart_fields() {
+            let fields = self.request.multipart_fields().map(|field| {
+                let field_name =
+                    field.ident.as_ref().expect("expected field to have an identifier");
+                let part_name = field_name.unraw().to_string();
+
+                quote! {
+                    #field_name: match ruma_api::multipart::take_part(
+                        &mut multipart_parts,
+                        #part_name,
+                    ) {
+                        Ok(value) => value,
+                        Err(err) => {
+                            let err = ruma_api::error::RequestDeserializationError::new(
+                                err,
+                                ruma_api::exports::http::Request::new(body.to_vec()),
+                            );
+                            return Err(
+                                ruma_api::error::FromHttpRequestError::from(err)
+                                    .with_endpoint_name(#name)
+                            );
+                        }
+                    },
+                }
+            });
+
+            quote! { #(#fields)* }
         } else {
             self.request.request_init_body_fields()
         };
 
-        let extract_response_headers = if self.response.has_header_fields() {
+        // Responses with a `Content-Range` header are partial, so they have a different status.
+        let content_range_field = self.response.content_range_field();
+
+        // The body of the closure passed to `from_http_response` or, if `borrowed` is set,
+        // `from_http_response_ref`.
+        let parse_response = |borrowed: bool| {
+            let new_error = response::deserialization_error_constructor(borrowed);
+            let extract_response_headers = self.response.parse_header_fields(borrowed);
+
+            let typed_response_body_decl = if self.response.has_body_fields()
+                || self.response.newtype_body_field().is_some()
+            {
+                quote! {
+                    let response_body: <ResponseBody as ruma_api::Outgoing>::Incoming =
+                        match ruma_api::exports::serde_json::from_slice(response.body()) {
+                            Ok(body) => body,
+                            Err(err) => return Err(#new_error(err, response).into()),
+                        };
+                }
+            } else if self.response.newtype_text_body_field().is_some() {
+                quote! {
+                    let response_text_body = match std::str::from_utf8(response.body()) {
+                        Ok(body) => body.to_owned(),
+                        Err(err) => return Err(#new_error(err, response).into()),
+                    };
+                }
+            } else {
+                TokenStream::new()
+            };
+
+            let check_response_status = if self.metadata.has_explicit_status {
+                let is_partial = if content_range_field.is_some() {
+                    quote! {
+                        || status == ruma_api::exports::http::StatusCode::PARTIAL_CONTENT
+                    }
+                } else {
+                    TokenStream::new()
+                };
+                let new_status_error = if borrowed {
+                    quote!(ruma_api::error::UnexpectedStatusError::from_borrowed)
+                } else {
+                    quote!(ruma_api::error::UnexpectedStatusError::new)
+                };
+
+                quote! {
+                    let status = response.status();
+                    if !(status == ruma_api::exports::http::StatusCode::#status #is_partial) {
+                        let expected = ruma_api::exports::http::StatusCode::#status;
+                        return Err(#new_status_error(expected, response).into());
+                    }
+                }
+            } else {
+                TokenStream::new()
+            };
+
+            let response_init_fields = self.response.init_fields(borrowed);
+
             quote! {
-                let mut headers = response.headers().clone();
+                #check_response_status
+
+                #extract_response_headers
+
+                #typed_response_body_decl
+
+                Ok(Self {
+                    #response_init_fields
+                })
             }
-        } else {
-            TokenStream::new()
         };
+        let parse_owned_response = parse_response(false);
+        let parse_borrowed_response = parse_response(true);
 
-        let typed_response_body_decl = if self.response.has_body_fields()
-            || self.response.newtype_body_field().is_some()
-        {
-            quote! {
-                let response_body: <ResponseBody as ruma_api::Outgoing>::Incoming =
-                    match ruma_api::exports::serde_json::from_slice(response.body().as_slice()) {
-                        Ok(body) => body,
-                        Err(err) => {
-                            return Err(
-                                ruma_api::error::ResponseDeserializationError::new(err, response)
-                                    .into()
-                            );
-                        }
-                    };
+        let request_body_keys = self.request.body_fields().filter_map(serialized_field_name);
+        let response_body_keys =
+            self.response.struct_body_fields().filter_map(serialized_field_name);
+
+        let response_status = match content_range_field {
+            Some(field) if is_option(&field.ty) => {
+                let field_name = &field.ident;
+                quote! {
+                    if response.#field_name.is_some() {
+                        ruma_api::exports::http::StatusCode::PARTIAL_CONTENT
+                    } else {
+                        ruma_api::exports::http::StatusCode::#status
+                    }
+                }
             }
-        } else {
-            TokenStream::new()
+            Some(_) => quote!(ruma_api::exports::http::StatusCode::PARTIAL_CONTENT),
+            None => quote!(ruma_api::exports::http::StatusCode::#status),
         };
 
-        let response_init_fields = self.response.init_fields();
-
+        // Responses of `OPTIONS` endpoints only consist of headers.
+        let default_content_type =
+            if self.response.has_content_type_header() || self.metadata.method == "OPTIONS" {
+                TokenStream::new()
+            } else if self.response.newtype_text_body_field().is_some() {
+                quote! {
+                    .header(
+                        ruma_api::exports::http::header::CONTENT_TYPE,
+                        "text/plain; charset=utf-8",
+                    )
+                }
+            } else {
+                quote! {
+                    .header(ruma_api::exports::http::header::CONTENT_TYPE, "application/json")
+                }
+            };
         let serialize_response_headers = self.response.apply_header_fields();
+        let append_response_headers = self.response.append_header_fields();
 
-        let body = self.response.to_body();
+        let body = self.response.to_body(&serialize_body);
 
         let request_doc = format!(
             "Data for a request to the `{}` API endpoint.\n\n{}",
@@ -397,6 +1240,166 @@ impl ToTokens for Api {
         );
         let response_doc = format!("Data in the response from the `{}` API endpoint.", name);
 
+        let suggested_timeout = self.request.timeout_hint_field().map(|field| {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+
+            quote! {
+                fn suggested_timeout(&self) -> Option<std::time::Duration> {
+                    ruma_api::timeout::TimeoutHint::to_duration(&self.#field_name)
+                }
+            }
+        });
+
+        let transaction_id_mut = self.request.transaction_id_field().map(|field| {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+
+            quote! {
+                fn transaction_id_mut(&mut self) -> Option<&mut Option<String>> {
+                    Some(&mut self.#field_name)
+                }
+            }
+        });
+
+        let request_try_from_parts = if self.request.has_body_fields()
+            || self.request.newtype_body_field().is_some()
+            || self.request.newtype_raw_body_field().is_some()
+            || self.request.has_multipart_fields()
+        {
+            TokenStream::new()
+        } else {
+            quote! {
+                impl std::convert::TryFrom<ruma_api::exports::http::request::Parts>
+                    for #request_try_from_type
+                {
+                    type Error = ruma_api::error::FromHttpRequestError;
+
+                    /// Parses the request from the head of an HTTP request, without requiring its
+                    /// body, since this endpoint doesn't have any body fields.
+                    fn try_from(
+                        parts: ruma_api::exports::http::request::Parts,
+                    ) -> Result<Self, Self::Error> {
+                        Self::try_from_parts(&parts)?.complete(&[])
+                    }
+                }
+            }
+        };
+
+        let partial_init_fields = self.request.partial_init_fields();
+
+        let (check_content_length, check_body_size) = match self.metadata.max_body_size {
+            Some(limit) => {
+                let check_content_length = quote! {
+                    // Rejects requests announcing a body that is too large before it is read.
+                    if let Ok(Some(length)) = ruma_api::header::parse_optional::<u64>(
+                        &parts.headers,
+                        ruma_api::exports::http::header::CONTENT_LENGTH,
+                    ) {
+                        ruma_api::error::BodyTooLargeError::check(length, #limit)?;
+                    }
+                };
+                let check_body_size = quote! {
+                    if let Err(err) = ruma_api::error::BodyTooLargeError::check(
+                        body.len() as u64,
+                        #limit,
+                    ) {
+                        return Err(
+                            ruma_api::error::FromHttpRequestError::from(err)
+                                .with_endpoint_name(#name)
+                        );
+                    }
+                };
+
+                (check_content_length, check_body_size)
+            }
+            None => (TokenStream::new(), TokenStream::new()),
+        };
+
+        // Raw bodies are moved into the request instead of being copied from a slice when the
+        // whole `http::Request` is converted.
+        let (complete, complete_owned, try_from_complete) =
+            if self.request.newtype_raw_body_field().is_some() {
+                let complete = quote! {
+                    self.complete_owned(body.to_vec())
+                };
+                let complete_owned = quote! {
+                    fn complete_owned(
+                        self,
+                        body: Vec<u8>,
+                    ) -> Result<#request_try_from_type, ruma_api::error::FromHttpRequestError> {
+                        #check_body_size
+
+                        Ok(#request_try_from_type {
+                            #partial_init_fields
+                            #parse_request_body
+                        })
+                    }
+                };
+                // Errors of `try_from_parts` only have the head of the request, so the body is added here.
+                let try_from_complete = quote! {
+                    let partial = match #request_try_from_type::try_from_parts(&parts) {
+                        Ok(partial) => partial,
+                        Err(err) => return Err(err.with_http_request(parts, body)),
+                    };
+                    partial.complete_owned(body)
+                };
+
+                (complete, complete_owned, try_from_complete)
+            } else {
+                let complete = quote! {
+                    #check_body_size
+                    #extract_request_body
+
+                    Ok(#request_try_from_type {
+                        #partial_init_fields
+                        #parse_request_body
+                    })
+                };
+                let try_from_complete = quote! {
+                    // Errors only have the head or the body of the request, so the complete
+                    // request is added here.
+                    let result = match #request_try_from_type::try_from_parts(&parts) {
+                        Ok(partial) => partial.complete(&body),
+                        Err(err) => Err(err),
+                    };
+                    result.map_err(|err| err.with_http_request(parts, body))
+                };
+
+                (complete, TokenStream::new(), try_from_complete)
+            };
+
+        let matches_path = if self.request.path_default().is_some() {
+            // The last segment, which has a default, can be left out.
+            let (template_without_default, _) =
+                path_str.rsplit_once('/').expect("path needs to start with '/'");
+
+            quote! {
+                ruma_api::path::matches_template(#path, uri.path())
+                    || ruma_api::path::matches_template(#template_without_default, uri.path())
+            }
+        } else {
+            quote! {
+                ruma_api::path::matches_template(#path, uri.path())
+            }
+        };
+
+        let derive_default =
+            if self.metadata.default { quote!(#[derive(Default)]) } else { TokenStream::new() };
+        let derive_clone =
+            if self.metadata.clone { quote!(#[derive(Clone)]) } else { TokenStream::new() };
+        let derive_eq = if self.metadata.eq {
+            quote!(#[derive(PartialEq, Eq)])
+        } else if self.metadata.partial_eq {
+            quote!(#[derive(PartialEq)])
+        } else {
+            TokenStream::new()
+        };
+        // `Hash` is only useful together with `Eq`, so `hash` implies `eq` for the request.
+        let derive_request_eq = if self.metadata.hash {
+            quote!(#[derive(PartialEq, Eq, Hash)])
+        } else {
+            derive_eq.clone()
+        };
+
         let api = quote! {
             use ruma_api::exports::serde::de::Error as _;
             use ruma_api::exports::serde::Deserialize as _;
@@ -405,105 +1408,324 @@ impl ToTokens for Api {
             use std::convert::TryInto as _;
 
             #[doc = #request_doc]
+            #derive_default
+            #derive_request_eq
+            #derive_clone
             #request_type
 
             impl std::convert::TryFrom<ruma_api::exports::http::Request<Vec<u8>>> for #request_try_from_type {
                 type Error = ruma_api::error::FromHttpRequestError;
 
-                #[allow(unused_variables)]
                 fn try_from(request: ruma_api::exports::http::Request<Vec<u8>>) -> Result<Self, Self::Error> {
-                    #extract_request_path
-                    #extract_request_query
-                    #extract_request_headers
-                    #extract_request_body
+                    let (parts, body) = request.into_parts();
+                    #try_from_complete
+                }
+            }
 
-                    Ok(Self {
-                        #parse_request_path
-                        #parse_request_query
-                        #parse_request_headers
-                        #parse_request_body
-                    })
+            impl #request_try_from_type {
+                /// Parses the parts of the request that are not in the HTTP body, i.e. the path,
+                /// query string and headers.
+                ///
+                /// The body can be parsed afterwards with `PartialRequest::complete`, which allows
+                /// rejecting a request, e.g. because of failed authentication, before spending
+                /// time on deserializing its body.
+                pub fn try_from_parts(
+                    parts: &ruma_api::exports::http::request::Parts,
+                ) -> Result<PartialRequest, ruma_api::error::FromHttpRequestError> {
+                    #[allow(unused_variables)]
+                    fn parse(
+                        parts: &ruma_api::exports::http::request::Parts,
+                    ) -> Result<PartialRequest, ruma_api::error::FromHttpRequestError> {
+                        #check_content_length
+                        #extract_request_path
+                        #extract_request_query
+                        #extract_request_headers
+
+                        Ok(PartialRequest {
+                            #parse_request_path
+                            #parse_request_query
+                            #parse_request_headers
+                            #parse_multipart_boundary
+                        })
+                    }
+
+                    parse(parts).map_err(|err| err.with_endpoint_name(#name))
                 }
             }
 
+            impl PartialRequest {
+                /// Parses the given HTTP body and combines it with the other parts of the request.
+                #[allow(unused_variables)]
+                pub fn complete(
+                    self,
+                    body: &[u8],
+                ) -> Result<#request_try_from_type, ruma_api::error::FromHttpRequestError> {
+                    #complete
+                }
+
+                #complete_owned
+            }
+
+            #request_try_from_parts
+
+            /// Converts the request into an `http::Request` whose URI has a placeholder scheme and
+            /// authority.
+            ///
+            /// This is intended for server-side code and tests that only look at the path, query,
+            /// headers and body. Clients should use `Request::try_into_http_request` or
+            /// `Request::try_into_http_request_with_base_url` instead, which produce the absolute URI
+            /// of the actual server.
             impl std::convert::TryFrom<Request> for ruma_api::exports::http::Request<Vec<u8>> {
                 type Error = ruma_api::error::IntoHttpError;
 
-                #[allow(unused_mut, unused_variables)]
                 fn try_from(request: Request) -> Result<Self, Self::Error> {
-                    let metadata = Request::METADATA;
-
                     // Use dummy homeserver url which has to be overwritten in
                     // the calling code. Previously (with http::Uri) this was
                     // not required, but Url::parse only accepts absolute urls.
-                    let mut url =
-                        ruma_api::exports::url::Url::parse("http://invalid-host-please-change/")
-                            .unwrap();
+                    request.try_into_http_request_with_base_url(
+                        &ruma_api::exports::url::Url::parse("http://invalid-host-please-change/")
+                            .unwrap(),
+                    )
+                }
+            }
+
+            impl Request {
+                /// The HTTP method of this request's endpoint, the same as
+                /// `<Request as Endpoint>::METADATA.method`.
+                pub const METHOD: ruma_api::exports::http::Method =
+                    ruma_api::exports::http::Method::#method;
+
+                /// The path template of this request's endpoint, the same as
+                /// `<Request as Endpoint>::METADATA.path`.
+                pub const PATH: &'static str = #path;
+
+                /// The keys of the JSON object in the body of this request, in declaration order.
+                ///
+                /// Keys of flattened fields, like an `additional` field, are not included, and
+                /// the list is empty if the body is not a JSON object with regular fields.
+                pub const BODY_FIELDS: &'static [&'static str] = &[#(#request_body_keys),*];
+
+                /// Builds the URL of this request by appending the endpoint's path, with path
+                /// parameters filled in, to the given base URL and setting the query string.
+                ///
+                /// Any path of the base URL is kept as a prefix, e.g. a base URL of
+                /// `https://example.org/matrix` results in URLs like
+                /// `https://example.org/matrix/_matrix/client/...`. The query string and fragment
+                /// of the base URL are dropped.
+                pub fn url(
+                    &self,
+                    base_url: &ruma_api::exports::url::Url,
+                ) -> Result<ruma_api::exports::url::Url, ruma_api::error::IntoHttpError> {
+                    let request = self;
+                    let mut url = base_url.clone();
+                    url.set_query(None);
+                    url.set_fragment(None);
+
+                    { #url_set_path }
+                    { #url_set_borrowed_querystring }
+
+                    Ok(url)
+                }
+
+                /// The origin-form request target of this request, i.e. the path with path
+                /// parameters filled in, followed by the query string if there is one, like
+                /// `/_matrix/client/r0/rooms/!room:example.org/messages?dir=b`.
+                ///
+                /// This is what the `X-Matrix` signature of federation requests covers, and is
+                /// built without constructing an `http::Request`.
+                pub fn path_with_params(&self) -> Result<String, ruma_api::error::IntoHttpError> {
+                    let url = self.url(
+                        &ruma_api::exports::url::Url::parse("http://invalid-host-please-change/")
+                            .unwrap(),
+                    )?;
+
+                    Ok(url[ruma_api::exports::url::Position::BeforePath..].to_owned())
+                }
+
+                /// Converts this request into an `http::Request` for the server at `base_url`.
+                ///
+                /// Unlike the `TryFrom` conversion, this builds the full URL from the given base
+                /// URL (see `url`), and adds an `Authorization: Bearer` header with the given
+                /// access token if the endpoint requires authentication. Returns an error if
+                /// `base_url` is invalid, or if the endpoint requires authentication and no access
+                /// token is given.
+                pub fn try_into_http_request(
+                    self,
+                    base_url: &str,
+                    access_token: Option<&ruma_api::auth::AccessToken>,
+                ) -> Result<
+                    ruma_api::exports::http::Request<Vec<u8>>,
+                    ruma_api::error::IntoHttpError,
+                > {
+                    let base_url = ruma_api::exports::url::Url::parse(base_url)
+                        .map_err(ruma_api::error::IntoHttpError::invalid_base_url)?;
+                    let mut http_request = self.try_into_http_request_with_base_url(&base_url)?;
+
+                    if <Request as ruma_api::Endpoint>::METADATA.requires_authentication {
+                        if access_token.is_none() {
+                            return Err(ruma_api::error::IntoHttpError::missing_access_token());
+                        }
+
+                        ruma_api::auth::add_bearer_token(&mut http_request, access_token)?;
+                    }
+
+                    Ok(http_request)
+                }
+
+                /// Converts this request into an `http::Request` with an absolute URI for the server
+                /// at `base_url`.
+                ///
+                /// This is the client-side counterpart of the `TryFrom` conversion, which only
+                /// produces a placeholder scheme and authority. The URI is built like in `url`.
+                /// Unlike `try_into_http_request`, no `Authorization` header is added.
+                #[allow(unused_mut, unused_variables)]
+                pub fn try_into_http_request_with_base_url(
+                    self,
+                    base_url: &ruma_api::exports::url::Url,
+                ) -> Result<
+                    ruma_api::exports::http::Request<Vec<u8>>,
+                    ruma_api::error::IntoHttpError,
+                > {
+                    let request = self;
+                    let mut url = base_url.clone();
+                    url.set_query(None);
+                    url.set_fragment(None);
 
                     { #url_set_path }
                     { #url_set_querystring }
 
+                    #encode_multipart_body
+
                     let mut http_request = ruma_api::exports::http::Request::new(#request_body);
 
                     *http_request.method_mut() = ruma_api::exports::http::Method::#method;
-                    *http_request.uri_mut() = url.into_string().parse().unwrap();
+                    *http_request.uri_mut() = url.as_str().parse().unwrap();
+                    #set_multipart_content_type
 
                     { #add_headers_to_request }
 
                     Ok(http_request)
                 }
+
+                /// Checks that this request can be converted into an `http::Request`, without
+                /// serializing its body.
+                ///
+                /// This runs the fallible parts of the conversion that don't depend on the body,
+                /// i.e. building the URL with its path parameters and query string, and the
+                /// headers. Body serialization errors are not detected.
+                pub fn validate(&self) -> Result<(), ruma_api::error::IntoHttpError> {
+                    self.url(
+                        &ruma_api::exports::url::Url::parse("http://invalid-host-please-change/")
+                            .unwrap(),
+                    )?;
+
+                    { #validate_headers }
+
+                    Ok(())
+                }
+
+                /// Whether the path of the given URI matches the path template of this endpoint,
+                /// regardless of the values of path parameters.
+                ///
+                /// This is a cheap check for routing, before the request is parsed.
+                pub fn matches_path(uri: &ruma_api::exports::http::Uri) -> bool {
+                    #matches_path
+                }
+
+                /// The metadata of this request's endpoint, like `<Request as Endpoint>::METADATA`.
+                pub fn metadata(&self) -> &'static ruma_api::Metadata {
+                    static METADATA: ruma_api::Metadata = <Request as ruma_api::Endpoint>::METADATA;
+                    &METADATA
+                }
+            }
+
+            impl AsRef<ruma_api::Metadata> for Request {
+                fn as_ref(&self) -> &ruma_api::Metadata {
+                    self.metadata()
+                }
             }
 
             #[doc = #response_doc]
+            #derive_default
+            #derive_eq
+            #derive_clone
             #response_type
 
+            impl Response {
+                /// The keys of the JSON object in the body of this response, in declaration order.
+                ///
+                /// Keys of flattened fields, like an `additional` field, are not included, and
+                /// the list is empty if the body is not a JSON object with regular fields.
+                pub const BODY_FIELDS: &'static [&'static str] = &[#(#response_body_keys),*];
+            }
+
             impl std::convert::TryFrom<Response> for ruma_api::exports::http::Response<Vec<u8>> {
                 type Error = ruma_api::error::IntoHttpError;
 
-                #[allow(unused_variables)]
+                #[allow(unused_mut, unused_variables)]
                 fn try_from(response: Response) -> Result<Self, Self::Error> {
-                    let response = ruma_api::exports::http::Response::builder()
-                        .header(ruma_api::exports::http::header::CONTENT_TYPE, "application/json")
+                    let status = #response_status;
+                    let mut http_response = ruma_api::exports::http::Response::builder()
+                        .status(status)
+                        #default_content_type
                         #serialize_response_headers
                         .body(#body)
                         .unwrap();
-                    Ok(response)
+                    #append_response_headers
+                    Ok(http_response)
                 }
             }
 
             impl std::convert::TryFrom<ruma_api::exports::http::Response<Vec<u8>>> for #response_try_from_type {
-                type Error = ruma_api::error::FromHttpResponseError;
+                type Error = ruma_api::error::FromHttpResponseError<#error_ty>;
 
-                #[allow(unused_variables)]
+                // The closure's error type is the same as the one of `try_from` itself.
+                #[allow(unused_variables, clippy::result_large_err)]
                 fn try_from(
                     response: ruma_api::exports::http::Response<Vec<u8>>,
                 ) -> Result<Self, Self::Error> {
-                    if response.status().as_u16() < 400 {
-                        #extract_response_headers
+                    ruma_api::error::from_http_response(response, |response| {
+                        #parse_owned_response
+                    })
+                }
+            }
 
-                        #typed_response_body_decl
+            impl std::convert::TryFrom<&ruma_api::exports::http::Response<Vec<u8>>> for #response_try_from_type {
+                type Error = ruma_api::error::FromHttpResponseError<#error_ty>;
 
-                        Ok(Self {
-                            #response_init_fields
-                        })
-                    } else {
-                        Err(ruma_api::error::ServerError::new(response).into())
-                    }
+                #[allow(unused_variables, clippy::result_large_err)]
+                fn try_from(
+                    response: &ruma_api::exports::http::Response<Vec<u8>>,
+                ) -> Result<Self, Self::Error> {
+                    ruma_api::error::from_http_response_ref(response, |response| {
+                        #parse_borrowed_response
+                    })
                 }
             }
 
+            #response_error
+
             impl ruma_api::Endpoint for Request {
                 type Response = Response;
+                type EndpointError = #error_ty;
 
                 /// Metadata for the `#name` endpoint.
                 const METADATA: ruma_api::Metadata = ruma_api::Metadata {
                     description: #description,
-                    method: ruma_api::exports::http::Method::#method,
+                    method: Request::METHOD,
                     name: #name,
-                    path: #path,
+                    path: Request::PATH,
                     rate_limited: #rate_limited,
                     requires_authentication: #requires_authentication,
+                    status: ruma_api::exports::http::StatusCode::#status,
+                    appservice: #appservice,
+                    api: ruma_api::ApiKind::#api,
+                    added_in: #added_in,
+                    removed_in: #removed_in,
                 };
+
+                #suggested_timeout
+                #transaction_id_mut
             }
         };
 
@@ -518,6 +1740,7 @@ mod kw {
     custom_keyword!(metadata);
     custom_keyword!(request);
     custom_keyword!(response);
+    custom_keyword!(errors);
 }
 
 /// The entire `ruma_api!` macro structure directly as it appears in the source code..
@@ -528,11 +1751,18 @@ pub struct RawApi {
     pub request: RawRequest,
     /// The `response` section of the macro.
     pub response: RawResponse,
+    /// The optional `errors` section of the macro.
+    pub errors: Option<RawErrors>,
 }
 
 impl Parse for RawApi {
     fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
-        Ok(Self { metadata: input.parse()?, request: input.parse()?, response: input.parse()? })
+        Ok(Self {
+            metadata: input.parse()?,
+            request: input.parse()?,
+            response: input.parse()?,
+            errors: if input.peek(kw::errors) { Some(input.parse()?) } else { None },
+        })
     }
 }
 
@@ -579,18 +1809,51 @@ impl Parse for RawRequest {
 }
 
 pub struct RawResponse {
-    pub response_kw: kw::response,
     pub fields: Vec<Field>,
 }
 
+pub struct RawErrors {
+    pub entries: Vec<ErrorEntry>,
+}
+
+impl Parse for RawErrors {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        input.parse::<kw::errors>()?;
+        let entries;
+        braced!(entries in input);
+
+        Ok(Self {
+            entries: entries
+                .parse_terminated::<ErrorEntry, Token![,]>(ErrorEntry::parse)?
+                .into_iter()
+                .collect(),
+        })
+    }
+}
+
+/// An entry of the `errors` section, like `400 => ValidationError`.
+pub struct ErrorEntry {
+    pub status: LitInt,
+    pub ty: Type,
+}
+
+impl Parse for ErrorEntry {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let status = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let ty = input.parse()?;
+
+        Ok(Self { status, ty })
+    }
+}
+
 impl Parse for RawResponse {
     fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
-        let response_kw = input.parse::<kw::response>()?;
+        input.parse::<kw::response>()?;
         let fields;
         braced!(fields in input);
 
         Ok(Self {
-            response_kw,
             fields: fields
                 .parse_terminated::<Field, Token![,]>(Field::parse_named)?
                 .into_iter()
@@ -1,17 +1,42 @@
 //! Details of the `#[ruma_api(...)]` attributes.
 
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
 use syn::{
     parse::{Parse, ParseStream},
-    Ident, Token,
+    punctuated::Punctuated,
+    Ident, LitStr, Path, Token,
 };
 
-/// Like syn::MetaNameValue, but expects an identifier as the value. Also, we don't care about the
-/// the span of the equals sign, so we don't have the `eq_token` field from syn::MetaNameValue.
+/// Like syn::MetaNameValue, but expects an identifier, a path or a string literal as the value. Also, we
+/// don't care about the the span of the equals sign, so we don't have the `eq_token` field from
+/// syn::MetaNameValue.
 pub struct MetaNameValue {
     /// The part left of the equals sign
     pub name: Ident,
     /// The part right of the equals sign
-    pub value: Ident,
+    pub value: MetaValue,
+}
+
+/// The value of a name-value pair in a ruma_api attribute.
+pub enum MetaValue {
+    /// An identifier, like `CONTENT_TYPE` in `#[ruma_api(header = CONTENT_TYPE)]`
+    Ident(Ident),
+    /// A string literal, like `"x-foo"` in `#[ruma_api(header = "x-foo")]`
+    Str(LitStr),
+    /// A path with more than one segment, like `defaults::limit` in
+    /// `#[ruma_api(default = defaults::limit)]`
+    Path(Path),
+}
+
+impl ToTokens for MetaValue {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            MetaValue::Ident(ident) => ident.to_tokens(tokens),
+            MetaValue::Str(lit) => lit.to_tokens(tokens),
+            MetaValue::Path(path) => path.to_tokens(tokens),
+        }
+    }
 }
 
 /// Like syn::Meta, but only parses ruma_api attributes
@@ -23,14 +48,12 @@ pub enum Meta {
 }
 
 impl Meta {
-    /// Check if the given attribute is a ruma_api attribute. If it is, parse it.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the given attribute is a ruma_api attribute, but fails to parse.
-    pub fn from_attribute(attr: &syn::Attribute) -> syn::Result<Option<Self>> {
+    /// Check if the given attribute is a ruma_api attribute. If it is, parse the comma-separated
+    /// list of arguments in it, like `query, timeout_hint` in `#[ruma_api(query, timeout_hint)]`.
+    pub fn from_attribute(attr: &syn::Attribute) -> syn::Result<Option<Vec<Self>>> {
         if attr.path.is_ident("ruma_api") {
-            attr.parse_args().map(Some)
+            attr.parse_args_with(Punctuated::<Self, Token![,]>::parse_terminated)
+                .map(|metas| Some(metas.into_iter().collect()))
         } else {
             Ok(None)
         }
@@ -43,9 +66,87 @@ impl Parse for Meta {
 
         if input.peek(Token![=]) {
             let _ = input.parse::<Token![=]>();
-            Ok(Meta::NameValue(MetaNameValue { name: ident, value: input.parse()? }))
+            let value = if input.peek(LitStr) {
+                MetaValue::Str(input.parse()?)
+            } else {
+                let path: Path = input.parse()?;
+                match path.get_ident() {
+                    Some(ident) => MetaValue::Ident(ident.clone()),
+                    None => MetaValue::Path(path),
+                }
+            };
+
+            Ok(Meta::NameValue(MetaNameValue { name: ident, value }))
         } else {
             Ok(Meta::Word(ident))
         }
     }
 }
+
+/// The name of the header of a `#[ruma_api(header = ...)]` field.
+pub enum HeaderName {
+    /// A header name constant from `http::header`, like `CONTENT_TYPE`.
+    Const(Ident),
+    /// A custom header name, validated and converted to lowercase.
+    Custom(LitStr),
+}
+
+impl HeaderName {
+    /// Creates a `HeaderName` from the value of a `header` attribute, checking that string
+    /// literals are valid header names.
+    pub fn new(value: MetaValue) -> syn::Result<Self> {
+        match value {
+            MetaValue::Ident(ident) => Ok(HeaderName::Const(ident)),
+            MetaValue::Path(path) => Err(syn::Error::new_spanned(
+                path,
+                "expected a header name constant from `http::header` or a string literal",
+            )),
+            MetaValue::Str(lit) => {
+                let name = lit.value();
+
+                // See the definition of `token` in RFC 7230, section 3.2.6.
+                let is_valid = !name.is_empty()
+                    && name
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+                if !is_valid {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        format!("`{}` is not a valid header name", name),
+                    ));
+                }
+
+                Ok(HeaderName::Custom(LitStr::new(&name.to_ascii_lowercase(), lit.span())))
+            }
+        }
+    }
+
+    /// Whether this is the `Content-Type` header.
+    pub fn is_content_type(&self) -> bool {
+        match self {
+            HeaderName::Const(ident) => ident == "CONTENT_TYPE",
+            HeaderName::Custom(lit) => lit.value() == "content-type",
+        }
+    }
+
+    /// Whether this is the `Content-Range` header.
+    pub fn is_content_range(&self) -> bool {
+        match self {
+            HeaderName::Const(ident) => ident == "CONTENT_RANGE",
+            HeaderName::Custom(lit) => lit.value() == "content-range",
+        }
+    }
+}
+
+impl ToTokens for HeaderName {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let header_name = match self {
+            HeaderName::Const(ident) => quote!(ruma_api::exports::http::header::#ident),
+            HeaderName::Custom(lit) => {
+                quote!(ruma_api::exports::http::header::HeaderName::from_static(#lit))
+            }
+        };
+
+        header_name.to_tokens(tokens);
+    }
+}
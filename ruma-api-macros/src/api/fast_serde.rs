@@ -0,0 +1,236 @@
+//! Hand-written `Serialize` and `Deserialize` implementations for the body structs of endpoints
+//! with `fast: true` in their metadata.
+//!
+//! The generated code produces the same output as `#[derive(Serialize)]` on a struct without serde
+//! attributes, but skips the parts of the derived code that only exist to support those attributes
+//! and formats other than JSON.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt as _, Field, Ident};
+
+use crate::api::is_option;
+
+/// Checks that the given body fields are supported by the hand-written implementations.
+pub fn check_fields<'a>(fields: impl Iterator<Item = &'a Field>) -> syn::Result<()> {
+    for field in fields {
+        let unsupported_attr = field
+            .attrs
+            .iter()
+            .find(|attr| attr.path.is_ident("serde") || attr.path.is_ident("wrap_incoming"));
+
+        if let Some(attr) = unsupported_attr {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "body fields of `fast` endpoints can't have `serde` or `wrap_incoming` attributes",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Produces `Serialize` and `Deserialize` implementations for the struct `ident` with the given
+/// named fields.
+///
+/// Like the derived implementations, `Option` fields are left out when they are `None`, unless
+/// they are listed in `explicit_null_fields`. If `deny_unknown_fields` is set, deserialization
+/// fails on unknown fields like with `#[serde(deny_unknown_fields)]`.
+pub fn impl_serde(
+    ident: &Ident,
+    fields: &[&Field],
+    explicit_null_fields: &[Ident],
+    deny_unknown_fields: bool,
+) -> TokenStream {
+    let serialize = impl_serialize(ident, fields, explicit_null_fields);
+    let deserialize = impl_deserialize(ident, fields, deny_unknown_fields);
+
+    quote! {
+        #serialize
+        #deserialize
+    }
+}
+
+fn field_names<'a>(fields: &[&'a Field]) -> Vec<&'a Ident> {
+    fields
+        .iter()
+        .map(|field| field.ident.as_ref().expect("expected field to have an identifier"))
+        .collect()
+}
+
+fn impl_serialize(ident: &Ident, fields: &[&Field], explicit_null_fields: &[Ident]) -> TokenStream {
+    let ident_str = ident.to_string();
+    let field_count = fields.len();
+    let field_names = field_names(fields);
+
+    let serialize_fields = fields.iter().zip(&field_names).map(|(field, field_name)| {
+        let field_name_str = field_name.unraw().to_string();
+
+        if is_option(&field.ty) && !explicit_null_fields.contains(field_name) {
+            quote! {
+                if self.#field_name.is_some() {
+                    state.serialize_field(#field_name_str, &self.#field_name)?;
+                } else {
+                    state.skip_field(#field_name_str)?;
+                }
+            }
+        } else {
+            quote! {
+                state.serialize_field(#field_name_str, &self.#field_name)?;
+            }
+        }
+    });
+
+    quote! {
+        impl ruma_api::exports::serde::Serialize for #ident {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ruma_api::exports::serde::Serializer,
+            {
+                use ruma_api::exports::serde::ser::SerializeStruct as _;
+
+                let mut state = serializer.serialize_struct(#ident_str, #field_count)?;
+                #(#serialize_fields)*
+                state.end()
+            }
+        }
+    }
+}
+
+fn impl_deserialize(ident: &Ident, fields: &[&Field], deny_unknown_fields: bool) -> TokenStream {
+    let ident_str = ident.to_string();
+    let expecting = format!("struct {}", ident);
+    let field_names = field_names(fields);
+    let field_name_strs: Vec<_> = field_names.iter().map(|name| name.unraw().to_string()).collect();
+    let field_variants: Vec<_> =
+        (0..fields.len()).map(|i| format_ident!("Field{}", i, span = Span::call_site())).collect();
+    let field_types = fields.iter().map(|field| &field.ty);
+
+    let unwrap_fields = fields.iter().zip(&field_names).zip(&field_name_strs).map(
+        |((field, field_name), field_name_str)| {
+            if is_option(&field.ty) {
+                // Like the derived implementation, treat missing `Option` fields as `None`.
+                quote! {
+                    let #field_name = match #field_name {
+                        Some(value) => value,
+                        None => None,
+                    };
+                }
+            } else {
+                quote! {
+                    let #field_name = match #field_name {
+                        Some(value) => value,
+                        None => return Err(A::Error::missing_field(#field_name_str)),
+                    };
+                }
+            }
+        },
+    );
+
+    let (ignore_variant, unknown_field, ignore_arm) = if deny_unknown_fields {
+        (
+            TokenStream::new(),
+            quote! { return Err(E::unknown_field(value, FIELDS)) },
+            TokenStream::new(),
+        )
+    } else {
+        (
+            quote! { Ignore, },
+            quote! { Field::Ignore },
+            quote! {
+                Field::Ignore => {
+                    map.next_value::<ruma_api::exports::serde::de::IgnoredAny>()?;
+                }
+            },
+        )
+    };
+
+    quote! {
+        impl<'de> ruma_api::exports::serde::Deserialize<'de> for #ident {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: ruma_api::exports::serde::Deserializer<'de>,
+            {
+                use ruma_api::exports::serde::de::{Error as _, MapAccess, Visitor};
+
+                const FIELDS: &[&str] = &[#(#field_name_strs),*];
+
+                enum Field {
+                    #(#field_variants,)*
+                    #ignore_variant
+                }
+
+                impl<'de> ruma_api::exports::serde::Deserialize<'de> for Field {
+                    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                    where
+                        D: ruma_api::exports::serde::Deserializer<'de>,
+                    {
+                        struct FieldVisitor;
+
+                        impl<'de> Visitor<'de> for FieldVisitor {
+                            type Value = Field;
+
+                            fn expecting(
+                                &self,
+                                f: &mut std::fmt::Formatter<'_>,
+                            ) -> std::fmt::Result {
+                                f.write_str("field identifier")
+                            }
+
+                            fn visit_str<E>(self, value: &str) -> Result<Field, E>
+                            where
+                                E: ruma_api::exports::serde::de::Error,
+                            {
+                                Ok(match value {
+                                    #(#field_name_strs => Field::#field_variants,)*
+                                    _ => #unknown_field,
+                                })
+                            }
+                        }
+
+                        deserializer.deserialize_identifier(FieldVisitor)
+                    }
+                }
+
+                struct StructVisitor;
+
+                impl<'de> Visitor<'de> for StructVisitor {
+                    type Value = #ident;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+                    where
+                        A: MapAccess<'de>,
+                    {
+                        #(let mut #field_names: Option<#field_types> = None;)*
+
+                        while let Some(key) = map.next_key::<Field>()? {
+                            match key {
+                                #(
+                                    Field::#field_variants => {
+                                        if #field_names.is_some() {
+                                            return Err(A::Error::duplicate_field(
+                                                #field_name_strs,
+                                            ));
+                                        }
+                                        #field_names = Some(map.next_value()?);
+                                    }
+                                )*
+                                #ignore_arm
+                            }
+                        }
+
+                        #(#unwrap_fields)*
+
+                        Ok(#ident { #(#field_names),* })
+                    }
+                }
+
+                deserializer.deserialize_struct(#ident_str, FIELDS, StructVisitor)
+            }
+        }
+    }
+}
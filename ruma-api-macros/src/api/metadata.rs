@@ -2,7 +2,10 @@
 
 use std::convert::TryFrom;
 
-use syn::{Expr, ExprLit, ExprPath, Ident, Lit, LitBool, LitStr, Member};
+use proc_macro2::Span;
+use syn::{
+    parse_quote, Expr, ExprLit, ExprPath, Ident, Lit, LitBool, LitStr, Member, Type, TypePath,
+};
 
 use crate::api::RawMetadata;
 
@@ -20,8 +23,59 @@ pub struct Metadata {
     pub rate_limited: LitBool,
     /// The description field.
     pub requires_authentication: LitBool,
+    /// The status field, as the name of the corresponding `http::StatusCode` constant.
+    pub status: Ident,
+    /// Whether the status field was set explicitly.
+    pub has_explicit_status: bool,
+    /// The default field.
+    pub default: bool,
+    /// The partial_eq field.
+    pub partial_eq: bool,
+    /// The eq field.
+    pub eq: bool,
+    /// The clone field.
+    pub clone: bool,
+    /// The hash field.
+    pub hash: bool,
+    /// The appservice field.
+    pub appservice: LitBool,
+    /// The api field, as the name of an `ApiKind` variant.
+    pub api: Ident,
+    /// The added_in field.
+    pub added_in: Option<LitStr>,
+    /// The removed_in field.
+    pub removed_in: Option<LitStr>,
+    /// The canonical_json field.
+    pub canonical_json: bool,
+    /// The fast field.
+    pub fast: bool,
+    /// The deny_unknown_fields field.
+    pub deny_unknown_fields: bool,
+    /// The lossy_path_decoding field.
+    pub lossy_path_decoding: bool,
+    /// The max_body_size field.
+    pub max_body_size: Option<u64>,
+    /// The error field, defaulting to `ruma_api::MatrixError`.
+    pub error: Type,
 }
 
+/// The names of the `http::StatusCode` constants for all successful status codes.
+const SUCCESS_STATUS_CODES: &[(u16, &str)] = &[
+    (200, "OK"),
+    (201, "CREATED"),
+    (202, "ACCEPTED"),
+    (203, "NON_AUTHORITATIVE_INFORMATION"),
+    (204, "NO_CONTENT"),
+    (205, "RESET_CONTENT"),
+    (206, "PARTIAL_CONTENT"),
+    (207, "MULTI_STATUS"),
+    (208, "ALREADY_REPORTED"),
+    (226, "IM_USED"),
+];
+
+/// The names of the `ruma_api::ApiKind` variants.
+const API_KINDS: &[&str] = &["ClientServer", "ServerServer", "Media", "Identity", "AppService"];
+
 impl TryFrom<RawMetadata> for Metadata {
     type Error = syn::Error;
 
@@ -32,6 +86,22 @@ impl TryFrom<RawMetadata> for Metadata {
         let mut path = None;
         let mut rate_limited = None;
         let mut requires_authentication = None;
+        let mut status = None;
+        let mut default = None;
+        let mut partial_eq = None;
+        let mut clone = None;
+        let mut eq = None;
+        let mut hash = None;
+        let mut appservice = None;
+        let mut api = None;
+        let mut added_in = None;
+        let mut removed_in = None;
+        let mut canonical_json = None;
+        let mut fast = None;
+        let mut deny_unknown_fields = None;
+        let mut lossy_path_decoding = None;
+        let mut max_body_size = None;
+        let mut error = None;
 
         for field_value in raw.field_values {
             let identifier = match field_value.member.clone() {
@@ -77,6 +147,123 @@ impl TryFrom<RawMetadata> for Metadata {
                     }
                     _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
                 },
+                "status" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Int(literal), .. }) => {
+                        let code = literal.base10_parse::<u16>()?;
+                        let name = SUCCESS_STATUS_CODES
+                            .iter()
+                            .find(|(c, _)| *c == code)
+                            .map(|(_, name)| *name)
+                            .ok_or_else(|| {
+                                syn::Error::new_spanned(
+                                    &literal,
+                                    "expected a successful (2xx) HTTP status code",
+                                )
+                            })?;
+
+                        status = Some(Ident::new(name, Span::call_site()));
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected an integer literal")),
+                },
+                "appservice" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        appservice = Some(literal);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "api" => {
+                    match expr {
+                        Expr::Path(ExprPath { ref path, .. })
+                            if path.segments.len() == 1
+                                && API_KINDS.iter().any(|kind| path.segments[0].ident == kind) =>
+                        {
+                            api = Some(path.segments[0].ident.clone());
+                        }
+                        _ => return Err(syn::Error::new_spanned(
+                            expr,
+                            "expected one of `ClientServer`, `ServerServer`, `Media`, `Identity`, \
+                             `AppService`",
+                        )),
+                    }
+                }
+                "added_in" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Str(literal), .. }) => {
+                        added_in = Some(literal);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a string literal")),
+                },
+                "removed_in" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Str(literal), .. }) => {
+                        removed_in = Some(literal);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a string literal")),
+                },
+                "default" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        default = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "partial_eq" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        partial_eq = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "clone" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        clone = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "eq" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        eq = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "hash" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        hash = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "canonical_json" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        canonical_json = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "fast" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        fast = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "deny_unknown_fields" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        deny_unknown_fields = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "lossy_path_decoding" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        lossy_path_decoding = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "max_body_size" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Int(literal), .. }) => {
+                        max_body_size = Some(literal.base10_parse::<u64>()?);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected an integer literal")),
+                },
+                "error" => match expr {
+                    Expr::Path(ExprPath { path, qself, .. }) => {
+                        error = Some(Type::Path(TypePath { qself, path }));
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a type path")),
+                },
                 _ => return Err(syn::Error::new_spanned(field_value, "unexpected field")),
             }
         }
@@ -93,6 +280,24 @@ impl TryFrom<RawMetadata> for Metadata {
             rate_limited: rate_limited.ok_or_else(|| missing_field("rate_limited"))?,
             requires_authentication: requires_authentication
                 .ok_or_else(|| missing_field("requires_authentication"))?,
+            has_explicit_status: status.is_some(),
+            status: status.unwrap_or_else(|| Ident::new("OK", Span::call_site())),
+            default: default.unwrap_or(false),
+            partial_eq: partial_eq.unwrap_or(false),
+            eq: eq.unwrap_or(false),
+            clone: clone.unwrap_or(true),
+            hash: hash.unwrap_or(false),
+            appservice: appservice
+                .unwrap_or_else(|| LitBool { value: false, span: Span::call_site() }),
+            api: api.unwrap_or_else(|| Ident::new("ClientServer", Span::call_site())),
+            added_in,
+            removed_in,
+            canonical_json: canonical_json.unwrap_or(false),
+            fast: fast.unwrap_or(false),
+            deny_unknown_fields: deny_unknown_fields.unwrap_or(false),
+            lossy_path_decoding: lossy_path_decoding.unwrap_or(false),
+            max_body_size,
+            error: error.unwrap_or_else(|| parse_quote!(ruma_api::MatrixError)),
         })
     }
 }
@@ -2,12 +2,14 @@
 
 use std::{convert::TryFrom, mem};
 
-use proc_macro2::TokenStream;
-use quote::{quote, quote_spanned, ToTokens};
-use syn::{spanned::Spanned, Field, Ident};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{ext::IdentExt as _, parse_quote, spanned::Spanned, Field, Ident, LitStr};
 
 use crate::api::{
-    attribute::{Meta, MetaNameValue},
+    attribute::{HeaderName, Meta, MetaNameValue, MetaValue},
+    fast_serde, field_name, has_serde_attr, impl_debug_with_redaction, is_duration,
+    is_nested_option, is_option, is_vec, option_inner_type, skip_serializing_none,
     strip_serde_attrs, RawRequest,
 };
 
@@ -15,11 +17,59 @@ use crate::api::{
 pub struct Request {
     /// The fields of the request.
     fields: Vec<RequestField>,
+    /// The names of the fields that are left out of the request's `Debug` output.
+    sensitive_fields: Vec<Ident>,
+    /// The names of the `Option` body fields that are serialized as `null` when they are `None`.
+    explicit_null_fields: Vec<Ident>,
+    /// Whether to use hand-written serde implementations for the body struct.
+    fast_serde: bool,
+    /// Whether to reject unknown fields in the request body.
+    deny_unknown_fields: bool,
+    /// The field with the `timeout_hint` attribute.
+    timeout_hint_field: Option<Field>,
+    /// The field with the `transaction_id` attribute.
+    transaction_id_field: Option<Field>,
+    /// The field with the `path_default` attribute, and the default path segment.
+    path_default: Option<(Field, LitStr)>,
+    /// The path fields with the `comma` attribute.
+    comma_separated_fields: Vec<Field>,
+    /// The `prefix` of the query map field, if it has one.
+    query_map_prefix: Option<LitStr>,
 }
 
 impl Request {
+    /// Switches the body struct to hand-written serde implementations, for endpoints with
+    /// `fast: true` in their metadata.
+    pub fn enable_fast_serde(&mut self) -> syn::Result<()> {
+        fast_serde::check_fields(self.body_fields())?;
+        self.fast_serde = true;
+        Ok(())
+    }
+
+    /// Makes the body struct reject unknown fields, for endpoints with `deny_unknown_fields: true`
+    /// in their metadata.
+    pub fn enable_deny_unknown_fields(&mut self) -> syn::Result<()> {
+        for field in self.body_fields() {
+            if has_serde_attr(field, &["flatten"])
+                || field.attrs.iter().any(|attr| attr.path.is_ident("wrap_incoming"))
+            {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "body fields of `deny_unknown_fields` endpoints can't be flattened or have \
+                     `wrap_incoming` attributes",
+                ));
+            }
+        }
+
+        self.deny_unknown_fields = true;
+        Ok(())
+    }
+
     /// Produces code to add necessary HTTP headers to an `http::Request`.
-    pub fn add_headers_to_request(&self) -> TokenStream {
+    ///
+    /// If `borrowed` is set, `request` is a reference to the request, so the value of the
+    /// header_map field is cloned instead of being moved out.
+    pub fn add_headers_to_request(&self, borrowed: bool) -> TokenStream {
         let append_stmts = self.header_fields().map(|request_field| {
             let (field, header_name) = match request_field {
                 RequestField::Header(field, header_name) => (field, header_name),
@@ -28,17 +78,63 @@ impl Request {
 
             let field_name = &field.ident;
 
+            if is_option(&field.ty) {
+                quote! {
+                    if let Some(value) = &request.#field_name {
+                        headers.append(#header_name, ruma_api::header::to_value(value)?);
+                    }
+                }
+            } else {
+                quote! {
+                    headers.append(
+                        #header_name,
+                        ruma_api::header::to_value(&request.#field_name)?,
+                    );
+                }
+            }
+        });
+
+        let append_map = self.header_map_field().map(|field| {
+            let field_name = &field.ident;
+            let field_type = &field.ty;
+            let header_names = self.header_fields().map(|request_field| match request_field {
+                RequestField::Header(_, header_name) => header_name,
+                _ => unreachable!("expected request field to be header variant"),
+            });
+            let header_map = if borrowed {
+                quote!(::std::clone::Clone::clone(&request.#field_name))
+            } else {
+                quote!(request.#field_name)
+            };
+
             quote! {
-                headers.append(
-                    ruma_api::exports::http::header::#header_name,
-                    ruma_api::exports::http::header::HeaderValue::from_str(request.#field_name.as_ref())
-                        .expect("failed to convert value into HeaderValue"),
-                );
+                // Gives a proper compile error when the header_map field's type has the wrong
+                // item type, instead of a confusing one from inside the loop below.
+                fn assert_trait_impl<T>()
+                where
+                    T: std::iter::IntoIterator<
+                        Item = (ruma_api::exports::http::header::HeaderName, std::string::String),
+                    >,
+                {}
+                assert_trait_impl::<#field_type>();
+
+                for (name, value) in #header_map {
+                    // Dedicated header fields take precedence over entries of the header map.
+                    if ruma_api::header::is_hop_by_hop(&name) #(|| name == #header_names)* {
+                        continue;
+                    }
+
+                    headers.append(
+                        name,
+                        ruma_api::exports::http::header::HeaderValue::from_str(&value)?,
+                    );
+                }
             }
         });
 
         quote! {
             #(#append_stmts)*
+            #append_map
         }
     }
 
@@ -51,29 +147,50 @@ impl Request {
             };
 
             let field_name = &field.ident;
-            let header_name_string = header_name.to_string();
+            let parse_fn =
+                if is_option(&field.ty) { quote!(parse_optional) } else { quote!(parse_required) };
 
             quote! {
-                #field_name: match headers.get(ruma_api::exports::http::header::#header_name)
-                    .and_then(|v| v.to_str().ok()) {
-                        Some(header) => header.to_owned(),
-                        None => {
-                            return Err(
-                                ruma_api::error::RequestDeserializationError::new(
-                                    ruma_api::exports::serde_json::Error::missing_field(
-                                        #header_name_string
-                                    ),
-                                    request,
-                                )
-                                .into()
-                            );
-                        }
+                #field_name: match ruma_api::header::#parse_fn(
+                    headers,
+                    #header_name,
+                ) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        return Err(ruma_api::error::RequestDeserializationError::from_parts(
+                            err,
+                            parts,
+                        )
+                        .into());
                     }
+                }
+            }
+        });
+
+        let map_field = self.header_map_field().map(|field| {
+            let field_name = &field.ident;
+            let header_names = self.header_fields().map(|request_field| match request_field {
+                RequestField::Header(_, header_name) => header_name,
+                _ => panic!("expected request field to be header variant"),
+            });
+
+            quote! {
+                #field_name: headers
+                    .iter()
+                    .filter(|(name, _)| {
+                        !ruma_api::header::is_hop_by_hop(name)
+                            #(&& *name != #header_names)*
+                    })
+                    .filter_map(|(name, value)| {
+                        value.to_str().ok().map(|value| (name.clone(), value.to_owned()))
+                    })
+                    .collect(),
             }
         });
 
         quote! {
             #(#fields,)*
+            #map_field
         }
     }
 
@@ -84,7 +201,7 @@ impl Request {
 
     /// Whether or not this request has any data in HTTP headers.
     pub fn has_header_fields(&self) -> bool {
-        self.fields.iter().any(|field| field.is_header())
+        self.fields.iter().any(|field| field.is_header() || field.is_header_map())
     }
 
     /// Whether or not this request has any data in the URL path.
@@ -92,9 +209,39 @@ impl Request {
         self.fields.iter().any(|field| field.is_path())
     }
 
-    /// Whether or not this request has any data in the query string.
+    /// Whether or not this request has any query fields besides a query map field without a
+    /// prefix.
     pub fn has_query_fields(&self) -> bool {
         self.fields.iter().any(|field| field.is_query())
+            || self.prefixed_query_map_field().is_some()
+    }
+
+    /// Whether or not this request has query fields that are serialized through the
+    /// `RequestQuery` struct.
+    pub fn has_query_struct_fields(&self) -> bool {
+        self.fields.iter().any(|field| field.as_query_struct_field().is_some())
+    }
+
+    /// Produces an iterator over the `Vec` query fields, which are sent as one query parameter
+    /// per element.
+    pub fn repeated_query_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter_map(RequestField::as_repeated_query_field)
+    }
+
+    /// Produces an iterator over the query fields with the `flatten` attribute, whose own fields
+    /// are sent as top-level query parameters.
+    pub fn flattened_query_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter_map(RequestField::as_flattened_query_field)
+    }
+
+    /// Produces an iterator over the fields that are sent as parts of a `multipart/form-data` body.
+    pub fn multipart_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter_map(|field| field.field_of_kind(RequestFieldKind::Multipart))
+    }
+
+    /// Whether or not this request has a `multipart/form-data` body.
+    pub fn has_multipart_fields(&self) -> bool {
+        self.multipart_fields().next().is_some()
     }
 
     /// Produces an iterator over all the body fields.
@@ -127,9 +274,45 @@ impl Request {
         self.fields.iter().find_map(RequestField::as_newtype_raw_body_field)
     }
 
-    /// Returns the query map field.
+    /// Returns the query map field if it has no prefix, i.e. holds the whole query string.
     pub fn query_map_field(&self) -> Option<&Field> {
-        self.fields.iter().find_map(RequestField::as_query_map_field)
+        self.fields
+            .iter()
+            .find_map(RequestField::as_query_map_field)
+            .filter(|_| self.query_map_prefix.is_none())
+    }
+
+    /// Returns the query map field with a `prefix`, and the prefix.
+    pub fn prefixed_query_map_field(&self) -> Option<(&Field, &LitStr)> {
+        let prefix = self.query_map_prefix.as_ref()?;
+        self.fields.iter().find_map(RequestField::as_query_map_field).map(|field| (field, prefix))
+    }
+
+    /// Returns the field with the `timeout_hint` attribute.
+    pub fn timeout_hint_field(&self) -> Option<&Field> {
+        self.timeout_hint_field.as_ref()
+    }
+
+    /// Returns the field with the `transaction_id` attribute.
+    pub fn transaction_id_field(&self) -> Option<&Field> {
+        self.transaction_id_field.as_ref()
+    }
+
+    /// Returns the field with the `path_default` attribute, and the default path segment.
+    pub fn path_default(&self) -> Option<(&Field, &LitStr)> {
+        self.path_default.as_ref().map(|(field, default)| (field, default))
+    }
+
+    /// Whether the path field with the given name has the `comma` attribute.
+    pub fn is_comma_separated(&self, path_var: &str) -> bool {
+        self.comma_separated_fields
+            .iter()
+            .any(|field| matches!(&field.ident, Some(ident) if ident == path_var))
+    }
+
+    /// Returns the header map field.
+    pub fn header_map_field(&self) -> Option<&Field> {
+        self.fields.iter().find_map(RequestField::as_header_map_field)
     }
 
     /// Produces code for a struct initializer for body fields on a variable named `request`.
@@ -137,14 +320,50 @@ impl Request {
         self.struct_init_fields(RequestFieldKind::Body, quote!(request))
     }
 
-    /// Produces code for a struct initializer for path fields on a variable named `request`.
-    pub fn request_path_init_fields(&self) -> TokenStream {
-        self.struct_init_fields(RequestFieldKind::Path, quote!(request))
+    /// Produces code for a struct initializer for query string fields on a variable named
+    /// `request`.
+    ///
+    /// If `borrowed` is set, `request` is a reference to the request, so the fields are cloned
+    /// instead of being moved out.
+    pub fn request_query_init_fields(&self, borrowed: bool) -> TokenStream {
+        let fields =
+            self.fields.iter().filter_map(RequestField::as_query_struct_field).map(|field| {
+                let field_name =
+                    field.ident.as_ref().expect("expected field to have an identifier");
+                let span = field.span();
+
+                if borrowed {
+                    quote_spanned! {span=>
+                        #field_name: ::std::clone::Clone::clone(&request.#field_name)
+                    }
+                } else {
+                    quote_spanned! {span=>
+                        #field_name: request.#field_name
+                    }
+                }
+            });
+
+        quote! { #(#fields,)* }
+    }
+
+    /// Produces code for a struct initializer for all fields that are not part of the HTTP body,
+    /// from the `PartialRequest` `self`.
+    pub fn partial_init_fields(&self) -> TokenStream {
+        let fields = self.partial_fields().map(|field| {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let span = field.span();
+
+            quote_spanned! {span=>
+                #field_name: self.#field_name
+            }
+        });
+
+        quote! { #(#fields,)* }
     }
 
-    /// Produces code for a struct initializer for query string fields on a variable named `request`.
-    pub fn request_query_init_fields(&self) -> TokenStream {
-        self.struct_init_fields(RequestFieldKind::Query, quote!(request))
+    /// Produces an iterator over all fields that are not part of the HTTP body.
+    fn partial_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter(|field| !field.is_in_body()).map(RequestField::field)
     }
 
     /// Produces code for a struct initializer for body fields on a variable named `request_body`.
@@ -153,9 +372,42 @@ impl Request {
     }
 
     /// Produces code for a struct initializer for query string fields on a variable named
-    /// `request_query`.
+    /// `request_query`, and on the variables holding the values of `Vec` query fields.
     pub fn request_init_query_fields(&self) -> TokenStream {
-        self.struct_init_fields(RequestFieldKind::Query, quote!(request_query))
+        let fields = self.fields.iter().filter(|field| field.is_query()).map(|request_field| {
+            let field = request_field.field();
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let span = field.span();
+
+            if request_field.kind() == RequestFieldKind::FlattenedQuery {
+                let var = flattened_query_var(field);
+                quote_spanned! {span=>
+                    #field_name: #var
+                }
+            } else if is_vec(&field.ty) {
+                let var = repeated_query_var(field);
+                quote_spanned! {span=>
+                    #field_name: #var
+                }
+            } else {
+                quote_spanned! {span=>
+                    #field_name: request_query.#field_name
+                }
+            }
+        });
+        let prefixed_query_map = self.prefixed_query_map_field().map(|(field, _)| {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let var = prefixed_query_map_var(field);
+
+            quote! {
+                #field_name: #var,
+            }
+        });
+
+        quote! {
+            #(#fields,)*
+            #prefixed_query_map
+        }
     }
 
     /// Produces code for a struct initializer for the given field kind to be accessed through the
@@ -186,7 +438,17 @@ impl TryFrom<RawRequest> for Request {
 
     fn try_from(raw: RawRequest) -> syn::Result<Self> {
         let mut newtype_body_field = None;
+        let mut additional_field = None;
+        let mut timeout_hint_field = None;
+        let mut transaction_id_field = None;
+        let mut path_default = None;
         let mut query_map_field = None;
+        let mut header_map_field = None;
+        let mut comma_separated_fields = Vec::new();
+        let mut query_map_prefix = None;
+
+        let mut sensitive_fields = Vec::new();
+        let mut explicit_null_fields = Vec::new();
 
         let fields = raw
             .fields
@@ -194,9 +456,14 @@ impl TryFrom<RawRequest> for Request {
             .map(|mut field| {
                 let mut field_kind = None;
                 let mut header = None;
-
-                for attr in mem::replace(&mut field.attrs, Vec::new()) {
-                    let meta = match Meta::from_attribute(&attr)? {
+                let mut is_additional = false;
+                let mut default_fn = None;
+                let mut is_flattened = false;
+                let mut is_comma_separated = false;
+                let mut prefix = None;
+
+                for attr in mem::take(&mut field.attrs) {
+                    let metas = match Meta::from_attribute(&attr)? {
                         Some(m) => m,
                         None => {
                             field.attrs.push(attr);
@@ -204,101 +471,446 @@ impl TryFrom<RawRequest> for Request {
                         }
                     };
 
-                    if field_kind.is_some() {
-                        return Err(syn::Error::new_spanned(
-                            attr,
-                            "There can only be one field kind attribute",
-                        ));
-                    }
+                    for meta in metas {
+                        if let Meta::Word(ident) = &meta {
+                            if ident == "additional" {
+                                if let Some(f) = &additional_field {
+                                    let mut error = syn::Error::new_spanned(
+                                        field,
+                                        "There can only be one `additional` field",
+                                    );
+                                    error.combine(syn::Error::new_spanned(
+                                        f,
+                                        "Previous `additional` field",
+                                    ));
+                                    return Err(error);
+                                }
+
+                                additional_field = Some(field.clone());
+                                is_additional = true;
+                                field.attrs.push(parse_quote!(#[serde(flatten)]));
+                                continue;
+                            }
 
-                    field_kind = Some(match meta {
-                        Meta::Word(ident) => {
-                            match &ident.to_string()[..] {
-                                s @ "body" | s @ "raw_body" => {
-                                    if let Some(f) = &newtype_body_field {
-                                        let mut error = syn::Error::new_spanned(
-                                            field,
-                                            "There can only be one newtype body field",
-                                        );
-                                        error.combine(syn::Error::new_spanned(
-                                            f,
-                                            "Previous newtype body field",
+                            if ident == "timeout_hint" {
+                                if let Some(f) = &timeout_hint_field {
+                                    let mut error = syn::Error::new_spanned(
+                                        field,
+                                        "There can only be one `timeout_hint` field",
+                                    );
+                                    error.combine(syn::Error::new_spanned(
+                                        f,
+                                        "Previous `timeout_hint` field",
+                                    ));
+                                    return Err(error);
+                                }
+
+                                timeout_hint_field = Some(field.clone());
+                                continue;
+                            }
+
+                            if ident == "transaction_id" {
+                                if let Some(f) = &transaction_id_field {
+                                    let mut error = syn::Error::new_spanned(
+                                        field,
+                                        "There can only be one `transaction_id` field",
+                                    );
+                                    error.combine(syn::Error::new_spanned(
+                                        f,
+                                        "Previous `transaction_id` field",
+                                    ));
+                                    return Err(error);
+                                }
+
+                                transaction_id_field = Some(field.clone());
+                                continue;
+                            }
+
+                            if ident == "flatten" {
+                                is_flattened = true;
+                                continue;
+                            }
+
+                            if ident == "comma" {
+                                is_comma_separated = true;
+                                continue;
+                            }
+
+                            if ident == "sensitive" {
+                                sensitive_fields.push(
+                                    field
+                                        .ident
+                                        .clone()
+                                        .expect("expected field to have an identifier"),
+                                );
+                                continue;
+                            }
+
+                            if ident == "explicit_null" {
+                                if !is_option(&field.ty) {
+                                    return Err(syn::Error::new_spanned(
+                                        field,
+                                        "`explicit_null` can only be used on `Option` body fields",
+                                    ));
+                                }
+
+                                explicit_null_fields.push(
+                                    field
+                                        .ident
+                                        .clone()
+                                        .expect("expected field to have an identifier"),
+                                );
+                                continue;
+                            }
+                        }
+
+                        if let Meta::NameValue(MetaNameValue { name, value }) = &meta {
+                            if name == "path_default" {
+                                let default = match value {
+                                    MetaValue::Str(lit) => lit.clone(),
+                                    _ => {
+                                        return Err(syn::Error::new_spanned(
+                                            value,
+                                            "`path_default` expects a string literal",
                                         ));
-                                        return Err(error);
                                     }
+                                };
+
+                                if let Some((f, _)) = &path_default {
+                                    let mut error = syn::Error::new_spanned(
+                                        field,
+                                        "There can only be one `path_default` field",
+                                    );
+                                    error.combine(syn::Error::new_spanned(
+                                        f,
+                                        "Previous `path_default` field",
+                                    ));
+                                    return Err(error);
+                                }
+
+                                path_default = Some((field.clone(), default));
+                                continue;
+                            }
 
-                                    newtype_body_field = Some(field.clone());
-                                    match s {
-                                        "body" => RequestFieldKind::NewtypeBody,
-                                        "raw_body" => RequestFieldKind::NewtypeRawBody,
-                                        _ => unreachable!(),
+                            if name == "default" {
+                                let path = match value {
+                                    MetaValue::Str(_) => {
+                                        return Err(syn::Error::new_spanned(
+                                            value,
+                                            "`default` expects the path of a function",
+                                        ));
                                     }
-                                }
-                                "path" => RequestFieldKind::Path,
-                                "query" => RequestFieldKind::Query,
-                                "query_map" => {
-                                    if let Some(f) = &query_map_field {
-                                        let mut error = syn::Error::new_spanned(
-                                            field,
-                                            "There can only be one query map field",
-                                        );
-                                        error.combine(syn::Error::new_spanned(
-                                            f,
-                                            "Previous query map field",
+                                    _ => value.to_token_stream().to_string().replace(' ', ""),
+                                };
+
+                                default_fn = Some(LitStr::new(&path, value.span()));
+                                continue;
+                            }
+
+                            if name == "prefix" {
+                                match value {
+                                    MetaValue::Str(lit) if !lit.value().is_empty() => {
+                                        prefix = Some(lit.clone());
+                                    }
+                                    _ => {
+                                        return Err(syn::Error::new_spanned(
+                                            value,
+                                            "`prefix` expects a non-empty string literal",
                                         ));
-                                        return Err(error);
                                     }
+                                }
+                                continue;
+                            }
+                        }
 
-                                    query_map_field = Some(field.clone());
-                                    RequestFieldKind::QueryMap
-                                },
-                                _ => {
+                        if field_kind.is_some() {
+                            return Err(syn::Error::new_spanned(
+                                attr,
+                                "There can only be one field kind attribute",
+                            ));
+                        }
+
+                        field_kind = Some(match meta {
+                            Meta::Word(ident) => {
+                                match &ident.to_string()[..] {
+                                    s @ "body" | s @ "json_body" | s @ "raw_body" => {
+                                        if let Some(f) = &newtype_body_field {
+                                            let name = field_name(&field);
+                                            let mut error = syn::Error::new_spanned(
+                                                &field,
+                                                format!(
+                                                    "There can only be one newtype body field, \
+                                                     but `{}` and `{}` are both marked as one",
+                                                    field_name(f),
+                                                    name,
+                                                ),
+                                            );
+                                            error.combine(syn::Error::new_spanned(
+                                                f,
+                                                format!(
+                                                    "Previous newtype body field `{}`",
+                                                    field_name(f),
+                                                ),
+                                            ));
+                                            return Err(error);
+                                        }
+
+                                        newtype_body_field = Some(field.clone());
+                                        match s {
+                                            // `json_body` is an alias of `body` for
+                                            // `serde_json::Value` fields.
+                                            "body" | "json_body" => RequestFieldKind::NewtypeBody,
+                                            "raw_body" => RequestFieldKind::NewtypeRawBody,
+                                            _ => unreachable!(),
+                                        }
+                                    }
+                                    "path" => RequestFieldKind::Path,
+                                    "multipart" => RequestFieldKind::Multipart,
+                                    "query" => RequestFieldKind::Query,
+                                    "query_map" => {
+                                        if let Some(f) = &query_map_field {
+                                            let mut error = syn::Error::new_spanned(
+                                                field,
+                                                "There can only be one query map field",
+                                            );
+                                            error.combine(syn::Error::new_spanned(
+                                                f,
+                                                "Previous query map field",
+                                            ));
+                                            return Err(error);
+                                        }
+
+                                        query_map_field = Some(field.clone());
+                                        RequestFieldKind::QueryMap
+                                    }
+                                    "header_map" => {
+                                        if let Some(f) = &header_map_field {
+                                            let mut error = syn::Error::new_spanned(
+                                                field,
+                                                "There can only be one header map field",
+                                            );
+                                            error.combine(syn::Error::new_spanned(
+                                                f,
+                                                "Previous header map field",
+                                            ));
+                                            return Err(error);
+                                        }
+
+                                        header_map_field = Some(field.clone());
+                                        RequestFieldKind::HeaderMap
+                                    }
+                                    _ => {
+                                        return Err(syn::Error::new_spanned(
+                                            ident,
+                                            "Invalid #[ruma_api] argument, expected one of \
+                                             `body`, `json_body`, `raw_body`, `multipart`, \
+                                             `path`, `query`, `query_map`, `header_map`, \
+                                             `additional`, `sensitive`, `explicit_null`, \
+                                             `timeout_hint`, `transaction_id`, `flatten`, \
+                                             `comma`",
+                                        ));
+                                    }
+                                }
+                            }
+                            Meta::NameValue(MetaNameValue { name, value }) => {
+                                if name != "header" {
                                     return Err(syn::Error::new_spanned(
-                                        ident,
-                                        "Invalid #[ruma_api] argument, expected one of `body`, `path`, `query`, `query_map`",
+                                        name,
+                                        "Invalid #[ruma_api] argument with value, expected \
+                                         `header`, `path_default`, `default` or `prefix`",
                                     ));
                                 }
+
+                                header = Some(HeaderName::new(value)?);
+                                RequestFieldKind::Header
                             }
-                        }
-                        Meta::NameValue(MetaNameValue { name, value }) => {
-                            if name != "header" {
-                                return Err(syn::Error::new_spanned(
-                                    name,
-                                    "Invalid #[ruma_api] argument with value, expected `header`"
-                                ));
-                            }
+                        });
+                    }
+                }
 
-                            header = Some(value);
-                            RequestFieldKind::Header
-                        }
-                    });
+                if is_additional && field_kind.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        field,
+                        "An `additional` field is part of the JSON body and can't have a field \
+                         kind attribute",
+                    ));
                 }
 
-                Ok(RequestField::new(
-                    field_kind.unwrap_or(RequestFieldKind::Body),
-                    field,
-                    header,
-                ))
+                let mut field_kind = field_kind.unwrap_or(RequestFieldKind::Body);
+
+                if is_flattened {
+                    if field_kind != RequestFieldKind::Query || is_vec(&field.ty) {
+                        return Err(syn::Error::new_spanned(
+                            field,
+                            "`flatten` can only be used on non-`Vec` query fields",
+                        ));
+                    }
+
+                    field_kind = RequestFieldKind::FlattenedQuery;
+                }
+
+                if let Some(prefix) = prefix {
+                    if field_kind != RequestFieldKind::QueryMap {
+                        return Err(syn::Error::new_spanned(
+                            field,
+                            "`prefix` can only be used on `query_map` fields",
+                        ));
+                    }
+
+                    query_map_prefix = Some(prefix);
+                }
+
+                if is_comma_separated {
+                    if field_kind != RequestFieldKind::Path || !is_vec(&field.ty) {
+                        return Err(syn::Error::new_spanned(
+                            field,
+                            "`comma` can only be used on path fields of a `Vec` type",
+                        ));
+                    }
+
+                    comma_separated_fields.push(field.clone());
+                }
+
+                if field_kind == RequestFieldKind::Query {
+                    if is_nested_option(&field.ty) {
+                        field.attrs.push(parse_quote! {
+                            #[serde(default, with = "ruma_api::query::tri_state")]
+                        });
+                    } else if is_duration(&field.ty) {
+                        field.attrs.push(parse_quote! {
+                            #[serde(with = "ruma_api::query::duration_ms")]
+                        });
+                    } else if option_inner_type(&field.ty).is_some_and(is_duration) {
+                        field.attrs.push(parse_quote! {
+                            #[serde(default, with = "ruma_api::query::opt_duration_ms")]
+                        });
+                    }
+                }
+
+                if let Some(default_fn) = default_fn {
+                    let is_serde_field = match field_kind {
+                        RequestFieldKind::Body => true,
+                        RequestFieldKind::Query => !is_vec(&field.ty),
+                        _ => false,
+                    };
+
+                    if !is_serde_field {
+                        return Err(syn::Error::new_spanned(
+                            field,
+                            "`default` can only be used on body fields and non-`Vec` query fields",
+                        ));
+                    }
+
+                    field.attrs.push(parse_quote!(#[serde(default = #default_fn)]));
+                }
+
+                Ok(RequestField::new(field_kind, field, header))
             })
             .collect::<syn::Result<Vec<_>>>()?;
 
-        if newtype_body_field.is_some() && fields.iter().any(|f| f.is_body()) {
-            // TODO: highlight conflicting fields,
-            return Err(syn::Error::new_spanned(
-                raw.request_kw,
-                "Can't have both a newtype body field and regular body fields",
-            ));
+        if let Some(newtype_field) = &newtype_body_field {
+            let newtype_name = field_name(newtype_field);
+            let mut body_fields = fields.iter().filter_map(RequestField::as_body_field).peekable();
+
+            if body_fields.peek().is_some() {
+                let mut error = syn::Error::new_spanned(
+                    newtype_field,
+                    format!(
+                        "Newtype body field `{}` can't be combined with regular body fields",
+                        newtype_name,
+                    ),
+                );
+                for field in body_fields {
+                    error.combine(syn::Error::new_spanned(
+                        field,
+                        format!(
+                            "Regular body field `{}` conflicts with newtype body field `{}`",
+                            field_name(field),
+                            newtype_name,
+                        ),
+                    ));
+                }
+
+                return Err(error);
+            }
         }
 
-        if query_map_field.is_some() && fields.iter().any(|f| f.is_query()) {
+        if fields.iter().any(|f| f.kind() == RequestFieldKind::Multipart) {
+            let body_field = fields.iter().find(|f| {
+                f.is_body() || f.is_newtype_body() || f.kind() == RequestFieldKind::NewtypeRawBody
+            });
+
+            if let Some(body_field) = body_field {
+                return Err(syn::Error::new_spanned(
+                    body_field.field(),
+                    "Body fields can't be combined with `multipart` fields",
+                ));
+            }
+        }
+
+        if query_map_field.is_some()
+            && query_map_prefix.is_none()
+            && fields.iter().any(|f| f.is_query())
+        {
             return Err(syn::Error::new_spanned(
                 // TODO: raw,
                 raw.request_kw,
-                "Can't have both a query map field and regular query fields",
+                "Can't have both a query map field without a prefix and regular query fields",
             ));
         }
 
-        Ok(Self { fields })
+        if let Some(prefix) = &query_map_prefix {
+            let colliding_field =
+                fields.iter().filter_map(RequestField::as_query_field).find(|field| {
+                    field_name(field).trim_start_matches("r#").starts_with(&prefix.value())
+                });
+
+            if let Some(field) = colliding_field {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "The name of a query field can't start with the prefix of the query map field",
+                ));
+            }
+        }
+
+        if let Some(field) = &transaction_id_field {
+            let is_path = fields.iter().any(
+                |f| matches!(f, RequestField::Path(path_field) if path_field.ident == field.ident),
+            );
+
+            if !is_path || !is_option(&field.ty) {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "A `transaction_id` field must be a path field of type `Option<String>`",
+                ));
+            }
+        }
+
+        if let Some((field, _)) = &path_default {
+            let is_path = fields.iter().any(
+                |f| matches!(f, RequestField::Path(path_field) if path_field.ident == field.ident),
+            );
+
+            if !is_path || !is_option(&field.ty) {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "A `path_default` field must be a path field of an `Option` type",
+                ));
+            }
+        }
+
+        Ok(Self {
+            fields,
+            sensitive_fields,
+            explicit_null_fields,
+            fast_serde: false,
+            deny_unknown_fields: false,
+            timeout_hint_field,
+            transaction_id_field,
+            path_default,
+            comma_separated_fields,
+            query_map_prefix,
+        })
     }
 }
 
@@ -321,7 +933,12 @@ impl ToTokens for Request {
                     quote!(ruma_api::exports::serde::Deserialize)
                 };
 
-                Some((derive_deserialize, quote! { (#field); }))
+                let derive_serde = quote! {
+                    ruma_api::exports::serde::Serialize,
+                    #derive_deserialize
+                };
+
+                Some((derive_serde, TokenStream::new(), quote! { (#field); }))
             } else if self.has_body_fields() {
                 let fields = self.fields.iter().filter(|f| f.is_body());
                 let derive_deserialize = if fields.clone().any(|f| f.has_wrap_incoming_attr()) {
@@ -329,36 +946,67 @@ impl ToTokens for Request {
                 } else {
                     quote!(ruma_api::exports::serde::Deserialize)
                 };
-                let fields = fields.map(RequestField::field);
+                let fields: Vec<_> = fields.map(RequestField::field).collect();
+
+                if self.fast_serde {
+                    let ident = Ident::new("RequestBody", Span::call_site());
+                    let impl_serde = fast_serde::impl_serde(
+                        &ident,
+                        &fields,
+                        &self.explicit_null_fields,
+                        self.deny_unknown_fields,
+                    );
+
+                    Some((
+                        TokenStream::new(),
+                        TokenStream::new(),
+                        quote! { { #(#fields),* } #impl_serde },
+                    ))
+                } else {
+                    let derive_serde = quote! {
+                        ruma_api::exports::serde::Serialize,
+                        #derive_deserialize
+                    };
+                    let fields = fields
+                        .iter()
+                        .map(|field| skip_serializing_none(field, &self.explicit_null_fields));
+                    let serde_attrs = if self.deny_unknown_fields {
+                        quote!(#[serde(deny_unknown_fields)])
+                    } else {
+                        TokenStream::new()
+                    };
 
-                Some((derive_deserialize, quote! { { #(#fields),* } }))
+                    Some((derive_serde, serde_attrs, quote! { { #(#fields),* } }))
+                }
             } else {
                 None
             }
-            .map(|(derive_deserialize, def)| {
+            .map(|(derive_serde, serde_attrs, def)| {
                 quote! {
                     /// Data in the request body.
                     #[derive(
                         Debug,
                         ruma_api::Outgoing,
-                        ruma_api::exports::serde::Serialize,
-                        #derive_deserialize
+                        #derive_serde
                     )]
+                    #serde_attrs
                     struct RequestBody #def
                 }
             });
 
-        let request_path_struct = if self.has_path_fields() {
-            let fields = self.fields.iter().filter_map(RequestField::as_path_field);
+        let request_query_struct = if self.query_map_field().is_some() {
+            TokenStream::new()
+        } else if self.has_query_struct_fields() {
+            let fields = self.fields.iter().filter_map(RequestField::as_query_struct_field);
 
             quote! {
-                /// Data in the request path.
+                /// Data in the request's query string.
                 #[derive(
                     Debug,
                     ruma_api::exports::serde::Deserialize,
                     ruma_api::exports::serde::Serialize,
                 )]
-                struct RequestPath {
+                struct RequestQuery {
                     #(#fields),*
                 }
             }
@@ -366,43 +1014,62 @@ impl ToTokens for Request {
             TokenStream::new()
         };
 
-        let request_query_struct = if let Some(f) = self.query_map_field() {
-            let field = Field { ident: None, colon_token: None, ..f.clone() };
-
-            quote! {
-                /// Data in the request's query string.
-                #[derive(
-                    Debug,
-                    ruma_api::exports::serde::Deserialize,
-                    ruma_api::exports::serde::Serialize,
-                )]
-                struct RequestQuery(#field);
+        let (debug_attr, impl_debug) = if self.sensitive_fields.is_empty() {
+            (quote!(#[derive(Debug)]), TokenStream::new())
+        } else {
+            let ident = Ident::new("Request", Span::call_site());
+            let fields = self.fields.iter().map(RequestField::field);
+            let mut impl_debug = impl_debug_with_redaction(&ident, fields, &self.sensitive_fields);
+
+            if self.uses_wrap_incoming() {
+                let ident = Ident::new("IncomingRequest", Span::call_site());
+                let fields = self.fields.iter().map(RequestField::field);
+                impl_debug.extend(impl_debug_with_redaction(
+                    &ident,
+                    fields,
+                    &self.sensitive_fields,
+                ));
             }
-        } else if self.has_query_fields() {
-            let fields = self.fields.iter().filter_map(RequestField::as_query_field);
 
-            quote! {
-                /// Data in the request's query string.
-                #[derive(
-                    Debug,
-                    ruma_api::exports::serde::Deserialize,
-                    ruma_api::exports::serde::Serialize,
-                )]
-                struct RequestQuery {
-                    #(#fields),*
-                }
-            }
+            (quote!(#[incoming_no_debug]), impl_debug)
+        };
+
+        let partial_fields: Vec<_> = self.partial_fields().map(strip_serde_attrs).collect();
+        let (partial_debug_attr, partial_impl_debug) = if self.sensitive_fields.is_empty() {
+            (quote!(#[derive(Debug)]), TokenStream::new())
+        } else {
+            let ident = Ident::new("PartialRequest", Span::call_site());
+            let impl_debug =
+                impl_debug_with_redaction(&ident, self.partial_fields(), &self.sensitive_fields);
+
+            (TokenStream::new(), impl_debug)
+        };
+
+        let multipart_boundary = if self.has_multipart_fields() {
+            quote!(multipart_boundary: String,)
         } else {
             TokenStream::new()
         };
 
         let request = quote! {
-            #[derive(Debug, Clone, ruma_api::Outgoing)]
+            #[derive(ruma_api::Outgoing)]
             #[incoming_no_deserialize]
+            #debug_attr
             pub struct Request #request_def
 
+            #impl_debug
+
+            /// The parts of a request that are not in the HTTP body, as returned by
+            /// `try_from_parts`.
+            #partial_debug_attr
+            pub struct PartialRequest {
+                #(#partial_fields,)*
+                #multipart_boundary
+            }
+
+            #partial_impl_debug
+
             #request_body_struct
-            #request_path_struct
             #request_query_struct
         };
 
@@ -415,31 +1082,40 @@ pub enum RequestField {
     /// JSON data in the body of the request.
     Body(Field),
     /// Data in an HTTP header.
-    Header(Field, Ident),
+    Header(Field, HeaderName),
+    /// Data that appears in HTTP headers as dynamic name-value pairs.
+    HeaderMap(Field),
     /// A specific data type in the body of the request.
     NewtypeBody(Field),
     /// Arbitrary bytes in the body of the request.
     NewtypeRawBody(Field),
+    /// A part of a `multipart/form-data` body.
+    Multipart(Field),
     /// Data that appears in the URL path.
     Path(Field),
     /// Data that appears in the query string.
     Query(Field),
+    /// A struct whose fields appear in the query string.
+    FlattenedQuery(Field),
     /// Data that appears in the query string as dynamic key-value pairs.
     QueryMap(Field),
 }
 
 impl RequestField {
     /// Creates a new `RequestField`.
-    fn new(kind: RequestFieldKind, field: Field, header: Option<Ident>) -> Self {
+    fn new(kind: RequestFieldKind, field: Field, header: Option<HeaderName>) -> Self {
         match kind {
             RequestFieldKind::Body => RequestField::Body(field),
             RequestFieldKind::Header => {
                 RequestField::Header(field, header.expect("missing header name"))
             }
+            RequestFieldKind::HeaderMap => RequestField::HeaderMap(field),
             RequestFieldKind::NewtypeBody => RequestField::NewtypeBody(field),
             RequestFieldKind::NewtypeRawBody => RequestField::NewtypeRawBody(field),
+            RequestFieldKind::Multipart => RequestField::Multipart(field),
             RequestFieldKind::Path => RequestField::Path(field),
             RequestFieldKind::Query => RequestField::Query(field),
+            RequestFieldKind::FlattenedQuery => RequestField::FlattenedQuery(field),
             RequestFieldKind::QueryMap => RequestField::QueryMap(field),
         }
     }
@@ -449,10 +1125,13 @@ impl RequestField {
         match self {
             RequestField::Body(..) => RequestFieldKind::Body,
             RequestField::Header(..) => RequestFieldKind::Header,
+            RequestField::HeaderMap(..) => RequestFieldKind::HeaderMap,
             RequestField::NewtypeBody(..) => RequestFieldKind::NewtypeBody,
             RequestField::NewtypeRawBody(..) => RequestFieldKind::NewtypeRawBody,
+            RequestField::Multipart(..) => RequestFieldKind::Multipart,
             RequestField::Path(..) => RequestFieldKind::Path,
             RequestField::Query(..) => RequestFieldKind::Query,
+            RequestField::FlattenedQuery(..) => RequestFieldKind::FlattenedQuery,
             RequestField::QueryMap(..) => RequestFieldKind::QueryMap,
         }
     }
@@ -467,6 +1146,11 @@ impl RequestField {
         self.kind() == RequestFieldKind::Header
     }
 
+    /// Whether or not this request field is a header map kind.
+    fn is_header_map(&self) -> bool {
+        self.kind() == RequestFieldKind::HeaderMap
+    }
+
     /// Whether or not this request field is a newtype body kind.
     fn is_newtype_body(&self) -> bool {
         self.kind() == RequestFieldKind::NewtypeBody
@@ -477,9 +1161,20 @@ impl RequestField {
         self.kind() == RequestFieldKind::Path
     }
 
+    /// Whether or not this request field is part of the HTTP body.
+    fn is_in_body(&self) -> bool {
+        matches!(
+            self,
+            RequestField::Body(_)
+                | RequestField::NewtypeBody(_)
+                | RequestField::NewtypeRawBody(_)
+                | RequestField::Multipart(_)
+        )
+    }
+
     /// Whether or not this request field is a query string kind.
     fn is_query(&self) -> bool {
-        self.kind() == RequestFieldKind::Query
+        matches!(self.kind(), RequestFieldKind::Query | RequestFieldKind::FlattenedQuery)
     }
 
     /// Return the contained field if this request field is a body kind.
@@ -497,30 +1192,49 @@ impl RequestField {
         self.field_of_kind(RequestFieldKind::NewtypeRawBody)
     }
 
-    /// Return the contained field if this request field is a path kind.
-    fn as_path_field(&self) -> Option<&Field> {
-        self.field_of_kind(RequestFieldKind::Path)
-    }
-
     /// Return the contained field if this request field is a query kind.
     fn as_query_field(&self) -> Option<&Field> {
         self.field_of_kind(RequestFieldKind::Query)
     }
 
+    /// Return the contained field if this request field is a query kind that is serialized through
+    /// the `RequestQuery` struct.
+    fn as_query_struct_field(&self) -> Option<&Field> {
+        self.as_query_field().filter(|field| !is_vec(&field.ty))
+    }
+
+    /// Return the contained field if this request field is a query kind with a `Vec` type.
+    fn as_repeated_query_field(&self) -> Option<&Field> {
+        self.as_query_field().filter(|field| is_vec(&field.ty))
+    }
+
+    /// Return the contained field if this request field is a flattened query kind.
+    fn as_flattened_query_field(&self) -> Option<&Field> {
+        self.field_of_kind(RequestFieldKind::FlattenedQuery)
+    }
+
     /// Return the contained field if this request field is a query map kind.
     fn as_query_map_field(&self) -> Option<&Field> {
         self.field_of_kind(RequestFieldKind::QueryMap)
     }
 
+    /// Return the contained field if this request field is a header map kind.
+    fn as_header_map_field(&self) -> Option<&Field> {
+        self.field_of_kind(RequestFieldKind::HeaderMap)
+    }
+
     /// Gets the inner `Field` value.
     fn field(&self) -> &Field {
         match self {
             RequestField::Body(field)
             | RequestField::Header(field, _)
+            | RequestField::HeaderMap(field)
             | RequestField::NewtypeBody(field)
             | RequestField::NewtypeRawBody(field)
+            | RequestField::Multipart(field)
             | RequestField::Path(field)
             | RequestField::Query(field)
+            | RequestField::FlattenedQuery(field)
             | RequestField::QueryMap(field) => field,
         }
     }
@@ -550,13 +1264,38 @@ enum RequestFieldKind {
     /// See the similarly named variant of `RequestField`.
     Header,
     /// See the similarly named variant of `RequestField`.
+    HeaderMap,
+    /// See the similarly named variant of `RequestField`.
     NewtypeBody,
     /// See the similarly named variant of `RequestField`.
     NewtypeRawBody,
     /// See the similarly named variant of `RequestField`.
+    Multipart,
+    /// See the similarly named variant of `RequestField`.
     Path,
     /// See the similarly named variant of `RequestField`.
     Query,
     /// See the similarly named variant of `RequestField`.
+    FlattenedQuery,
+    /// See the similarly named variant of `RequestField`.
     QueryMap,
 }
+
+/// The name of the variable holding the parsed value of the given flattened query field.
+pub fn flattened_query_var(field: &Field) -> Ident {
+    let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+    format_ident!("flattened_query_{}", field_name.unraw())
+}
+
+/// The name of the variable holding the parsed values of the given `Vec` query field.
+pub fn repeated_query_var(field: &Field) -> Ident {
+    let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+    format_ident!("repeated_query_{}", field_name.unraw())
+}
+
+/// The name of the variable holding the parsed pairs of the given `query_map` field with a
+/// `prefix`.
+pub fn prefixed_query_map_var(field: &Field) -> Ident {
+    let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+    format_ident!("prefixed_query_map_{}", field_name.unraw())
+}
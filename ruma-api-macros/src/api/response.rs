@@ -2,12 +2,13 @@
 
 use std::{convert::TryFrom, mem};
 
-use proc_macro2::TokenStream;
-use quote::{quote, quote_spanned, ToTokens};
-use syn::{spanned::Spanned, Field, Ident};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{ext::IdentExt as _, parse_quote, spanned::Spanned, Field, Ident};
 
 use crate::api::{
-    attribute::{Meta, MetaNameValue},
+    attribute::{HeaderName, Meta, MetaNameValue},
+    fast_serde, field_name, impl_debug_with_redaction, is_option, is_vec, skip_serializing_none,
     strip_serde_attrs, RawResponse,
 };
 
@@ -15,17 +16,52 @@ use crate::api::{
 pub struct Response {
     /// The fields of the response.
     fields: Vec<ResponseField>,
+    /// The names of the fields that are left out of the response's `Debug` output.
+    sensitive_fields: Vec<Ident>,
+    /// The names of the `Option` body fields that are serialized as `null` when they are `None`.
+    explicit_null_fields: Vec<Ident>,
+    /// Whether to use hand-written serde implementations for the body struct.
+    fast_serde: bool,
 }
 
 impl Response {
+    /// Switches the body struct to hand-written serde implementations, for endpoints with
+    /// `fast: true` in their metadata.
+    pub fn enable_fast_serde(&mut self) -> syn::Result<()> {
+        fast_serde::check_fields(self.body_fields())?;
+        self.fast_serde = true;
+        Ok(())
+    }
+
     /// Whether or not this response has any data in the HTTP body.
     pub fn has_body_fields(&self) -> bool {
         self.fields.iter().any(|field| field.is_body())
     }
 
-    /// Whether or not this response has any data in HTTP headers.
-    pub fn has_header_fields(&self) -> bool {
-        self.fields.iter().any(|field| field.is_header())
+    /// Whether or not this response has a field for the `Content-Type` header.
+    pub fn has_content_type_header(&self) -> bool {
+        self.fields.iter().any(ResponseField::is_content_type_header)
+    }
+
+    /// Returns the field for the `Content-Range` header, if any.
+    pub fn content_range_field(&self) -> Option<&Field> {
+        self.fields.iter().find_map(|field| match field {
+            ResponseField::Header(field, header_name) if header_name.is_content_range() => {
+                Some(field)
+            }
+            _ => None,
+        })
+    }
+
+    /// Produces an iterator over all the fields that are part of the HTTP body, including newtype
+    /// body fields.
+    pub fn body_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter(|field| !field.is_header()).map(ResponseField::field)
+    }
+
+    /// Produces an iterator over the regular body fields, excluding newtype body fields.
+    pub fn struct_body_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter_map(ResponseField::as_body_field)
     }
 
     /// Whether any field has a #[wrap_incoming] attribute.
@@ -33,8 +69,9 @@ impl Response {
         self.fields.iter().any(|f| f.has_wrap_incoming_attr())
     }
 
-    /// Produces code for a response struct initializer.
-    pub fn init_fields(&self) -> TokenStream {
+    /// Produces code for a response struct initializer, taking the body of an `http::Response`
+    /// named `response` that is owned or, if `borrowed` is set, borrowed.
+    pub fn init_fields(&self, borrowed: bool) -> TokenStream {
         let fields = self.fields.iter().map(|response_field| {
             let field = response_field.field();
             let field_name = field.ident.as_ref().expect("expected field to have an identifier");
@@ -46,13 +83,10 @@ impl Response {
                         #field_name: response_body.#field_name
                     }
                 }
-                ResponseField::Header(_, header_name) => {
+                ResponseField::Header(..) => {
+                    let var = header_var(field);
                     quote_spanned! {span=>
-                        #field_name: headers.remove(ruma_api::exports::http::header::#header_name)
-                            .expect("response missing expected header")
-                            .to_str()
-                            .expect("failed to convert HeaderValue to str")
-                            .to_owned()
+                        #field_name: #var
                     }
                 }
                 ResponseField::NewtypeBody(_) => {
@@ -61,8 +95,19 @@ impl Response {
                     }
                 }
                 ResponseField::NewtypeRawBody(_) => {
+                    let body = if borrowed {
+                        quote!(response.body().clone())
+                    } else {
+                        quote!(response.into_body())
+                    };
+
+                    quote_spanned! {span=>
+                        #field_name: #body
+                    }
+                }
+                ResponseField::NewtypeTextBody(_) => {
                     quote_spanned! {span=>
-                        #field_name: response.into_body()
+                        #field_name: response_text_body
                     }
                 }
             }
@@ -73,33 +118,114 @@ impl Response {
         }
     }
 
+    /// Produces code to parse the header fields from an `http::Response` named `response` into
+    /// local variables, for use in the struct initializer produced by `init_fields`.
+    ///
+    /// If `borrowed` is set, `response` is a reference and only copied for errors.
+    pub fn parse_header_fields(&self, borrowed: bool) -> TokenStream {
+        let new_error = deserialization_error_constructor(borrowed);
+        let stmts = self.fields.iter().filter_map(|response_field| match response_field {
+            ResponseField::Header(field, header_name) => {
+                let var = header_var(field);
+                let span = field.span();
+                let parse_fn = if is_vec(&field.ty) {
+                    quote!(parse_all)
+                } else if is_option(&field.ty) {
+                    quote!(parse_optional)
+                } else {
+                    quote!(parse_required)
+                };
+
+                Some(quote_spanned! {span=>
+                    let #var = match ruma_api::header::#parse_fn(
+                        response.headers(),
+                        #header_name,
+                    ) {
+                        Ok(value) => value,
+                        Err(err) => {
+                            return Err(#new_error(err, response).into());
+                        }
+                    };
+                })
+            }
+            _ => None,
+        });
+
+        quote! { #(#stmts)* }
+    }
+
     /// Produces code to add necessary HTTP headers to an `http::Response`.
     pub fn apply_header_fields(&self) -> TokenStream {
-        let header_calls = self.fields.iter().filter_map(|response_field| {
-            if let ResponseField::Header(ref field, ref header_name) = *response_field {
+        let header_calls = self.fields.iter().filter_map(|response_field| match response_field {
+            ResponseField::Header(field, header_name)
+                if !is_option(&field.ty) && !is_vec(&field.ty) =>
+            {
                 let field_name =
                     field.ident.as_ref().expect("expected field to have an identifier");
                 let span = field.span();
 
                 Some(quote_spanned! {span=>
-                    .header(ruma_api::exports::http::header::#header_name, response.#field_name)
+                    .header(#header_name, ruma_api::header::to_value(&response.#field_name)?)
                 })
-            } else {
-                None
             }
+            _ => None,
         });
 
         quote! { #(#header_calls)* }
     }
 
-    /// Produces code to initialize the struct that will be used to create the response body.
-    pub fn to_body(&self) -> TokenStream {
+    /// Produces code to add the HTTP headers of optional and multi-value header fields to an
+    /// `http::Response` named `http_response`.
+    pub fn append_header_fields(&self) -> TokenStream {
+        let header_stmts = self.fields.iter().filter_map(|response_field| match response_field {
+            ResponseField::Header(field, header_name)
+                if is_option(&field.ty) || is_vec(&field.ty) =>
+            {
+                let field_name =
+                    field.ident.as_ref().expect("expected field to have an identifier");
+                let span = field.span();
+
+                let append_value = quote_spanned! {span=>
+                    http_response
+                        .headers_mut()
+                        .append(#header_name, ruma_api::header::to_value(&value)?);
+                };
+
+                if is_option(&field.ty) {
+                    Some(quote_spanned! {span=>
+                        if let Some(value) = response.#field_name {
+                            #append_value
+                        }
+                    })
+                } else {
+                    Some(quote_spanned! {span=>
+                        for value in response.#field_name {
+                            #append_value
+                        }
+                    })
+                }
+            }
+            _ => None,
+        });
+
+        quote! { #(#header_stmts)* }
+    }
+
+    /// Produces code to initialize the struct that will be used to create the response body, and to
+    /// serialize it with the function at the path `serialize_body`.
+    pub fn to_body(&self, serialize_body: &TokenStream) -> TokenStream {
         if let Some(field) = self.newtype_raw_body_field() {
             let field_name = field.ident.as_ref().expect("expected field to have an identifier");
             let span = field.span();
             return quote_spanned!(span=> response.#field_name);
         }
 
+        if let Some(field) = self.newtype_text_body_field() {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let span = field.span();
+            return quote_spanned!(span=> response.#field_name.into_bytes());
+        }
+
         if !self.has_body_fields() && self.newtype_body_field().is_none() {
             return quote!(Vec::new());
         }
@@ -128,7 +254,18 @@ impl Response {
             }
         };
 
-        quote!(ruma_api::exports::serde_json::to_vec(&#body)?)
+        let check =
+            if self.uses_wrap_incoming() { quote!(check_deserializes) } else { quote!(check) };
+
+        quote! {
+            {
+                let body = #serialize_body(&#body)?;
+                ruma_api::roundtrip::#check::<<ResponseBody as ruma_api::Outgoing>::Incoming>(
+                    &body,
+                );
+                body
+            }
+        }
     }
 
     /// Gets the newtype body field, if this response has one.
@@ -140,6 +277,11 @@ impl Response {
     pub fn newtype_raw_body_field(&self) -> Option<&Field> {
         self.fields.iter().find_map(ResponseField::as_newtype_raw_body_field)
     }
+
+    /// Gets the newtype text body field, if this response has one.
+    pub fn newtype_text_body_field(&self) -> Option<&Field> {
+        self.fields.iter().find_map(ResponseField::as_newtype_text_body_field)
+    }
 }
 
 impl TryFrom<RawResponse> for Response {
@@ -147,16 +289,21 @@ impl TryFrom<RawResponse> for Response {
 
     fn try_from(raw: RawResponse) -> syn::Result<Self> {
         let mut newtype_body_field = None;
+        let mut additional_field = None;
+
+        let mut sensitive_fields = Vec::new();
+        let mut explicit_null_fields = Vec::new();
 
-        let fields = raw
+        let mut fields = raw
             .fields
             .into_iter()
             .map(|mut field| {
                 let mut field_kind = None;
                 let mut header = None;
+                let mut is_additional = false;
 
-                for attr in mem::replace(&mut field.attrs, Vec::new()) {
-                    let meta = match Meta::from_attribute(&attr)? {
+                for attr in mem::take(&mut field.attrs) {
+                    let metas = match Meta::from_attribute(&attr)? {
                         Some(m) => m,
                         None => {
                             field.attrs.push(attr);
@@ -164,54 +311,127 @@ impl TryFrom<RawResponse> for Response {
                         }
                     };
 
-                    if field_kind.is_some() {
-                        return Err(syn::Error::new_spanned(
-                            attr,
-                            "There can only be one field kind attribute",
-                        ));
-                    }
-
-                    field_kind = Some(match meta {
-                        Meta::Word(ident) => match &ident.to_string()[..] {
-                            s @ "body" | s @ "raw_body" => {
-                                if let Some(f) = &newtype_body_field {
+                    for meta in metas {
+                        if let Meta::Word(ident) = &meta {
+                            if ident == "additional" {
+                                if let Some(f) = &additional_field {
                                     let mut error = syn::Error::new_spanned(
                                         field,
-                                        "There can only be one newtype body field",
+                                        "There can only be one `additional` field",
                                     );
                                     error.combine(syn::Error::new_spanned(
                                         f,
-                                        "Previous newtype body field",
+                                        "Previous `additional` field",
                                     ));
                                     return Err(error);
                                 }
 
-                                newtype_body_field = Some(field.clone());
-                                match s {
-                                    "body" => ResponseFieldKind::NewtypeBody,
-                                    "raw_body" => ResponseFieldKind::NewtypeRawBody,
-                                    _ => unreachable!(),
-                                }
+                                additional_field = Some(field.clone());
+                                is_additional = true;
+                                field.attrs.push(parse_quote!(#[serde(flatten)]));
+                                continue;
                             }
-                            _ => {
-                                return Err(syn::Error::new_spanned(
-                                    ident,
-                                    "Invalid #[ruma_api] argument with value, expected `body`",
-                                ));
+
+                            if ident == "sensitive" {
+                                sensitive_fields.push(
+                                    field
+                                        .ident
+                                        .clone()
+                                        .expect("expected field to have an identifier"),
+                                );
+                                continue;
                             }
-                        },
-                        Meta::NameValue(MetaNameValue { name, value }) => {
-                            if name != "header" {
-                                return Err(syn::Error::new_spanned(
-                                    name,
-                                    "Invalid #[ruma_api] argument with value, expected `header`",
-                                ));
+
+                            if ident == "explicit_null" {
+                                if !is_option(&field.ty) {
+                                    return Err(syn::Error::new_spanned(
+                                        field,
+                                        "`explicit_null` can only be used on `Option` body fields",
+                                    ));
+                                }
+
+                                explicit_null_fields.push(
+                                    field
+                                        .ident
+                                        .clone()
+                                        .expect("expected field to have an identifier"),
+                                );
+                                continue;
                             }
+                        }
 
-                            header = Some(value);
-                            ResponseFieldKind::Header
+                        if field_kind.is_some() {
+                            return Err(syn::Error::new_spanned(
+                                attr,
+                                "There can only be one field kind attribute",
+                            ));
                         }
-                    });
+
+                        field_kind = Some(match meta {
+                            Meta::Word(ident) => match &ident.to_string()[..] {
+                                s @ "body" | s @ "json_body" | s @ "raw_body" | s @ "text_body" => {
+                                    if let Some(f) = &newtype_body_field {
+                                        let name = field_name(&field);
+                                        let mut error = syn::Error::new_spanned(
+                                            &field,
+                                            format!(
+                                                "There can only be one newtype body field, \
+                                                 but `{}` and `{}` are both marked as one",
+                                                field_name(f),
+                                                name,
+                                            ),
+                                        );
+                                        error.combine(syn::Error::new_spanned(
+                                            f,
+                                            format!(
+                                                "Previous newtype body field `{}`",
+                                                field_name(f),
+                                            ),
+                                        ));
+                                        return Err(error);
+                                    }
+
+                                    newtype_body_field = Some(field.clone());
+                                    match s {
+                                        // `json_body` is an alias of `body` for
+                                        // `serde_json::Value` fields.
+                                        "body" | "json_body" => ResponseFieldKind::NewtypeBody,
+                                        "raw_body" => ResponseFieldKind::NewtypeRawBody,
+                                        "text_body" => ResponseFieldKind::NewtypeTextBody,
+                                        _ => unreachable!(),
+                                    }
+                                }
+                                _ => {
+                                    return Err(syn::Error::new_spanned(
+                                        ident,
+                                        "Invalid #[ruma_api] argument, expected one of `body`, \
+                                         `json_body`, `raw_body`, `text_body`, `additional`, \
+                                         `sensitive`, `explicit_null`",
+                                    ));
+                                }
+                            },
+                            Meta::NameValue(MetaNameValue { name, value }) => {
+                                if name != "header" {
+                                    return Err(syn::Error::new_spanned(
+                                        name,
+                                        "Invalid #[ruma_api] argument with value, expected \
+                                         `header`",
+                                    ));
+                                }
+
+                                header = Some(HeaderName::new(value)?);
+                                ResponseFieldKind::Header
+                            }
+                        });
+                    }
+                }
+
+                if is_additional && field_kind.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        field,
+                        "An `additional` field is part of the JSON body and can't have a field \
+                         kind attribute",
+                    ));
                 }
 
                 Ok(match field_kind.unwrap_or(ResponseFieldKind::Body) {
@@ -221,19 +441,55 @@ impl TryFrom<RawResponse> for Response {
                     }
                     ResponseFieldKind::NewtypeBody => ResponseField::NewtypeBody(field),
                     ResponseFieldKind::NewtypeRawBody => ResponseField::NewtypeRawBody(field),
+                    ResponseFieldKind::NewtypeTextBody => ResponseField::NewtypeTextBody(field),
                 })
             })
             .collect::<syn::Result<Vec<_>>>()?;
 
-        if newtype_body_field.is_some() && fields.iter().any(|f| f.is_body()) {
-            // TODO: highlight conflicting fields,
-            return Err(syn::Error::new_spanned(
-                raw.response_kw,
-                "Can't have both a newtype body field and regular body fields",
+        if let Some(newtype_field) = &newtype_body_field {
+            let newtype_name = field_name(newtype_field);
+            let mut body_fields = fields.iter().filter_map(ResponseField::as_body_field).peekable();
+
+            if body_fields.peek().is_some() {
+                let mut error = syn::Error::new_spanned(
+                    newtype_field,
+                    format!(
+                        "Newtype body field `{}` can't be combined with regular body fields",
+                        newtype_name,
+                    ),
+                );
+                for field in body_fields {
+                    error.combine(syn::Error::new_spanned(
+                        field,
+                        format!(
+                            "Regular body field `{}` conflicts with newtype body field `{}`",
+                            field_name(field),
+                            newtype_name,
+                        ),
+                    ));
+                }
+
+                return Err(error);
+            }
+        }
+
+        // Raw bodies can be in any format, so unless the `Content-Type` header is already captured
+        // by a header field, expose it in an extra field.
+        if fields.iter().any(ResponseField::is_newtype_raw_body)
+            && !fields.iter().any(ResponseField::is_content_type_header)
+        {
+            let content_type_field: syn::FieldsNamed = parse_quote! {{
+                /// The `Content-Type` of the raw response body.
+                pub content_type: Option<String>
+            }};
+
+            fields.push(ResponseField::Header(
+                content_type_field.named.into_iter().next().unwrap(),
+                HeaderName::Const(Ident::new("CONTENT_TYPE", Span::call_site())),
             ));
         }
 
-        Ok(Self { fields })
+        Ok(Self { fields, sensitive_fields, explicit_null_fields, fast_serde: false })
     }
 }
 
@@ -257,7 +513,12 @@ impl ToTokens for Response {
                     quote!(ruma_api::exports::serde::Deserialize)
                 };
 
-                Some((derive_deserialize, quote! { (#field); }))
+                let derive_serde = quote! {
+                    ruma_api::exports::serde::Serialize,
+                    #derive_deserialize
+                };
+
+                Some((derive_serde, quote! { (#field); }))
             } else if self.has_body_fields() {
                 let fields = self.fields.iter().filter(|f| f.is_body());
                 let derive_deserialize = if fields.clone().any(|f| f.has_wrap_incoming_attr()) {
@@ -265,30 +526,68 @@ impl ToTokens for Response {
                 } else {
                     quote!(ruma_api::exports::serde::Deserialize)
                 };
-                let fields = fields.map(ResponseField::field);
+                let fields: Vec<_> = fields.map(ResponseField::field).collect();
+
+                if self.fast_serde {
+                    let ident = Ident::new("ResponseBody", Span::call_site());
+                    let impl_serde =
+                        fast_serde::impl_serde(&ident, &fields, &self.explicit_null_fields, false);
 
-                Some((derive_deserialize, quote!({ #(#fields),* })))
+                    Some((TokenStream::new(), quote! { { #(#fields),* } #impl_serde }))
+                } else {
+                    let derive_serde = quote! {
+                        ruma_api::exports::serde::Serialize,
+                        #derive_deserialize
+                    };
+                    let fields = fields
+                        .iter()
+                        .map(|field| skip_serializing_none(field, &self.explicit_null_fields));
+
+                    Some((derive_serde, quote! { { #(#fields),* } }))
+                }
             } else {
                 None
             }
-            .map(|(derive_deserialize, def)| {
+            .map(|(derive_serde, def)| {
                 quote! {
                     /// Data in the response body.
                     #[derive(
                         Debug,
                         ruma_api::Outgoing,
-                        ruma_api::exports::serde::Serialize,
-                        #derive_deserialize
+                        #derive_serde
                     )]
                     struct ResponseBody #def
                 }
             });
 
+        let (debug_attr, impl_debug) = if self.sensitive_fields.is_empty() {
+            (quote!(#[derive(Debug)]), TokenStream::new())
+        } else {
+            let ident = Ident::new("Response", Span::call_site());
+            let fields = self.fields.iter().map(ResponseField::field);
+            let mut impl_debug = impl_debug_with_redaction(&ident, fields, &self.sensitive_fields);
+
+            if self.uses_wrap_incoming() {
+                let ident = Ident::new("IncomingResponse", Span::call_site());
+                let fields = self.fields.iter().map(ResponseField::field);
+                impl_debug.extend(impl_debug_with_redaction(
+                    &ident,
+                    fields,
+                    &self.sensitive_fields,
+                ));
+            }
+
+            (quote!(#[incoming_no_debug]), impl_debug)
+        };
+
         let response = quote! {
-            #[derive(Debug, Clone, ruma_api::Outgoing)]
+            #[derive(ruma_api::Outgoing)]
             #[incoming_no_deserialize]
+            #debug_attr
             pub struct Response #response_def
 
+            #impl_debug
+
             #response_body_struct
         };
 
@@ -301,11 +600,13 @@ pub enum ResponseField {
     /// JSON data in the body of the response.
     Body(Field),
     /// Data in an HTTP header.
-    Header(Field, Ident),
+    Header(Field, HeaderName),
     /// A specific data type in the body of the response.
     NewtypeBody(Field),
     /// Arbitrary bytes in the body of the response.
     NewtypeRawBody(Field),
+    /// UTF-8 text in the body of the response.
+    NewtypeTextBody(Field),
 }
 
 impl ResponseField {
@@ -315,7 +616,8 @@ impl ResponseField {
             ResponseField::Body(field)
             | ResponseField::Header(field, _)
             | ResponseField::NewtypeBody(field)
-            | ResponseField::NewtypeRawBody(field) => field,
+            | ResponseField::NewtypeRawBody(field)
+            | ResponseField::NewtypeTextBody(field) => field,
         }
     }
 
@@ -326,10 +628,7 @@ impl ResponseField {
 
     /// Whether or not this response field is a header kind.
     fn is_header(&self) -> bool {
-        match self {
-            ResponseField::Header(..) => true,
-            _ => false,
-        }
+        matches!(self, ResponseField::Header(..))
     }
 
     /// Whether or not this response field is a newtype body kind.
@@ -337,6 +636,16 @@ impl ResponseField {
         self.as_newtype_body_field().is_some()
     }
 
+    /// Whether or not this response field is a newtype raw body kind.
+    fn is_newtype_raw_body(&self) -> bool {
+        self.as_newtype_raw_body_field().is_some()
+    }
+
+    /// Whether or not this response field is the `Content-Type` header.
+    fn is_content_type_header(&self) -> bool {
+        matches!(self, ResponseField::Header(_, header_name) if header_name.is_content_type())
+    }
+
     /// Return the contained field if this response field is a body kind.
     fn as_body_field(&self) -> Option<&Field> {
         match self {
@@ -361,6 +670,14 @@ impl ResponseField {
         }
     }
 
+    /// Return the contained field if this response field is a newtype text body kind.
+    fn as_newtype_text_body_field(&self) -> Option<&Field> {
+        match self {
+            ResponseField::NewtypeTextBody(field) => Some(field),
+            _ => None,
+        }
+    }
+
     /// Whether or not the reponse field has a #[wrap_incoming] attribute.
     fn has_wrap_incoming_attr(&self) -> bool {
         self.field().attrs.iter().any(|attr| {
@@ -379,4 +696,22 @@ enum ResponseFieldKind {
     NewtypeBody,
     /// See the similarly named variant of `ResponseField`.
     NewtypeRawBody,
+    /// See the similarly named variant of `ResponseField`.
+    NewtypeTextBody,
+}
+
+/// The name of the variable holding the parsed value of the given header field.
+fn header_var(field: &Field) -> Ident {
+    let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+    format_ident!("header_{}", field_name.unraw())
+}
+
+/// The constructor of `ResponseDeserializationError` for an `http::Response` that is owned or, if
+/// `borrowed` is set, borrowed.
+pub fn deserialization_error_constructor(borrowed: bool) -> TokenStream {
+    if borrowed {
+        quote!(ruma_api::error::ResponseDeserializationError::from_borrowed)
+    } else {
+        quote!(ruma_api::error::ResponseDeserializationError::new)
+    }
 }
@@ -1,10 +1,15 @@
 use std::mem;
 
-use proc_macro2::{Ident, Span, TokenStream};
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
 use quote::{format_ident, quote, ToTokens};
 use syn::{
-    parse_quote, punctuated::Pair, spanned::Spanned, Attribute, Data, DeriveInput, Fields,
-    GenericArgument, Path, PathArguments, Type, TypePath,
+    parse_quote,
+    punctuated::{Pair, Punctuated},
+    spanned::Spanned,
+    token::Add,
+    Attribute, Data, DeriveInput, Fields, GenericArgument, GenericParam, Generics, Index, Lifetime,
+    Member, Path, PathArguments, Type, TypeArray, TypeGroup, TypeParamBound, TypeParen, TypePath,
+    TypeReference, TypeSlice, TypeTuple, WherePredicate,
 };
 
 mod wrap_incoming;
@@ -17,18 +22,17 @@ enum StructKind {
 }
 
 pub fn expand_derive_outgoing(input: DeriveInput) -> syn::Result<TokenStream> {
-    if !input.generics.params.is_empty() {
-        return Err(syn::Error::new_spanned(
-            input.generics,
-            "derive(Outgoing) doesn't currently support types with generics!",
-        ));
-    }
-
-    let derive_deserialize = if no_deserialize_in_attrs(&input.attrs) {
+    let derive_deserialize = if has_attr(&input.attrs, "incoming_no_deserialize") {
         TokenStream::new()
     } else {
         quote!(ruma_api::exports::serde::Deserialize)
     };
+    let derive_debug = if has_attr(&input.attrs, "incoming_no_debug") {
+        TokenStream::new()
+    } else {
+        quote!(Debug,)
+    };
+    let try_into_outgoing = has_attr(&input.attrs, "incoming_try_into_outgoing");
 
     let (mut fields, struct_kind): (Vec<_>, _) = match input.data {
         Data::Enum(_) | Data::Union(_) => {
@@ -41,17 +45,37 @@ pub fn expand_derive_outgoing(input: DeriveInput) -> syn::Result<TokenStream> {
             Fields::Unnamed(fs) => {
                 (fs.unnamed.into_pairs().map(Pair::into_value).collect(), StructKind::Tuple)
             }
-            Fields::Unit => return Ok(impl_outgoing_with_incoming_self(input.ident)),
+            Fields::Unit => {
+                return Ok(impl_outgoing_with_incoming_self(&input.ident, &input.generics))
+            }
         },
     };
 
+    // Borrowed fields can't be deserialized into, so types with lifetime parameters always get an
+    // 'Incoming' variant in which references are replaced by their owned equivalents.
+    let has_lifetimes = input.generics.lifetimes().next().is_some();
     let mut any_attribute = false;
 
-    for field in &mut fields {
+    if try_into_outgoing && has_lifetimes {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "#[incoming_try_into_outgoing] is not supported for types with lifetime parameters",
+        ));
+    }
+
+    // The expressions for the fields of the original type in the generated conversion from the
+    // 'Incoming' type.
+    let mut field_conversions = Vec::new();
+
+    for (i, field) in fields.iter_mut().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        };
         let mut field_meta = None;
 
         let mut remaining_attrs = Vec::new();
-        for attr in mem::replace(&mut field.attrs, Vec::new()) {
+        for attr in mem::take(&mut field.attrs) {
             if let Some(meta) = Meta::from_attribute(&attr)? {
                 if field_meta.is_some() {
                     return Err(syn::Error::new_spanned(
@@ -69,15 +93,36 @@ pub fn expand_derive_outgoing(input: DeriveInput) -> syn::Result<TokenStream> {
 
         if let Some(attr) = field_meta {
             if let Some(type_to_wrap) = attr.type_to_wrap {
+                if try_into_outgoing {
+                    return Err(syn::Error::new_spanned(
+                        &type_to_wrap,
+                        "#[incoming_try_into_outgoing] doesn't support wrapping generic arguments",
+                    ));
+                }
+
                 wrap_generic_arg(&type_to_wrap, &mut field.ty, attr.wrapper_type.as_ref())?;
             } else {
                 wrap_ty(&mut field.ty, attr.wrapper_type)?;
             }
+
+            let field_name = match &member {
+                Member::Named(ident) => ident.to_string(),
+                Member::Unnamed(index) => index.index.to_string(),
+            };
+            field_conversions.push(quote! {
+                ruma_api::error::FromIncomingError::convert(#field_name, incoming.#member)?
+            });
+        } else {
+            field_conversions.push(quote!(incoming.#member));
+        }
+
+        if has_lifetimes {
+            to_owned_ty(&mut field.ty)?;
         }
     }
 
-    if !any_attribute {
-        return Ok(impl_outgoing_with_incoming_self(input.ident));
+    if !any_attribute && !has_lifetimes {
+        return Ok(impl_outgoing_with_incoming_self(&input.ident, &input.generics));
     }
 
     let vis = input.vis;
@@ -85,27 +130,59 @@ pub fn expand_derive_outgoing(input: DeriveInput) -> syn::Result<TokenStream> {
     let original_ident = input.ident;
     let incoming_ident = format_ident!("Incoming{}", original_ident, span = Span::call_site());
 
+    let incoming_generics = strip_lifetimes(&input.generics);
+    let (_, incoming_ty_generics, incoming_where_clause) = incoming_generics.split_for_impl();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
     let struct_def = match struct_kind {
-        StructKind::Struct => quote! { { #(#fields,)* } },
-        StructKind::Tuple => quote! { ( #(#fields,)* ); },
+        StructKind::Struct => quote! { #incoming_where_clause { #(#fields,)* } },
+        StructKind::Tuple => quote! { ( #(#fields,)* ) #incoming_where_clause; },
+    };
+
+    let impl_try_from_incoming = if try_into_outgoing {
+        let init = match struct_kind {
+            StructKind::Struct => {
+                let members = fields.iter().map(|field| &field.ident);
+                quote! { Self { #(#members: #field_conversions,)* } }
+            }
+            StructKind::Tuple => quote! { Self( #(#field_conversions,)* ) },
+        };
+
+        quote! {
+            impl #impl_generics ::std::convert::TryFrom<#incoming_ident #incoming_ty_generics>
+                for #original_ident #ty_generics #where_clause
+            {
+                type Error = ruma_api::error::FromIncomingError;
+
+                fn try_from(
+                    incoming: #incoming_ident #incoming_ty_generics,
+                ) -> Result<Self, Self::Error> {
+                    Ok(#init)
+                }
+            }
+        }
+    } else {
+        TokenStream::new()
     };
 
     Ok(quote! {
         #[doc = #doc]
-        #[derive(Debug, #derive_deserialize)]
-        #vis struct #incoming_ident #struct_def
+        #[derive(#derive_debug #derive_deserialize)]
+        #vis struct #incoming_ident #incoming_generics #struct_def
 
-        impl ruma_api::Outgoing for #original_ident {
-            type Incoming = #incoming_ident;
+        impl #impl_generics ruma_api::Outgoing for #original_ident #ty_generics #where_clause {
+            type Incoming = #incoming_ident #incoming_ty_generics;
         }
+
+        #impl_try_from_incoming
     })
 }
 
-fn no_deserialize_in_attrs(attrs: &[Attribute]) -> bool {
+fn has_attr(attrs: &[Attribute], name: &str) -> bool {
     for attr in attrs {
         match &attr.path {
             Path { leading_colon: None, segments }
-                if segments.len() == 1 && segments[0].ident == "incoming_no_deserialize" =>
+                if segments.len() == 1 && segments[0].ident == name =>
             {
                 return true
             }
@@ -116,14 +193,158 @@ fn no_deserialize_in_attrs(attrs: &[Attribute]) -> bool {
     false
 }
 
-fn impl_outgoing_with_incoming_self(ident: Ident) -> TokenStream {
+fn impl_outgoing_with_incoming_self(ident: &Ident, generics: &Generics) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
     quote! {
-        impl ruma_api::Outgoing for #ident {
+        impl #impl_generics ruma_api::Outgoing for #ident #ty_generics #where_clause {
             type Incoming = Self;
         }
     }
 }
 
+/// Removes all lifetime parameters, as well as the bounds and where clause predicates that refer to
+/// them, e.g. `T: 'a`.
+fn strip_lifetimes(generics: &Generics) -> Generics {
+    let mut generics = generics.clone();
+    let lifetimes: Vec<Lifetime> = generics.lifetimes().map(|def| def.lifetime.clone()).collect();
+
+    generics.params = mem::take(&mut generics.params)
+        .into_pairs()
+        .filter(|pair| !matches!(pair.value(), GenericParam::Lifetime(_)))
+        .collect();
+
+    for param in generics.type_params_mut() {
+        strip_lifetime_bounds(&mut param.bounds, &lifetimes);
+    }
+
+    if let Some(where_clause) = &mut generics.where_clause {
+        where_clause.predicates = mem::take(&mut where_clause.predicates)
+            .into_iter()
+            .filter_map(|mut predicate| {
+                match &mut predicate {
+                    WherePredicate::Lifetime(_) => return None,
+                    WherePredicate::Type(predicate) => {
+                        if mentions_lifetime(predicate.bounded_ty.to_token_stream(), &lifetimes) {
+                            return None;
+                        }
+
+                        strip_lifetime_bounds(&mut predicate.bounds, &lifetimes);
+                        if predicate.bounds.is_empty() {
+                            return None;
+                        }
+                    }
+                    WherePredicate::Eq(predicate) => {
+                        if mentions_lifetime(predicate.to_token_stream(), &lifetimes) {
+                            return None;
+                        }
+                    }
+                }
+
+                Some(predicate)
+            })
+            .collect();
+    }
+
+    generics
+}
+
+/// Removes the bounds that refer to one of the given lifetimes.
+fn strip_lifetime_bounds(bounds: &mut Punctuated<TypeParamBound, Add>, lifetimes: &[Lifetime]) {
+    *bounds = mem::take(bounds)
+        .into_iter()
+        .filter(|bound| !mentions_lifetime(bound.to_token_stream(), lifetimes))
+        .collect();
+}
+
+/// Whether the given tokens contain one of the given lifetimes.
+fn mentions_lifetime(tokens: TokenStream, lifetimes: &[Lifetime]) -> bool {
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                if let Some(TokenTree::Ident(ident)) = tokens.peek() {
+                    if lifetimes.iter().any(|lifetime| lifetime.ident == *ident) {
+                        return true;
+                    }
+                }
+            }
+            TokenTree::Group(group) if mentions_lifetime(group.stream(), lifetimes) => {
+                return true;
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
+
+fn contains_lifetime(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Punct(punct) => punct.as_char() == '\'',
+        TokenTree::Group(group) => contains_lifetime(group.stream()),
+        _ => false,
+    })
+}
+
+/// Replaces borrowed types with their owned equivalents: `&str` becomes `String`, `&[T]` becomes
+/// `Vec<T>` and `&T` becomes `T`. Lifetime arguments of other types are removed.
+fn to_owned_ty(ty: &mut Type) -> syn::Result<()> {
+    match ty {
+        Type::Reference(TypeReference { elem, .. }) => {
+            let mut elem = (**elem).clone();
+            to_owned_ty(&mut elem)?;
+
+            *ty = match elem {
+                Type::Path(TypePath { qself: None, path }) if path.is_ident("str") => {
+                    parse_quote!(String)
+                }
+                Type::Slice(TypeSlice { elem, .. }) => parse_quote!(Vec<#elem>),
+                elem => elem,
+            };
+        }
+        Type::Path(TypePath { path, .. }) => {
+            for segment in &mut path.segments {
+                if let PathArguments::AngleBracketed(ab) = &mut segment.arguments {
+                    ab.args = mem::take(&mut ab.args)
+                        .into_pairs()
+                        .filter(|pair| !matches!(pair.value(), GenericArgument::Lifetime(_)))
+                        .collect();
+
+                    for arg in &mut ab.args {
+                        if let GenericArgument::Type(ty) = arg {
+                            to_owned_ty(ty)?;
+                        }
+                    }
+
+                    if ab.args.is_empty() {
+                        segment.arguments = PathArguments::None;
+                    }
+                }
+            }
+        }
+        Type::Slice(TypeSlice { elem, .. })
+        | Type::Array(TypeArray { elem, .. })
+        | Type::Paren(TypeParen { elem, .. })
+        | Type::Group(TypeGroup { elem, .. }) => to_owned_ty(elem)?,
+        Type::Tuple(TypeTuple { elems, .. }) => {
+            for elem in elems {
+                to_owned_ty(elem)?;
+            }
+        }
+        _ => {
+            if contains_lifetime(ty.to_token_stream()) {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "Can't convert this type to an owned type",
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn wrap_ty(ty: &mut Type, path: Option<Path>) -> syn::Result<()> {
     if let Some(wrap_ty) = path {
         *ty = parse_quote!(#wrap_ty<#ty>);
@@ -71,9 +71,46 @@ pub fn ruma_api(input: TokenStream) -> TokenStream {
 ///     pub ys: Vec<EventResult<YEvent>>,
 /// }
 /// ```
+///
+/// Generic type parameters are carried over to the 'Incoming' type. Since the 'Incoming' type is
+/// meant to be deserialized into, it can't borrow any data, so if the type has lifetime
+/// parameters, an 'Incoming' type is always generated, with lifetimes removed and references
+/// replaced by their owned equivalents:
+///
+/// ```ignore
+/// #[derive(Outgoing)]
+/// struct MyRequest<'a> {
+///     pub data: &'a str,
+///     pub list: &'a [u32],
+/// }
+///
+/// // generated
+/// struct IncomingMyRequest {
+///     pub data: String,
+///     pub list: Vec<u32>,
+/// }
+/// ```
+///
+/// Proxies that parse an incoming request and send it on need to turn the 'Incoming' type back
+/// into the original one. With `#[incoming_try_into_outgoing]` on the struct, the derive macro
+/// generates `TryFrom<IncomingT> for T` for this. Fields with `#[wrap_incoming]` or
+/// `#[wrap_incoming(with Wrapper)]` are converted with their own `TryFrom` implementation, e.g.
+/// `Bar: TryFrom<IncomingBar>` (which `#[incoming_try_into_outgoing]` on `Bar` provides), and the
+/// conversion fails with a `ruma_api::error::FromIncomingError` if any of them fails. This isn't
+/// supported for types with lifetime parameters, since the owned 'Incoming' type can't be borrowed
+/// from, nor for fields with `#[wrap_incoming(Type ...)]`. Types without an 'Incoming' variant
+/// don't need it: `Incoming = Self`, so the conversion is the identity.
 // TODO: Make it clear that `#[wrap_incoming]` and `#[wrap_incoming(Type)]` without the "with" part
 // are (only) useful for fallible deserialization of nested structures.
-#[proc_macro_derive(Outgoing, attributes(wrap_incoming, incoming_no_deserialize))]
+#[proc_macro_derive(
+    Outgoing,
+    attributes(
+        wrap_incoming,
+        incoming_no_deserialize,
+        incoming_no_debug,
+        incoming_try_into_outgoing
+    )
+)]
 pub fn derive_outgoing(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     expand_derive_outgoing(input).unwrap_or_else(|err| err.to_compile_error()).into()
@@ -0,0 +1,53 @@
+//! Support for application services acting on behalf of users.
+//!
+//! Application services authenticate by passing their token in the `access_token` query
+//! parameter, and can assert the identity of a user in their namespace with the `user_id` query
+//! parameter. Endpoints that support this have `Metadata::appservice` set, and transports can use
+//! [`add_identity_assertion`](fn.add_identity_assertion.html) to add the query parameters to an
+//! `http::Request` generated for such an endpoint. It refuses to do so for other endpoints.
+
+use std::convert::TryFrom;
+
+use http::uri::{PathAndQuery, Uri};
+
+use crate::{auth::AccessToken, error::IntoHttpError, query, Metadata};
+
+/// Adds the `access_token` and (optionally) `user_id` query parameters to the given request for the
+/// endpoint described by `metadata`.
+///
+/// Any existing query parameters of the request are kept. Returns an error if the endpoint doesn't
+/// have `Metadata::appservice` set, since other endpoints don't accept an identity assertion.
+pub fn add_identity_assertion<T>(
+    http_request: &mut http::Request<T>,
+    metadata: &Metadata,
+    access_token: &AccessToken,
+    user_id: Option<&str>,
+) -> Result<(), IntoHttpError> {
+    if !metadata.appservice {
+        return Err(IntoHttpError::not_appservice(metadata.name));
+    }
+
+    let mut params = vec![("access_token", access_token.as_str())];
+    if let Some(user_id) = user_id {
+        params.push(("user_id", user_id));
+    }
+    let added_query = query::to_string(params)?;
+
+    let mut parts = http_request.uri().clone().into_parts();
+    let path_and_query = match parts.path_and_query.as_ref() {
+        Some(pq) => match pq.query() {
+            Some(query) if !query.is_empty() => {
+                format!("{}?{}&{}", pq.path(), query, added_query)
+            }
+            _ => format!("{}?{}", pq.path(), added_query),
+        },
+        None => format!("/?{}", added_query),
+    };
+
+    // Both parts are valid already and the added query is percent-encoded, so the result is
+    // always valid as well.
+    parts.path_and_query = Some(PathAndQuery::try_from(path_and_query.as_str()).unwrap());
+    *http_request.uri_mut() = Uri::from_parts(parts).unwrap();
+
+    Ok(())
+}
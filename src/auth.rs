@@ -0,0 +1,202 @@
+//! Types and helpers for authenticating requests.
+
+use std::{
+    convert::Infallible,
+    fmt::{self, Debug, Display, Formatter},
+    str::FromStr,
+};
+
+use http::header::{HeaderValue, AUTHORIZATION};
+
+use crate::error::IntoHttpError;
+
+/// An access token, used to authenticate requests to endpoints that require authentication.
+///
+/// Unlike a plain `String`, this type can't be mixed up with other string parameters like user
+/// IDs, and its `Debug` output doesn't contain the token, so it doesn't end up in logs by accident.
+/// Use `as_str` or the `Display` implementation to get at the token itself.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct AccessToken(String);
+
+impl AccessToken {
+    /// Creates a new `AccessToken` from the given token.
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+
+    /// The token as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Debug for AccessToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("AccessToken(<redacted>)")
+    }
+}
+
+impl Display for AccessToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for AccessToken {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+/// Adds an `Authorization: Bearer` header with the given access token to the given request.
+///
+/// Does nothing if `access_token` is `None`, so transports can pass through the token of an
+/// optionally authenticated client. Any existing `Authorization` header is replaced.
+pub fn add_bearer_token<T>(
+    http_request: &mut http::Request<T>,
+    access_token: Option<&AccessToken>,
+) -> Result<(), IntoHttpError> {
+    if let Some(access_token) = access_token {
+        let value = HeaderValue::from_str(&format!("Bearer {}", access_token.as_str()))?;
+        http_request.headers_mut().insert(AUTHORIZATION, value);
+    }
+
+    Ok(())
+}
+
+/// The value of an `Authorization: X-Matrix ...` header, used to authenticate server-server
+/// requests.
+///
+/// The `Display` and `FromStr` implementations produce and parse the full header value, including
+/// the `X-Matrix` scheme, so this type can be used for a request field with
+/// `#[ruma_api(header = AUTHORIZATION)]`. Creating and verifying the signature is left to the
+/// caller.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XMatrixAuth {
+    /// The server name of the sending server.
+    pub origin: String,
+
+    /// The server name of the receiving server, if included.
+    pub destination: Option<String>,
+
+    /// The ID of the signing key used, e.g. `ed25519:key1`.
+    pub key_id: String,
+
+    /// The unpadded base64 signature of the request.
+    pub signature: String,
+}
+
+impl Display for XMatrixAuth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "X-Matrix origin={}", Quoted(&self.origin))?;
+        if let Some(destination) = &self.destination {
+            write!(f, ",destination={}", Quoted(destination))?;
+        }
+        write!(f, ",key={},sig={}", Quoted(&self.key_id), Quoted(&self.signature))
+    }
+}
+
+impl FromStr for XMatrixAuth {
+    type Err = XMatrixParseError;
+
+    /// Parses an `X-Matrix` header value.
+    ///
+    /// The scheme and parameter names are matched case-insensitively, parameter values may be
+    /// quoted or not, whitespace around the separating commas is ignored and unknown parameters
+    /// are skipped.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_start();
+        let scheme_end = s.find(char::is_whitespace).unwrap_or(s.len());
+        if !s[..scheme_end].eq_ignore_ascii_case("X-Matrix") {
+            return Err(XMatrixParseError("expected the `X-Matrix` scheme"));
+        }
+
+        let mut origin = None;
+        let mut destination = None;
+        let mut key_id = None;
+        let mut signature = None;
+
+        let mut rest = s[scheme_end..].trim_start();
+        while !rest.is_empty() {
+            let name_end = rest.find('=').ok_or(XMatrixParseError("expected `=` after name"))?;
+            let name = rest[..name_end].trim();
+            let (value, remaining) = parse_param_value(rest[name_end + 1..].trim_start())?;
+
+            match &name.to_ascii_lowercase()[..] {
+                "origin" => origin = Some(value),
+                "destination" => destination = Some(value),
+                "key" => key_id = Some(value),
+                "sig" => signature = Some(value),
+                _ => {}
+            }
+
+            rest = remaining.trim_start();
+            if rest.starts_with(',') {
+                rest = rest[1..].trim_start();
+            } else if !rest.is_empty() {
+                return Err(XMatrixParseError("expected `,` between parameters"));
+            }
+        }
+
+        Ok(Self {
+            origin: origin.ok_or(XMatrixParseError("missing `origin` parameter"))?,
+            destination,
+            key_id: key_id.ok_or(XMatrixParseError("missing `key` parameter"))?,
+            signature: signature.ok_or(XMatrixParseError("missing `sig` parameter"))?,
+        })
+    }
+}
+
+/// Parses a quoted or unquoted parameter value from the start of `s`, returning it along with the
+/// rest of the input.
+fn parse_param_value(s: &str) -> Result<(String, &str), XMatrixParseError> {
+    if !s.starts_with('"') {
+        let end = s.find(',').unwrap_or(s.len());
+        return Ok((s[..end].trim_end().to_owned(), &s[end..]));
+    }
+
+    let mut value = String::new();
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &s[i + 1..])),
+            '\\' => match chars.next() {
+                Some((_, escaped)) => value.push(escaped),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+
+    Err(XMatrixParseError("unterminated quoted value"))
+}
+
+/// Displays a string as a quoted parameter value, escaping quotes and backslashes.
+struct Quoted<'a>(&'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("\"")?;
+        for c in self.0.chars() {
+            if c == '"' || c == '\\' {
+                f.write_str("\\")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        f.write_str("\"")
+    }
+}
+
+/// An error when parsing an `X-Matrix` authorization header value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XMatrixParseError(&'static str);
+
+impl Display for XMatrixParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid X-Matrix authorization: {}", self.0)
+    }
+}
+
+impl std::error::Error for XMatrixParseError {}
@@ -0,0 +1,42 @@
+//! Functions used by `assert_endpoint!` to check the bounds of the `Endpoint` trait one by one.
+//!
+//! Each function checks a single bound, so that a missing conversion results in an error that
+//! names the offending type and trait instead of an error about the `Endpoint` trait as a whole.
+//!
+//! This module is public so it is accessible from `assert_endpoint!`. It is not considered part
+//! of ruma-api's public API.
+
+use std::convert::{TryFrom, TryInto};
+
+use crate::{
+    error::{FromHttpRequestError, FromHttpResponseError, IntoHttpError},
+    Outgoing,
+};
+
+pub fn request_implements_outgoing<T: Outgoing>() {}
+
+pub fn request_converts_into_http_request<T>()
+where
+    T: TryInto<http::Request<Vec<u8>>, Error = IntoHttpError>,
+{
+}
+
+pub fn incoming_request_converts_from_http_request<T>()
+where
+    T: TryFrom<http::Request<Vec<u8>>, Error = FromHttpRequestError>,
+{
+}
+
+pub fn response_implements_outgoing<T: Outgoing>() {}
+
+pub fn response_converts_into_http_response<T>()
+where
+    T: TryInto<http::Response<Vec<u8>>, Error = IntoHttpError>,
+{
+}
+
+pub fn incoming_response_converts_from_http_response<T, E>()
+where
+    T: TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError<E>>,
+{
+}
@@ -0,0 +1,61 @@
+//! Serialization of request and response bodies as [canonical JSON].
+//!
+//! Federation requests are signed over the canonical JSON form of their body, in which object keys
+//! are sorted and there is no insignificant whitespace. Endpoints with `canonical_json: true` in
+//! their metadata serialize their bodies through this module, so the bytes sent are exactly the
+//! bytes that were signed. Incoming bodies are parsed as regular JSON.
+//!
+//! [canonical JSON]: https://matrix.org/docs/spec/appendices#canonical-json
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Serializes the given value as a canonical JSON byte vector.
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, serde_json::Error> {
+    let value = serde_json::to_value(value)?;
+    let mut buf = Vec::new();
+    write_value(&mut buf, &value)?;
+
+    Ok(buf)
+}
+
+/// Serializes the given value as a canonical JSON string.
+pub fn to_string<T: Serialize>(value: &T) -> Result<String, serde_json::Error> {
+    // `to_vec` only ever produces valid UTF-8.
+    to_vec(value).map(|buf| String::from_utf8(buf).expect("canonical JSON is valid UTF-8"))
+}
+
+fn write_value(buf: &mut Vec<u8>, value: &Value) -> Result<(), serde_json::Error> {
+    match value {
+        Value::Array(values) => {
+            buf.push(b'[');
+            for (i, value) in values.iter().enumerate() {
+                if i != 0 {
+                    buf.push(b',');
+                }
+                write_value(buf, value)?;
+            }
+            buf.push(b']');
+        }
+        Value::Object(map) => {
+            // Don't rely on the iteration order of `serde_json::Map`, which depends on whether
+            // serde_json's `preserve_order` feature is enabled anywhere in the dependency graph.
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+
+            buf.push(b'{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i != 0 {
+                    buf.push(b',');
+                }
+                serde_json::to_writer(&mut *buf, key)?;
+                buf.push(b':');
+                write_value(buf, value)?;
+            }
+            buf.push(b'}');
+        }
+        _ => serde_json::to_writer(&mut *buf, value)?,
+    }
+
+    Ok(())
+}
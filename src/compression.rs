@@ -0,0 +1,108 @@
+//! Decompression of response bodies according to their `Content-Encoding` header.
+//!
+//! Responses converted by `ruma_api!` generated code are passed through `decompress_response`
+//! before their body is parsed. `gzip` and `deflate` bodies can only be decompressed with the
+//! `compression` feature; without it, or for other encodings, the conversion fails with an error
+//! naming the encoding instead of a confusing JSON error.
+
+use http::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+
+use crate::error::{DeserializationError, ResponseDeserializationError};
+
+/// Decompresses the body of the given response according to its `Content-Encoding` header.
+///
+/// If the body was decompressed, the `Content-Encoding` and `Content-Length` headers are removed.
+/// Responses without a `Content-Encoding` header or with the `identity` encoding are returned
+/// unchanged.
+// The error contains the response, like all errors of response conversions.
+#[allow(clippy::result_large_err)]
+pub fn decompress_response(
+    mut response: http::Response<Vec<u8>>,
+) -> Result<http::Response<Vec<u8>>, ResponseDeserializationError> {
+    let encodings: Vec<String> = match response.headers().get(CONTENT_ENCODING) {
+        Some(value) => match value.to_str() {
+            Ok(value) => value
+                .split(',')
+                .map(|encoding| encoding.trim().to_ascii_lowercase())
+                .filter(|encoding| !encoding.is_empty() && encoding != "identity")
+                .collect(),
+            Err(_) => {
+                return Err(ResponseDeserializationError::new(
+                    DeserializationError::ContentEncoding(
+                        "invalid Content-Encoding header".to_owned(),
+                    ),
+                    response,
+                ));
+            }
+        },
+        None => return Ok(response),
+    };
+
+    if encodings.is_empty() {
+        return Ok(response);
+    }
+
+    // Encodings are listed in the order they were applied, so they are undone in reverse. The
+    // error contains the response with its original body.
+    let mut body = None;
+    for encoding in encodings.iter().rev() {
+        match decode(encoding, body.as_deref().unwrap_or(response.body())) {
+            Ok(decoded) => body = Some(decoded),
+            Err(message) => {
+                return Err(ResponseDeserializationError::new(
+                    DeserializationError::ContentEncoding(message),
+                    response,
+                ));
+            }
+        }
+    }
+
+    response.headers_mut().remove(CONTENT_ENCODING);
+    response.headers_mut().remove(CONTENT_LENGTH);
+    *response.body_mut() = body.expect("at least one encoding was decoded");
+
+    Ok(response)
+}
+
+/// Whether `decompress_response` would change the given response or fail, i.e. whether it has a
+/// `Content-Encoding` header other than `identity`.
+pub(crate) fn is_encoded(response: &http::Response<Vec<u8>>) -> bool {
+    response.headers().get(CONTENT_ENCODING).is_some_and(|value| {
+        value.to_str().map_or(true, |value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|encoding| !encoding.is_empty() && !encoding.eq_ignore_ascii_case("identity"))
+        })
+    })
+}
+
+#[cfg(feature = "compression")]
+fn decode(encoding: &str, body: &[u8]) -> Result<Vec<u8>, String> {
+    use std::io::Read as _;
+
+    use flate2::read::{GzDecoder, ZlibDecoder};
+
+    let mut decoded = Vec::new();
+    let result = match encoding {
+        "gzip" | "x-gzip" => GzDecoder::new(body).read_to_end(&mut decoded),
+        "deflate" => ZlibDecoder::new(body).read_to_end(&mut decoded),
+        _ => return Err(format!("unsupported content encoding `{}`", encoding)),
+    };
+
+    match result {
+        Ok(_) => Ok(decoded),
+        Err(err) => Err(format!("failed to decompress `{}` body: {}", encoding, err)),
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+fn decode(encoding: &str, _body: &[u8]) -> Result<Vec<u8>, String> {
+    match encoding {
+        "gzip" | "x-gzip" | "deflate" => Err(format!(
+            "content encoding `{}` is only supported with the `compression` feature of ruma-api",
+            encoding,
+        )),
+        _ => Err(format!("unsupported content encoding `{}`", encoding)),
+    }
+}
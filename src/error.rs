@@ -2,7 +2,15 @@
 //! converting between http requests / responses and ruma's representation of
 //! matrix API requests / responses.
 
-use std::fmt::{self, Display, Formatter};
+use std::{
+    any::Any,
+    fmt::{self, Debug, Display, Formatter},
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{header::Challenge, uiaa::UiaaResponse, MatrixError, MatrixErrorKind};
 
 /// An error when converting one of ruma's endpoint-specific request or response
 /// types to the corresponding http type.
@@ -23,9 +31,68 @@ impl From<serde_urlencoded::ser::Error> for IntoHttpError {
     }
 }
 
+#[doc(hidden)]
+impl From<http::header::InvalidHeaderValue> for IntoHttpError {
+    fn from(err: http::header::InvalidHeaderValue) -> Self {
+        Self(SerializationError::Header(err))
+    }
+}
+
+impl IntoHttpError {
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn cannot_be_a_base_url() -> Self {
+        Self(SerializationError::CannotBeABaseUrl)
+    }
+
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn invalid_base_url(err: impl Display) -> Self {
+        Self(SerializationError::InvalidBaseUrl(err.to_string()))
+    }
+
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn missing_access_token() -> Self {
+        Self(SerializationError::MissingAccessToken)
+    }
+
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn missing_transaction_id() -> Self {
+        Self(SerializationError::MissingTransactionId)
+    }
+
+    pub(crate) fn not_appservice(endpoint_name: &'static str) -> Self {
+        Self(SerializationError::NotAppservice(endpoint_name))
+    }
+}
+
 impl Display for IntoHttpError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match &self.0 {
+            SerializationError::CannotBeABaseUrl => {
+                write!(f, "The base URL can't have a path appended to it")
+            }
+            SerializationError::InvalidBaseUrl(err) => write!(f, "Invalid base URL: {}", err),
+            SerializationError::MissingAccessToken => {
+                write!(f, "This endpoint has to be accessed with an access token")
+            }
+            SerializationError::MissingTransactionId => write!(
+                f,
+                "The transaction ID of the request has to be set, e.g. with \
+                 `Endpoint::ensure_transaction_id`"
+            ),
+            SerializationError::NotAppservice(name) => write!(
+                f,
+                "The `{}` endpoint doesn't support application service identity assertion",
+                name
+            ),
+            SerializationError::Header(err) => write!(f, "Invalid header value: {}", err),
             SerializationError::Json(err) => write!(f, "JSON serialization failed: {}", err),
             SerializationError::Query(err) => {
                 write!(f, "Query parameter serialization failed: {}", err)
@@ -40,15 +107,65 @@ impl std::error::Error for IntoHttpError {}
 /// request types.
 #[derive(Debug)]
 #[non_exhaustive]
+#[allow(clippy::large_enum_variant)]
 pub enum FromHttpRequestError {
     /// Deserialization failed
     Deserialization(RequestDeserializationError),
+
+    /// The request body is larger than the endpoint's `max_body_size`.
+    TooLarge(BodyTooLargeError),
+}
+
+impl FromHttpRequestError {
+    /// The name of the endpoint the request was parsed for, if known.
+    ///
+    /// This is set for all errors returned by the conversions generated by `ruma_api!`.
+    pub fn endpoint_name(&self) -> Option<&str> {
+        match self {
+            Self::Deserialization(err) => err.endpoint_name,
+            Self::TooLarge(err) => err.endpoint_name,
+        }
+    }
+
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn with_endpoint_name(mut self, endpoint_name: &'static str) -> Self {
+        match &mut self {
+            Self::Deserialization(err) => err.endpoint_name = Some(endpoint_name),
+            Self::TooLarge(err) => err.endpoint_name = Some(endpoint_name),
+        }
+
+        self
+    }
+
+    /// Replaces the request stored in the error by the complete request, for errors that were
+    /// created with only its head or only its body.
+    ///
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn with_http_request(mut self, parts: http::request::Parts, body: Vec<u8>) -> Self {
+        match &mut self {
+            Self::Deserialization(err) => err.http_request = http::Request::from_parts(parts, body),
+            Self::TooLarge(_) => {}
+        }
+
+        self
+    }
 }
 
 impl Display for FromHttpRequestError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Deserialization(err) => write!(f, "deserialization failed: {}", err),
+            Self::Deserialization(err) => match err.endpoint_name {
+                Some(name) => write!(f, "deserialization failed for endpoint `{}`: {}", name, err),
+                None => write!(f, "deserialization failed: {}", err),
+            },
+            Self::TooLarge(err) => match err.endpoint_name {
+                Some(name) => write!(f, "request to endpoint `{}` rejected: {}", name, err),
+                None => write!(f, "request rejected: {}", err),
+            },
         }
     }
 }
@@ -59,6 +176,55 @@ impl From<RequestDeserializationError> for FromHttpRequestError {
     }
 }
 
+impl From<BodyTooLargeError> for FromHttpRequestError {
+    fn from(err: BodyTooLargeError) -> Self {
+        Self::TooLarge(err)
+    }
+}
+
+/// An error when the body of a request is larger than the `max_body_size` of its endpoint.
+///
+/// Servers usually respond to this with an `M_TOO_LARGE` error and status `413 Payload Too Large`.
+#[derive(Clone, Copy, Debug)]
+pub struct BodyTooLargeError {
+    size: u64,
+    limit: u64,
+    endpoint_name: Option<&'static str>,
+}
+
+impl BodyTooLargeError {
+    /// Returns an error if `size` exceeds `limit`.
+    ///
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn check(size: u64, limit: u64) -> Result<(), Self> {
+        if size > limit {
+            Err(Self { size, limit, endpoint_name: None })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The size of the body in bytes, as announced by its `Content-Length` header or as received.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The maximum body size of the endpoint in bytes.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+}
+
+impl Display for BodyTooLargeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "body of {} bytes exceeds the limit of {} bytes", self.size, self.limit)
+    }
+}
+
+impl std::error::Error for BodyTooLargeError {}
+
 impl std::error::Error for FromHttpRequestError {}
 
 /// An error that occurred when trying to deserialize a request.
@@ -66,6 +232,7 @@ impl std::error::Error for FromHttpRequestError {}
 pub struct RequestDeserializationError {
     inner: DeserializationError,
     http_request: http::Request<Vec<u8>>,
+    endpoint_name: Option<&'static str>,
 }
 
 impl RequestDeserializationError {
@@ -76,7 +243,104 @@ impl RequestDeserializationError {
         inner: impl Into<DeserializationError>,
         http_request: http::Request<Vec<u8>>,
     ) -> Self {
-        Self { inner: inner.into(), http_request }
+        Self { inner: inner.into(), http_request, endpoint_name: None }
+    }
+
+    /// Creates an error for a request of which only the head is available.
+    ///
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn from_parts(
+        inner: impl Into<DeserializationError>,
+        parts: &http::request::Parts,
+    ) -> Self {
+        let mut http_request = http::Request::new(Vec::new());
+        *http_request.method_mut() = parts.method.clone();
+        *http_request.uri_mut() = parts.uri.clone();
+        *http_request.version_mut() = parts.version;
+        *http_request.headers_mut() = parts.headers.clone();
+
+        Self { inner: inner.into(), http_request, endpoint_name: None }
+    }
+
+    /// The error that occurred while deserializing the request.
+    ///
+    /// For errors of a JSON body, this is a `serde_json::Error`, and for errors of the query string
+    /// a `serde_urlencoded::de::Error`, which can be accessed with `downcast_ref`.
+    pub fn error(&self) -> &(dyn std::error::Error + 'static) {
+        self.inner.as_dyn_error()
+    }
+
+    /// The request that failed to deserialize.
+    ///
+    /// This is the complete request for errors returned by the `TryFrom<http::Request<_>>`
+    /// conversion. Errors returned by `Request::try_from_parts` only have the head of the
+    /// request, and errors returned by `PartialRequest::complete` only have its body.
+    pub fn http_request(&self) -> &http::Request<Vec<u8>> {
+        &self.http_request
+    }
+
+    /// Consumes the error, returning the underlying error and the request that failed to
+    /// deserialize.
+    ///
+    /// This allows servers to handle the request differently, e.g. forward it to another handler,
+    /// instead of dropping it. See `http_request` for which parts of the request are available.
+    pub fn into_inner(self) -> (Box<dyn std::error::Error + Send + Sync>, http::Request<Vec<u8>>) {
+        (self.inner.into_boxed_error(), self.http_request)
+    }
+
+    /// The line of the request body at which JSON deserialization failed, starting at 1.
+    ///
+    /// Returns `None` if the error didn't occur while deserializing the JSON body.
+    pub fn line(&self) -> Option<usize> {
+        match &self.inner {
+            DeserializationError::Json(err) => Some(err.line()),
+            DeserializationError::Query(_)
+            | DeserializationError::Path(_)
+            | DeserializationError::Header(_)
+            | DeserializationError::ContentEncoding(_)
+            | DeserializationError::Utf8(_)
+            | DeserializationError::Multipart(_) => None,
+        }
+    }
+
+    /// The column of the request body at which JSON deserialization failed, starting at 1.
+    ///
+    /// Returns `None` if the error didn't occur while deserializing the JSON body.
+    pub fn column(&self) -> Option<usize> {
+        match &self.inner {
+            DeserializationError::Json(err) => Some(err.column()),
+            DeserializationError::Query(_)
+            | DeserializationError::Path(_)
+            | DeserializationError::Header(_)
+            | DeserializationError::ContentEncoding(_)
+            | DeserializationError::Utf8(_)
+            | DeserializationError::Multipart(_) => None,
+        }
+    }
+
+    /// A part of the request body around the position at which JSON deserialization failed.
+    ///
+    /// Returns `None` if the error didn't occur while deserializing the JSON body, or if the body
+    /// is not valid UTF-8.
+    pub fn snippet(&self) -> Option<&str> {
+        const CONTEXT: usize = 20;
+
+        let body = std::str::from_utf8(self.http_request.body()).ok()?;
+        let line = body.lines().nth(self.line()?.checked_sub(1)?)?;
+        let column = self.column()?.min(line.len());
+
+        let mut start = column.saturating_sub(CONTEXT);
+        while !line.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = (column + CONTEXT).min(line.len());
+        while !line.is_char_boundary(end) {
+            end += 1;
+        }
+
+        Some(&line[start..end])
     }
 }
 
@@ -90,36 +354,93 @@ impl std::error::Error for RequestDeserializationError {}
 
 /// An error when converting a http response to one of ruma's endpoint-specific
 /// response types.
+///
+/// `E` is the type of the error bodies returned by the endpoint, `MatrixError` unless the endpoint
+/// declares another type in its metadata.
+///
+/// `Transport` means that no response was received, so the request may or may not have reached the
+/// server. All other variants mean that the server did respond, and retrying the request as-is is
+/// unlikely to help unless the server's error says otherwise (e.g. `M_LIMIT_EXCEEDED`).
 #[derive(Debug)]
 #[non_exhaustive]
-pub enum FromHttpResponseError {
+pub enum FromHttpResponseError<E = MatrixError> {
     /// Deserialization failed
     Deserialization(ResponseDeserializationError),
     /// The server returned a non-success status
-    Http(ServerError),
+    Http(ServerError<E>),
+    /// The server returned a success status other than the one declared by the endpoint
+    UnexpectedStatus(UnexpectedStatusError),
+    /// The server returned `304 Not Modified` in response to a conditional request, so the
+    /// previously received response is still up to date
+    NotModified,
+    /// The server returned `412 Precondition Failed` in response to a conditional request, e.g.
+    /// because the resource was changed since the `ETag` sent in an `If-Match` header was received
+    PreconditionFailed(ServerError<E>),
+    /// The server returned `401 Unauthorized` asking for user-interactive authentication, i.e. the
+    /// client has to complete an authentication stage and retry the request
+    Uiaa(UiaaResponse),
+    /// No response was received because of a network error or a timeout
+    ///
+    /// ruma-api doesn't send requests itself; this variant is constructed by HTTP client
+    /// integrations.
+    Transport(TransportError),
 }
 
-impl Display for FromHttpResponseError {
+impl<E> FromHttpResponseError<E> {
+    /// The HTTP status of the response, if one was received.
+    ///
+    /// This is `None` only for `Transport` errors. It can be used to decide whether to retry a
+    /// request without matching on every variant.
+    pub fn status(&self) -> Option<http::StatusCode> {
+        match self {
+            Self::Deserialization(err) => Some(err.http_response.status()),
+            Self::Http(err) | Self::PreconditionFailed(err) => Some(err.status()),
+            Self::UnexpectedStatus(err) => Some(err.http_response.status()),
+            Self::NotModified => Some(http::StatusCode::NOT_MODIFIED),
+            Self::Uiaa(_) => Some(http::StatusCode::UNAUTHORIZED),
+            Self::Transport(_) => None,
+        }
+    }
+}
+
+impl<E> Display for FromHttpResponseError<E> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Self::Deserialization(err) => write!(f, "deserialization failed: {}", err),
             Self::Http(err) => write!(f, "the server returned an error: {}", err),
+            Self::UnexpectedStatus(err) => write!(f, "unexpected response status: {}", err),
+            Self::NotModified => write!(f, "the resource was not modified"),
+            Self::PreconditionFailed(_) => write!(f, "a precondition of the request failed"),
+            Self::Uiaa(_) => write!(f, "user-interactive authentication is required"),
+            Self::Transport(err) => write!(f, "no response received: {}", err),
         }
     }
 }
 
-impl From<ServerError> for FromHttpResponseError {
-    fn from(err: ServerError) -> Self {
+impl<E> From<ServerError<E>> for FromHttpResponseError<E> {
+    fn from(err: ServerError<E>) -> Self {
         Self::Http(err)
     }
 }
 
-impl From<ResponseDeserializationError> for FromHttpResponseError {
+impl<E> From<UnexpectedStatusError> for FromHttpResponseError<E> {
+    fn from(err: UnexpectedStatusError) -> Self {
+        Self::UnexpectedStatus(err)
+    }
+}
+
+impl<E> From<ResponseDeserializationError> for FromHttpResponseError<E> {
     fn from(err: ResponseDeserializationError) -> Self {
         Self::Deserialization(err)
     }
 }
 
+impl<E> From<TransportError> for FromHttpResponseError<E> {
+    fn from(err: TransportError) -> Self {
+        Self::Transport(err)
+    }
+}
+
 /// An error that occurred when trying to deserialize a response.
 #[derive(Debug)]
 pub struct ResponseDeserializationError {
@@ -137,6 +458,16 @@ impl ResponseDeserializationError {
     ) -> Self {
         Self { inner: inner.into(), http_response }
     }
+
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn from_borrowed(
+        inner: impl Into<DeserializationError>,
+        http_response: &http::Response<Vec<u8>>,
+    ) -> Self {
+        Self::new(inner, copy_http_response(http_response))
+    }
 }
 
 impl Display for ResponseDeserializationError {
@@ -148,26 +479,156 @@ impl Display for ResponseDeserializationError {
 impl std::error::Error for ResponseDeserializationError {}
 
 /// An error was reported by the server (HTTP status code 4xx or 5xx)
+///
+/// `E` is the type of the error bodies returned by the endpoint, see `FromHttpResponseError`.
 #[derive(Debug)]
-pub struct ServerError {
+pub struct ServerError<E = MatrixError> {
     http_response: http::Response<Vec<u8>>,
+    error: Option<E>,
 }
 
-impl ServerError {
+impl<E: ErrorBody> ServerError<E> {
     /// This method is public so it is accessible from `ruma_api!` generated
     /// code. It is not considered part of ruma-api's public API.
     #[doc(hidden)]
     pub fn new(http_response: http::Response<Vec<u8>>) -> Self {
-        Self { http_response }
+        let error = E::from_http_response(&http_response);
+        Self { http_response, error }
+    }
+}
+
+/// A type of error bodies returned by an endpoint, see `Endpoint::EndpointError`.
+///
+/// This is implemented for all types implementing `DeserializeOwned`, which are parsed from the
+/// body of every error response. A `MatrixError` parsed this way gets the status of the response
+/// as its `status_code`. `ruma_api!` implements it for the `ResponseError` enum it generates for
+/// endpoints with an `errors` block, which selects the body type by HTTP status.
+pub trait ErrorBody: Sized {
+    /// Parses the error from the given error response, returning `None` if the body doesn't
+    /// match.
+    fn from_http_response(response: &http::Response<Vec<u8>>) -> Option<Self>;
+}
+
+impl<T: DeserializeOwned + 'static> ErrorBody for T {
+    fn from_http_response(response: &http::Response<Vec<u8>>) -> Option<Self> {
+        let mut error: T = serde_json::from_slice(response.body()).ok()?;
+
+        // The status isn't part of the body, so the `Deserialize` implementation can't set it.
+        if let Some(error) = (&mut error as &mut dyn Any).downcast_mut::<MatrixError>() {
+            error.status_code = response.status();
+        }
+
+        Some(error)
     }
+}
 
+impl<E> ServerError<E> {
     /// Get the HTTP response without parsing its contents.
     pub fn into_raw_reponse(self) -> http::Response<Vec<u8>> {
         self.http_response
     }
+
+    /// The HTTP status code of the response.
+    pub fn status(&self) -> http::StatusCode {
+        self.http_response.status()
+    }
+
+    /// The challenges of the `WWW-Authenticate` headers of the response, e.g. a `Bearer`
+    /// challenge sent with a `401 Unauthorized` response for a missing or invalid access token.
+    ///
+    /// See `header::www_authenticate`.
+    pub fn www_authenticate(&self) -> Vec<Challenge> {
+        crate::header::www_authenticate(self.http_response.headers())
+    }
+
+    /// The raw body of the response.
+    pub fn body_bytes(&self) -> &[u8] {
+        self.http_response.body()
+    }
+
+    /// The time the server asked the client to wait before retrying the request.
+    ///
+    /// This is taken from the `Retry-After` header if it holds a number of seconds, or else from
+    /// the `retry_after_ms` field of an `M_LIMIT_EXCEEDED` error body.
+    pub fn retry_after(&self) -> Option<Duration> {
+        let header = self
+            .http_response
+            .headers()
+            .get(http::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+
+        header.or_else(|| {
+            #[derive(Deserialize)]
+            struct RetryAfterMs {
+                retry_after_ms: u64,
+            }
+
+            serde_json::from_slice::<RetryAfterMs>(self.http_response.body())
+                .ok()
+                .map(|body| Duration::from_millis(body.retry_after_ms))
+        })
+    }
+
+    /// The error parsed from the response body as the endpoint's error type, if the body is a
+    /// valid value of that type.
+    ///
+    /// For endpoints using the default `MatrixError`, `to_matrix_error` is usually more useful,
+    /// since it also sets the status code and handles bodies that are not Matrix errors.
+    pub fn error(&self) -> Option<&E> {
+        self.error.as_ref()
+    }
+
+    /// Like `error`, but takes ownership of the parsed error.
+    pub fn into_error(self) -> Option<E> {
+        self.error
+    }
+
+    /// Interpret the response as a Matrix error.
+    ///
+    /// If the response body is not a valid Matrix error (for example because it is empty or not
+    /// JSON, as returned by some gateways), the error kind is derived from the HTTP status and the
+    /// body is used as the message. The raw response remains available through
+    /// `into_raw_reponse`.
+    pub fn to_matrix_error(&self) -> MatrixError {
+        let status_code = self.http_response.status();
+
+        match self.try_matrix_error() {
+            Some(error) => error,
+            None => {
+                let body = String::from_utf8_lossy(self.http_response.body());
+                let message = if body.trim().is_empty() {
+                    status_code.canonical_reason().unwrap_or_default().to_owned()
+                } else {
+                    body.into_owned()
+                };
+
+                MatrixError::new(MatrixErrorKind::from_status(status_code), status_code, message)
+            }
+        }
+    }
+
+    /// Try to parse the response body as a Matrix error.
+    ///
+    /// Unlike `to_matrix_error`, this returns `None` if the body is not a valid Matrix error. The
+    /// status code of the returned error is set from the response. Bodies with an `error` message
+    /// but without an `errcode`, as sent by some non-compliant servers, are accepted, with the
+    /// error kind derived from the HTTP status.
+    pub fn try_matrix_error(&self) -> Option<MatrixError> {
+        let status_code = self.http_response.status();
+        let fields = serde_json::from_slice(self.http_response.body()).ok()?;
+
+        MatrixError::from_json_map::<serde_json::Error>(
+            fields,
+            MatrixErrorKind::from_status(status_code),
+        )
+        .ok()
+        .map(|error| MatrixError { status_code, ..error })
+    }
 }
 
-impl Display for ServerError {
+impl<E> Display for ServerError<E> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self.http_response.status().canonical_reason() {
             Some(reason) => {
@@ -178,10 +639,252 @@ impl Display for ServerError {
     }
 }
 
-impl std::error::Error for ServerError {}
+impl<E: Debug> std::error::Error for ServerError<E> {}
+
+/// Converts an HTTP response into an endpoint's response type or error.
+///
+/// This is the shared logic of all response conversions generated by `ruma_api!`, and can be used
+/// by hand-written endpoints as well:
+///
+/// * A `304 Not Modified` response results in `FromHttpResponseError::NotModified`.
+/// * A `412 Precondition Failed` response results in `FromHttpResponseError::PreconditionFailed`.
+/// * A `401 Unauthorized` response with a body describing user-interactive authentication flows
+///   results in `FromHttpResponseError::Uiaa`.
+/// * The body is decompressed according to the `Content-Encoding` header (see the `compression`
+///   module). For error responses, a body that fails to decompress is kept as it is, so that the
+///   decompression error doesn't hide the server error.
+/// * Responses with a status below 400 are passed to `parse`.
+/// * Other responses result in a `ServerError`, whose body is parsed as the endpoint's error type.
+// The error contains the response, like all errors of response conversions.
+#[allow(clippy::result_large_err)]
+pub fn from_http_response<T, E: ErrorBody>(
+    response: http::Response<Vec<u8>>,
+    parse: impl FnOnce(http::Response<Vec<u8>>) -> Result<T, FromHttpResponseError<E>>,
+) -> Result<T, FromHttpResponseError<E>> {
+    if response.status() == http::StatusCode::NOT_MODIFIED {
+        return Err(FromHttpResponseError::NotModified);
+    }
+
+    if response.status().as_u16() < 400 {
+        return parse(crate::compression::decompress_response(response)?);
+    }
+
+    let response = match crate::compression::decompress_response(response) {
+        Ok(response) => response,
+        Err(err) => err.http_response,
+    };
+
+    if response.status() == http::StatusCode::PRECONDITION_FAILED {
+        Err(FromHttpResponseError::PreconditionFailed(ServerError::new(response)))
+    } else if response.status() == http::StatusCode::UNAUTHORIZED {
+        match crate::uiaa::from_http_response(&response) {
+            Some(uiaa) => Err(FromHttpResponseError::Uiaa(uiaa)),
+            None => Err(ServerError::new(response).into()),
+        }
+    } else {
+        Err(ServerError::new(response).into())
+    }
+}
+
+/// Like `from_http_response`, but for a borrowed response.
+///
+/// Responses that are passed to `parse` as they are, i.e. successful responses whose body doesn't
+/// have to be decompressed, aren't copied. All others, and responses that fail to parse, are
+/// copied, since the errors contain the response.
+///
+/// This function is public so it is accessible from `ruma_api!` generated code. It is not
+/// considered part of ruma-api's public API.
+#[doc(hidden)]
+#[allow(clippy::result_large_err)]
+pub fn from_http_response_ref<T, E: ErrorBody>(
+    response: &http::Response<Vec<u8>>,
+    parse: impl FnOnce(&http::Response<Vec<u8>>) -> Result<T, FromHttpResponseError<E>>,
+) -> Result<T, FromHttpResponseError<E>> {
+    if response.status().as_u16() < 400
+        && response.status() != http::StatusCode::NOT_MODIFIED
+        && !crate::compression::is_encoded(response)
+    {
+        parse(response)
+    } else {
+        from_http_response(copy_http_response(response), |response| parse(&response))
+    }
+}
+
+/// Copies the status, version, headers and body of the given response.
+///
+/// `http::Response` doesn't implement `Clone`, since its extensions can't be cloned. They are not
+/// used by response conversions and left out of the copy.
+fn copy_http_response(response: &http::Response<Vec<u8>>) -> http::Response<Vec<u8>> {
+    let mut copy = http::Response::new(response.body().clone());
+    *copy.status_mut() = response.status();
+    *copy.version_mut() = response.version();
+    *copy.headers_mut() = response.headers().clone();
+    copy
+}
+
+/// The server returned a successful HTTP status, but not the one the endpoint declares in its
+/// metadata.
+#[derive(Debug)]
+pub struct UnexpectedStatusError {
+    expected: http::StatusCode,
+    http_response: http::Response<Vec<u8>>,
+}
+
+impl UnexpectedStatusError {
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn new(expected: http::StatusCode, http_response: http::Response<Vec<u8>>) -> Self {
+        Self { expected, http_response }
+    }
+
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn from_borrowed(
+        expected: http::StatusCode,
+        http_response: &http::Response<Vec<u8>>,
+    ) -> Self {
+        Self::new(expected, copy_http_response(http_response))
+    }
+
+    /// The status the endpoint declares in its metadata.
+    pub fn expected(&self) -> http::StatusCode {
+        self.expected
+    }
+
+    /// Get the HTTP response without parsing its contents.
+    pub fn into_raw_response(self) -> http::Response<Vec<u8>> {
+        self.http_response
+    }
+}
+
+impl Display for UnexpectedStatusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected HTTP status {}, got {}",
+            self.expected.as_str(),
+            self.http_response.status().as_str()
+        )
+    }
+}
+
+impl std::error::Error for UnexpectedStatusError {}
+
+/// An error when converting an 'Incoming' type back into the type it was generated from.
+///
+/// See the `incoming_try_into_outgoing` attribute of [the `Outgoing` derive
+/// macro](../derive.Outgoing.html).
+#[derive(Debug)]
+pub struct FromIncomingError {
+    field: &'static str,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl FromIncomingError {
+    /// Converts the value of the field `field` with its `TryFrom` implementation.
+    ///
+    /// This method is public so it is accessible from `#[derive(Outgoing)]` generated code. It is
+    /// not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn convert<T, U>(field: &'static str, value: U) -> Result<T, Self>
+    where
+        T: std::convert::TryFrom<U>,
+        T::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        T::try_from(value).map_err(|err| Self { field, source: err.into() })
+    }
+
+    /// The name of the field that couldn't be converted.
+    pub fn field(&self) -> &str {
+        self.field
+    }
+}
+
+impl Display for FromIncomingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to convert field `{}`: {}", self.field, self.source)
+    }
+}
+
+impl std::error::Error for FromIncomingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// An error of the transport used to send a request, which prevented a response from being
+/// received.
+#[derive(Debug)]
+pub struct TransportError {
+    kind: TransportErrorKind,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl TransportError {
+    /// Creates a new `TransportError` of the given kind, caused by `source`.
+    pub fn new(
+        kind: TransportErrorKind,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self { kind, source: source.into() }
+    }
+
+    /// The kind of this error.
+    pub fn kind(&self) -> TransportErrorKind {
+        self.kind
+    }
+
+    /// Whether the request timed out.
+    pub fn is_timeout(&self) -> bool {
+        self.kind == TransportErrorKind::Timeout
+    }
+
+    /// Get the underlying error of the transport.
+    pub fn into_inner(self) -> Box<dyn std::error::Error + Send + Sync> {
+        self.source
+    }
+}
+
+impl Display for TransportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            TransportErrorKind::Connect => "failed to connect",
+            TransportErrorKind::Timeout => "request timed out",
+            TransportErrorKind::Other => "transport error",
+        };
+
+        write!(f, "{}: {}", kind, self.source)
+    }
+}
+
+impl std::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// The kind of a `TransportError`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TransportErrorKind {
+    /// The server could not be reached, e.g. because of a DNS or connection failure.
+    Connect,
+    /// No response was received within the configured timeout.
+    Timeout,
+    /// Any other failure, e.g. the connection being closed while receiving the response.
+    Other,
+}
 
 #[derive(Debug)]
 enum SerializationError {
+    CannotBeABaseUrl,
+    InvalidBaseUrl(String),
+    MissingAccessToken,
+    MissingTransactionId,
+    NotAppservice(&'static str),
+    Header(http::header::InvalidHeaderValue),
     Json(serde_json::Error),
     Query(serde_urlencoded::ser::Error),
 }
@@ -193,6 +896,39 @@ enum SerializationError {
 pub enum DeserializationError {
     Json(serde_json::Error),
     Query(serde_urlencoded::de::Error),
+    Path(String),
+    Header(String),
+    ContentEncoding(String),
+    Utf8(std::str::Utf8Error),
+    Multipart(String),
+}
+
+impl DeserializationError {
+    /// The underlying error, if there is one, or this error otherwise.
+    fn as_dyn_error(&self) -> &(dyn std::error::Error + 'static) {
+        match self {
+            DeserializationError::Json(err) => err,
+            DeserializationError::Query(err) => err,
+            DeserializationError::Utf8(err) => err,
+            DeserializationError::Path(_)
+            | DeserializationError::Header(_)
+            | DeserializationError::ContentEncoding(_)
+            | DeserializationError::Multipart(_) => self,
+        }
+    }
+
+    /// Like `as_dyn_error`, but returning an owned error.
+    fn into_boxed_error(self) -> Box<dyn std::error::Error + Send + Sync> {
+        match self {
+            DeserializationError::Json(err) => Box::new(err),
+            DeserializationError::Query(err) => Box::new(err),
+            DeserializationError::Utf8(err) => Box::new(err),
+            DeserializationError::Path(_)
+            | DeserializationError::Header(_)
+            | DeserializationError::ContentEncoding(_)
+            | DeserializationError::Multipart(_) => Box::new(self),
+        }
+    }
 }
 
 impl Display for DeserializationError {
@@ -200,10 +936,28 @@ impl Display for DeserializationError {
         match self {
             DeserializationError::Json(err) => Display::fmt(err, f),
             DeserializationError::Query(err) => Display::fmt(err, f),
+            DeserializationError::Path(message) => {
+                write!(f, "invalid path parameter: {}", message)
+            }
+            DeserializationError::Header(message) => f.write_str(message),
+            DeserializationError::ContentEncoding(message) => f.write_str(message),
+            DeserializationError::Utf8(err) => write!(f, "body is not valid UTF-8: {}", err),
+            DeserializationError::Multipart(message) => {
+                write!(f, "invalid multipart body: {}", message)
+            }
         }
     }
 }
 
+impl std::error::Error for DeserializationError {}
+
+#[doc(hidden)]
+impl From<std::str::Utf8Error> for DeserializationError {
+    fn from(err: std::str::Utf8Error) -> Self {
+        Self::Utf8(err)
+    }
+}
+
 #[doc(hidden)]
 impl From<serde_json::Error> for DeserializationError {
     fn from(err: serde_json::Error) -> Self {
@@ -0,0 +1,641 @@
+//! Helpers for working with the HTTP headers of requests and responses.
+
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use http::header::{
+    HeaderMap, HeaderName, HeaderValue, InvalidHeaderValue, CONNECTION, PROXY_AUTHENTICATE,
+    PROXY_AUTHORIZATION, TE, TRAILER, TRANSFER_ENCODING, UPGRADE, WWW_AUTHENTICATE,
+};
+
+use crate::error::DeserializationError;
+
+/// Whether the given header is a hop-by-hop header as defined in RFC 7230, section 6.1.
+///
+/// Hop-by-hop headers are only meaningful for a single transport-level connection, so they are
+/// neither sent nor collected by `#[ruma_api(header_map)]` fields.
+pub fn is_hop_by_hop(name: &HeaderName) -> bool {
+    [CONNECTION, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, TE, TRAILER, TRANSFER_ENCODING, UPGRADE]
+        .contains(name)
+        || name == "keep-alive"
+}
+
+/// Inserts a transport-level default for the header `name`, unless the request already has it.
+///
+/// Headers set by an endpoint's `header` and `header_map` fields always take precedence over
+/// defaults a transport adds to every request, such as `User-Agent`. Transports should add their
+/// defaults with this function after converting the request with `TryInto<http::Request<_>>`, so a
+/// field like `#[ruma_api(header = USER_AGENT)] user_agent: Option<String>` overrides the default
+/// when it is set and leaves it in place when it is `None`.
+///
+/// Returns whether the default was inserted.
+pub fn insert_default(headers: &mut HeaderMap, name: HeaderName, value: HeaderValue) -> bool {
+    match headers.entry(name) {
+        http::header::Entry::Occupied(_) => false,
+        http::header::Entry::Vacant(entry) => {
+            entry.insert(value);
+            true
+        }
+    }
+}
+
+/// Converts a header field's value to a `HeaderValue` with its `Display` implementation.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn to_value(value: &impl Display) -> Result<HeaderValue, InvalidHeaderValue> {
+    HeaderValue::from_str(&value.to_string())
+}
+
+/// Parses the value of the header `name`, returning an error if it is missing.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn parse_required<T>(headers: &HeaderMap, name: HeaderName) -> Result<T, DeserializationError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match parse_optional(headers, name.clone())? {
+        Some(value) => Ok(value),
+        None => Err(DeserializationError::Header(format!("missing header `{}`", name))),
+    }
+}
+
+/// Parses the value of the header `name`, if present.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn parse_optional<T>(
+    headers: &HeaderMap,
+    name: HeaderName,
+) -> Result<Option<T>, DeserializationError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    headers.get(&name).map(|value| parse_value(&name, value)).transpose()
+}
+
+/// Parses the values of all headers called `name`.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn parse_all<T>(headers: &HeaderMap, name: HeaderName) -> Result<Vec<T>, DeserializationError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    headers.get_all(&name).iter().map(|value| parse_value(&name, value)).collect()
+}
+
+fn parse_value<T>(name: &HeaderName, value: &HeaderValue) -> Result<T, DeserializationError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = value.to_str().map_err(|err| invalid_value(name, err))?;
+    value.parse().map_err(|err| invalid_value(name, err))
+}
+
+fn invalid_value(name: &HeaderName, err: impl Display) -> DeserializationError {
+    DeserializationError::Header(format!("invalid value for header `{}`: {}", name, err))
+}
+
+/// The value of a `Range` header requesting a single range of bytes, like `bytes=0-1023`.
+///
+/// Used as the type of a request field with `#[ruma_api(header = RANGE)]`, e.g. for resuming media
+/// downloads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    /// The bytes from the first to the last given offset, inclusive: `bytes=first-last`.
+    Bounded(u64, u64),
+    /// All bytes from the given offset on: `bytes=first-`.
+    FromOffset(u64),
+    /// The given number of bytes at the end: `bytes=-length`.
+    Suffix(u64),
+}
+
+impl Display for ByteRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bounded(first, last) => write!(f, "bytes={}-{}", first, last),
+            Self::FromOffset(first) => write!(f, "bytes={}-", first),
+            Self::Suffix(length) => write!(f, "bytes=-{}", length),
+        }
+    }
+}
+
+impl FromStr for ByteRange {
+    type Err = ParseRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let range = s.trim().strip_prefix("bytes=").ok_or(ParseRangeError)?;
+        let dash = range.find('-').ok_or(ParseRangeError)?;
+        let (first, last) = (range[..dash].trim(), range[dash + 1..].trim());
+
+        match (first.is_empty(), last.is_empty()) {
+            (false, false) => {
+                let first = parse_offset(first)?;
+                let last = parse_offset(last)?;
+                if last < first {
+                    return Err(ParseRangeError);
+                }
+
+                Ok(Self::Bounded(first, last))
+            }
+            (false, true) => Ok(Self::FromOffset(parse_offset(first)?)),
+            (true, false) => Ok(Self::Suffix(parse_offset(last)?)),
+            (true, true) => Err(ParseRangeError),
+        }
+    }
+}
+
+/// The value of a `Content-Range` header of a `206 Partial Content` response, like
+/// `bytes 0-1023/146515`.
+///
+/// Used as the type of a response field with `#[ruma_api(header = CONTENT_RANGE)]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContentRange {
+    /// The offset of the first byte in the response body.
+    pub first: u64,
+
+    /// The offset of the last byte in the response body, inclusive.
+    pub last: u64,
+
+    /// The length of the whole resource, if known.
+    pub complete_length: Option<u64>,
+}
+
+impl Display for ContentRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bytes {}-{}/", self.first, self.last)?;
+        match self.complete_length {
+            Some(length) => write!(f, "{}", length),
+            None => f.write_str("*"),
+        }
+    }
+}
+
+impl FromStr for ContentRange {
+    type Err = ParseRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let range = s.trim().strip_prefix("bytes ").ok_or(ParseRangeError)?;
+        let slash = range.find('/').ok_or(ParseRangeError)?;
+        let dash = range[..slash].find('-').ok_or(ParseRangeError)?;
+
+        let first = parse_offset(&range[..dash])?;
+        let last = parse_offset(&range[dash + 1..slash])?;
+        let complete_length = match &range[slash + 1..] {
+            "*" => None,
+            length => Some(parse_offset(length)?),
+        };
+
+        if last < first || matches!(complete_length, Some(length) if last >= length) {
+            return Err(ParseRangeError);
+        }
+
+        Ok(Self { first, last, complete_length })
+    }
+}
+
+fn parse_offset(s: &str) -> Result<u64, ParseRangeError> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ParseRangeError);
+    }
+
+    s.parse().map_err(|_| ParseRangeError)
+}
+
+/// An error when parsing a `ByteRange` or `ContentRange`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseRangeError;
+
+impl Display for ParseRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid byte range")
+    }
+}
+
+impl std::error::Error for ParseRangeError {}
+
+/// A preference of a `Prefer` request header (RFC 7240), like `return=minimal`.
+///
+/// Preference names are case-insensitive, values are case-sensitive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Preference {
+    /// Asks for a minimal response without the representation of the resource: `return=minimal`.
+    ReturnMinimal,
+
+    /// Asks for a response with the full representation of the resource: `return=representation`.
+    ReturnRepresentation,
+
+    /// Asks for the request to be processed asynchronously: `respond-async`.
+    RespondAsync,
+
+    /// The number of seconds the client is willing to wait for a response: `wait=seconds`.
+    Wait(u64),
+
+    /// Asks for invalid parts of the request to be rejected: `handling=strict`.
+    HandlingStrict,
+
+    /// Asks for invalid parts of the request to be ignored if possible: `handling=lenient`.
+    HandlingLenient,
+
+    /// Any other preference, with its lowercase name and unquoted value, if any.
+    Other {
+        /// The name of the preference.
+        name: String,
+
+        /// The value of the preference.
+        value: Option<String>,
+    },
+}
+
+/// Formats the preference as an element of a `Prefer` header, quoting its value if necessary.
+impl Display for Preference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReturnMinimal => f.write_str("return=minimal"),
+            Self::ReturnRepresentation => f.write_str("return=representation"),
+            Self::RespondAsync => f.write_str("respond-async"),
+            Self::Wait(seconds) => write!(f, "wait={}", seconds),
+            Self::HandlingStrict => f.write_str("handling=strict"),
+            Self::HandlingLenient => f.write_str("handling=lenient"),
+            Self::Other { name, value: None } => f.write_str(name),
+            Self::Other { name, value: Some(value) } if is_token(value) => {
+                write!(f, "{}={}", name, value)
+            }
+            Self::Other { name, value: Some(value) } => {
+                write!(f, "{}=\"{}\"", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+        }
+    }
+}
+
+/// Parses a single preference. Parameters of the preference, after a `;`, are ignored.
+impl FromStr for Preference {
+    type Err = ParsePreferenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let preference = split_unquoted(s, ';').into_iter().next().unwrap_or_default();
+        let (name, value) = match preference.find('=') {
+            Some(eq) => (preference[..eq].trim(), Some(unquote(preference[eq + 1..].trim())?)),
+            None => (preference.trim(), None),
+        };
+
+        if !is_token(name) {
+            return Err(ParsePreferenceError);
+        }
+
+        let name = name.to_ascii_lowercase();
+        Ok(match (name.as_str(), value.as_deref()) {
+            ("return", Some("minimal")) => Self::ReturnMinimal,
+            ("return", Some("representation")) => Self::ReturnRepresentation,
+            ("respond-async", None) => Self::RespondAsync,
+            ("wait", Some(seconds)) => {
+                Self::Wait(seconds.parse().map_err(|_| ParsePreferenceError)?)
+            }
+            ("handling", Some("strict")) => Self::HandlingStrict,
+            ("handling", Some("lenient")) => Self::HandlingLenient,
+            _ => Self::Other { name, value },
+        })
+    }
+}
+
+/// The preferences of a `Prefer` request header or a `Preference-Applied` response header (RFC
+/// 7240), like `return=minimal, respond-async`.
+///
+/// Used as the type of a request field with `#[ruma_api(header = "Prefer")]`, or of a response
+/// field with `#[ruma_api(header = "Preference-Applied")]` to find out which preferences the
+/// server honored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Preferences(pub Vec<Preference>);
+
+impl Preferences {
+    /// Whether the given preference is one of these preferences.
+    pub fn contains(&self, preference: &Preference) -> bool {
+        self.0.contains(preference)
+    }
+}
+
+impl From<Vec<Preference>> for Preferences {
+    fn from(preferences: Vec<Preference>) -> Self {
+        Self(preferences)
+    }
+}
+
+impl From<Preference> for Preferences {
+    fn from(preference: Preference) -> Self {
+        Self(vec![preference])
+    }
+}
+
+/// Formats the preferences as a comma-separated list.
+impl Display for Preferences {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, preference) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", preference)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a comma-separated list of preferences, skipping empty elements.
+impl FromStr for Preferences {
+    type Err = ParsePreferenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        split_unquoted(s, ',')
+            .into_iter()
+            .filter(|preference| !preference.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// An error when parsing a `Preference` or `Preferences`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParsePreferenceError;
+
+impl Display for ParsePreferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid preference")
+    }
+}
+
+impl std::error::Error for ParsePreferenceError {}
+
+/// Splits the given string at every `separator` outside of quoted strings.
+fn split_unquoted(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if in_quotes && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c == separator && !in_quotes {
+            parts.push(&s[start..i]);
+            start = i + 1;
+        }
+    }
+
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Removes the quotes and escapes of a quoted string, or checks that an unquoted value is a token.
+fn unquote(value: &str) -> Result<String, ParsePreferenceError> {
+    let quoted = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(quoted) => quoted,
+        None if is_token(value) => return Ok(value.to_owned()),
+        None => return Err(ParsePreferenceError),
+    };
+
+    let mut unquoted = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.push(chars.next().ok_or(ParsePreferenceError)?),
+            '"' => return Err(ParsePreferenceError),
+            c => unquoted.push(c),
+        }
+    }
+
+    Ok(unquoted)
+}
+
+/// Whether the given string is a non-empty token (RFC 7230, section 3.2.6).
+fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+/// The deprecation of an endpoint, as announced by a server with the `Deprecation` response header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deprecation {
+    /// The endpoint is deprecated, without a date, as sent with `Deprecation: true`.
+    Deprecated,
+
+    /// The endpoint is or will be deprecated at the given time.
+    Since(SystemTime),
+}
+
+/// The deprecation announced by the `Deprecation` header of a response, if any.
+///
+/// Both the structured date form, like `@1688169599`, and the older forms `true` and an HTTP date
+/// are understood. Invalid values are ignored.
+///
+/// This works with the headers of any response, so transport code can warn about deprecated
+/// endpoints before converting the response, without the endpoint declaring a header field.
+pub fn deprecation(headers: &HeaderMap) -> Option<Deprecation> {
+    let value = headers.get("deprecation")?.to_str().ok()?.trim();
+
+    if value == "true" {
+        return Some(Deprecation::Deprecated);
+    }
+
+    let time = match value.strip_prefix('@') {
+        Some(secs) => UNIX_EPOCH.checked_add(Duration::from_secs(secs.parse().ok()?))?,
+        None => parse_http_date(value)?,
+    };
+
+    Some(Deprecation::Since(time))
+}
+
+/// The time given by the `Sunset` header of a response (RFC 8594), after which the endpoint is
+/// expected to stop working, if any.
+///
+/// Invalid values are ignored. Like `deprecation`, this works with the headers of any response.
+pub fn sunset(headers: &HeaderMap) -> Option<SystemTime> {
+    parse_http_date(headers.get("sunset")?.to_str().ok()?.trim())
+}
+
+/// An authentication challenge from the `WWW-Authenticate` header of a `401 Unauthorized` response
+/// (RFC 7235), like `Bearer realm="example.org", error="invalid_token"`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Challenge {
+    /// The authentication scheme, like `Bearer`.
+    pub scheme: String,
+
+    /// The parameters of the challenge, with lowercase names and unquoted values.
+    pub params: Vec<(String, String)>,
+}
+
+impl Challenge {
+    /// Whether this is a challenge for the `Bearer` scheme, i.e. for an access token.
+    ///
+    /// Schemes are case-insensitive, so this also matches e.g. `bearer`.
+    pub fn is_bearer(&self) -> bool {
+        self.scheme.eq_ignore_ascii_case("bearer")
+    }
+
+    /// The value of the parameter with the given name, compared case-insensitively, if any.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param_name, _)| param_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Formats the challenge as the value of a `WWW-Authenticate` header, with quoted parameter values.
+impl Display for Challenge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.scheme)?;
+
+        for (i, (name, value)) in self.params.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            write!(f, "{}{}=\"{}\"", separator, name, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The challenges of all `WWW-Authenticate` headers of a response.
+///
+/// Each header may contain several comma-separated challenges. Parsing a header stops at the first
+/// invalid part, keeping the challenges before it, and `token68` credentials like the ones of the
+/// `Negotiate` scheme are skipped.
+///
+/// Servers and gateways may send this with `401 Unauthorized` responses to tell clients which kind
+/// of authentication they expect, e.g. a `Bearer` challenge for a missing or invalid access token.
+pub fn www_authenticate(headers: &HeaderMap) -> Vec<Challenge> {
+    let mut challenges = Vec::new();
+
+    for value in headers.get_all(WWW_AUTHENTICATE) {
+        if let Ok(value) = value.to_str() {
+            parse_challenges(value, &mut challenges);
+        }
+    }
+
+    challenges
+}
+
+fn parse_challenges(mut rest: &str, challenges: &mut Vec<Challenge>) {
+    let first_challenge = challenges.len();
+
+    loop {
+        rest = rest.trim_start_matches(&[',', ' ', '\t'][..]);
+        if rest.is_empty() {
+            return;
+        }
+
+        let (token, after_token) = split_token(rest);
+        if token.is_empty() {
+            return;
+        }
+
+        let after_ws = after_token.trim_start_matches(&[' ', '\t'][..]);
+        let is_param = after_ws.starts_with('=') && !after_ws.starts_with("==");
+
+        if !is_param {
+            if after_ws.starts_with('=') {
+                // The end of a `token68` value.
+                rest = after_ws.trim_start_matches('=');
+            } else {
+                challenges.push(Challenge { scheme: token.to_owned(), params: Vec::new() });
+                rest = after_token;
+            }
+            continue;
+        }
+
+        let challenge = match challenges[first_challenge..].last_mut() {
+            Some(challenge) => challenge,
+            None => return,
+        };
+
+        let value_start = after_ws[1..].trim_start_matches(&[' ', '\t'][..]);
+        let value = if let Some(quoted) = value_start.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            loop {
+                match chars.next() {
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, c)) => value.push(c),
+                        None => return,
+                    },
+                    Some((i, '"')) => {
+                        rest = &quoted[i + 1..];
+                        break;
+                    }
+                    Some((_, c)) => value.push(c),
+                    None => return,
+                }
+            }
+            value
+        } else {
+            let (value, after_value) = split_token(value_start);
+            rest = after_value;
+            value.to_owned()
+        };
+
+        challenge.params.push((token.to_ascii_lowercase(), value));
+    }
+}
+
+/// Splits off the leading token (RFC 7230, section 3.2.6) of the given string, also accepting the
+/// `/` of `token68` values.
+fn split_token(s: &str) -> (&str, &str) {
+    let end = s
+        .find(|c: char| !(c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~/".contains(c)))
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Parses an HTTP date in the preferred IMF-fixdate format, like `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn parse_http_date(s: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] =
+        ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    let parts: Vec<_> = s.split_whitespace().collect();
+    let (day, month, year, time) = match parts[..] {
+        [_weekday, day, month, year, time, "GMT"] => (day, month, year, time),
+        _ => return None,
+    };
+
+    let day: u64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as u64 + 1;
+    let year: u64 = year.parse().ok()?;
+    let time: Vec<u64> = time.split(':').map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    let (hour, minute, second) = match time[..] {
+        [hour, minute, second] if hour < 24 && minute < 60 && second < 61 => (hour, minute, second),
+        _ => return None,
+    };
+    if year < 1970 || day == 0 || day > 31 {
+        return None;
+    }
+
+    // Days since the epoch of the given date in the proleptic Gregorian calendar, counting years
+    // from March so the leap day is at the end of the year.
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+
+    // Dates too far in the future to be represented are ignored like other invalid values.
+    let days = y
+        .checked_mul(365)?
+        .checked_add(y / 4 - y / 100 + y / 400 + (153 * m + 2) / 5 + day - 1)?
+        .checked_sub(719_468)?;
+
+    let secs = days.checked_mul(86_400)?.checked_add(hour * 3_600 + minute * 60 + second)?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
@@ -9,13 +9,31 @@
 //! input parameters for requests, and the structure of a successful response.
 //! Such types can then be used by client code to make requests, and by server code to fulfill
 //! those requests.
+//!
+//! Integer body fields like `u64` are deserialized without loss of precision. For
+//! `serde_json::Value` fields holding numbers beyond the range of `u64`, `i64` and `f64`, enable
+//! the `arbitrary-precision` feature. The `raw-value` feature allows
+//! `Box<serde_json::value::RawValue>` body fields, which keep a part of the body exactly as it was
+//! received. Since `serde_json` selects its number representation at compile time, these features
+//! apply to all endpoints.
+//!
+//! As a development aid, the `validate-roundtrip` feature makes the generated conversions parse
+//! every JSON body they serialize back into the body type and serialize it again, panicking if
+//! that fails or the JSON changes. The check only runs in builds with debug assertions.
+//!
+//! There is no `no_std` support: even the endpoint definitions depend on `std` through the `http`
+//! crate, since `Metadata` stores an `http::Method` and `http::StatusCode`.
 
 #![warn(rust_2018_idioms)]
 #![deny(missing_copy_implementations, missing_debug_implementations, missing_docs)]
 
 use http::Method;
 use serde::{Deserialize, Serialize};
-use std::convert::{TryFrom, TryInto};
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt::{self, Debug, Display, Formatter},
+    time::Duration,
+};
 
 /// Generates a `ruma_api::Endpoint` from a concise definition.
 ///
@@ -30,6 +48,12 @@ use std::convert::{TryFrom, TryInto};
 ///         path: &'static str,
 ///         rate_limited: bool,
 ///         requires_authentication: bool,
+///         status: u16, // optional
+///         default: bool, // optional
+///         appservice: bool, // optional
+///         api: ApiKind, // optional
+///         added_in: &'static str, // optional
+///         removed_in: &'static str, // optional
 ///     }
 ///
 ///     request {
@@ -57,6 +81,8 @@ use std::convert::{TryFrom, TryInto};
 /// *   `method`: The HTTP method used for requests to the endpoint.
 ///     It's not necessary to import `http::Method`'s associated constants. Just write
 ///     the value as if it was imported, e.g. `GET`.
+///     `GET` and `HEAD` endpoints can't have request body fields, and `HEAD` endpoints can't have
+///     response body fields either, so their responses are built from headers only.
 /// *   `name`: A unique name for the endpoint.
 ///     Generally this will be the same as the containing module.
 /// *   `path`: The path component of the URL for the endpoint, e.g. "/foo/bar".
@@ -66,32 +92,181 @@ use std::convert::{TryFrom, TryInto};
 ///     for details).
 /// *   `rate_limited`: Whether or not the endpoint enforces rate limiting on requests.
 /// *   `requires_authentication`: Whether or not the endpoint requires a valid access token.
+/// *   `status`: The successful HTTP status code the endpoint responds with, e.g. `204`.
+///     Optional, defaults to `200`. If set, responses with any other successful status are
+///     rejected with `FromHttpResponseError::UnexpectedStatus`. Endpoints responding with `204` or
+///     `205` can't have response body fields.
+/// *   `default`: Whether to derive `Default` for the generated `Request` and `Response` types.
+///     Optional, defaults to `false`. All request and response fields have to implement `Default`
+///     when this is set.
+/// *   `partial_eq`: Whether to derive `PartialEq` for the generated `Request` and `Response`
+///     types. Optional, defaults to `false`. All request and response fields have to implement
+///     `PartialEq` when this is set.
+/// *   `eq`: Like `partial_eq`, but derives `Eq` as well. Optional, defaults to `false`.
+/// *   `hash`: Whether to derive `Hash`, `PartialEq` and `Eq` for the generated `Request` type,
+///     e.g. to use requests as keys of a response cache. Optional, defaults to `false`. All request
+///     fields have to implement `Hash` and `Eq` when this is set, so it is not set by default.
+/// *   `clone`: Whether to derive `Clone` for the generated `Request` and `Response` types, e.g.
+///     so that retry logic can resend a request. Optional, defaults to `true`. Set this to `false`
+///     for endpoints with fields that don't implement `Clone`.
+/// *   `appservice`: Whether or not application services can use the endpoint by passing their
+///     token and the user they act as in the query string (see the `appservice` module).
+///     Optional, defaults to `false`.
+/// *   `api`: The API the endpoint belongs to, as the name of an `ApiKind` variant, e.g.
+///     `ServerServer`. Optional, defaults to `ClientServer`.
+/// *   `added_in`: The version of the Matrix specification that introduced the endpoint, e.g.
+///     `"v1.1"`. Optional.
+/// *   `removed_in`: The version of the Matrix specification that removed the endpoint. Optional.
+/// *   `error`: The type of the error bodies returned by the endpoint, for endpoints with errors
+///     that carry more than a `MatrixError`. The type must implement `ErrorBody` (which is
+///     implemented for all `DeserializeOwned` types) and `Debug`. Optional, defaults to
+///     `ruma_api::MatrixError`. Error responses are parsed as this type and available through
+///     `ServerError::error`.
+/// *   `canonical_json`: Whether to serialize the JSON bodies of outgoing requests and responses as
+///     canonical JSON, as required for signing federation requests (see the `canonical_json`
+///     module). Optional, defaults to `false`.
+/// *   `fast`: Whether to generate hand-written `Serialize` and `Deserialize` implementations for
+///     the request and response bodies instead of deriving them, for endpoints where
+///     (de)serialization performance matters. The output is the same as with the derived
+///     implementations. Body fields can't have `serde` or `wrap_incoming` attributes when this is
+///     set. Optional, defaults to `false`.
+/// *   `lossy_path_decoding`: Whether to accept path parameters of incoming requests with invalid
+///     percent-encoding or invalid UTF-8, replacing invalid UTF-8 with `U+FFFD`, instead of
+///     rejecting the request with a deserialization error. Optional, defaults to `false`.
+/// *   `max_body_size`: The maximum size of incoming request bodies in bytes, e.g. `1024`. Requests
+///     with a larger body, or a `Content-Length` header announcing one, are rejected with
+///     `FromHttpRequestError::TooLarge`. Optional, defaults to no limit. The size of the received
+///     body is always checked, so requests without a `Content-Length` header, e.g. with
+///     `Transfer-Encoding: chunked`, are limited as well.
+/// *   `deny_unknown_fields`: Whether to reject incoming requests whose JSON body contains fields
+///     that aren't part of the request, like `#[serde(deny_unknown_fields)]`. Such requests fail
+///     with `FromHttpRequestError::Deserialization`. Body fields can't be flattened or have
+///     `wrap_incoming` attributes when this is set. Optional, defaults to `false`.
 ///
 /// ## Request
 ///
 /// The request block contains normal struct field definitions.
 /// Doc comments and attributes are allowed as normal.
+/// `serde` attributes on body fields are applied to the generated body structs, so e.g. a field
+/// with `#[serde(rename = "m.new_name", alias = "old_name")]` is sent as `m.new_name` and accepted
+/// under either key.
 /// There are also a few special attributes available to control how the struct is converted into a
 /// `http::Request`:
 ///
 /// *   `#[ruma_api(header = HEADER_NAME)]`: Fields with this attribute will be treated as HTTP
 ///     headers on the request.
-///     The value must implement `AsRef<str>`.
-///     Generally this is a `String`.
+///     The value must implement `Display` and `FromStr`.
+///     Generally this is a `String`, but typed values like `http::Uri` are validated when the
+///     request is received.
 ///     The attribute value shown above as `HEADER_NAME` must be a header name constant from
-///     `http::header`, e.g. `CONTENT_TYPE`.
+///     `http::header`, e.g. `CONTENT_TYPE`, or a string literal for custom headers, e.g.
+///     `header = "X-Matrix-Foo"`.
+///     The field can also be an `Option`, in which case the header is optional, e.g. for
+///     conditional requests using `IF_NONE_MATCH`.
 /// *   `#[ruma_api(path)]`: Fields with this attribute will be inserted into the matching path
 ///     component of the request URL.
+///     The type of the field must implement `ruma_api::path::PathParam`, which is implemented for
+///     all types implementing both `Display` and `FromStr`.
+///     The field for the last path segment can be an `Option` with a default segment, e.g.
+///     `#[ruma_api(path, path_default = "v1")]`. `None` is sent as the default, and incoming
+///     requests that leave out the segment are accepted with the field set to `None`.
+///     A field of type `Vec<T>` marked with `#[ruma_api(path, comma)]` is sent as a single segment
+///     with the elements separated by commas, like `a,b,c`, e.g. for batch lookups. Commas within
+///     the elements are percent-encoded, and incoming segments are split on commas before each
+///     element is percent-decoded and parsed. An empty `Vec` results in an empty segment.
 /// *   `#[ruma_api(query)]`: Fields with this attribute will be inserting into the URL's query
 ///     string.
+///     Spaces in query values are encoded as `%20` rather than `+` (see the `query` module).
+///     A field of type `Vec<T>` is sent as one `key=value` pair per element, with `T`'s `Display`
+///     implementation, and is omitted if empty. Repeated keys are collected back into the `Vec`
+///     with `T`'s `FromStr` implementation.
+///     A field of type `Option<Option<T>>` distinguishes an omitted parameter from an empty one:
+///     `None` omits the key, `Some(None)` is sent as `key=` and `Some(Some(value))` as
+///     `key=value`, with `T`'s `Display` implementation. When parsing, a missing key results in
+///     `None`, an empty value in `Some(None)` and any other value is parsed with `T`'s `FromStr`
+///     implementation.
+///     A field of type `Duration` or `Option<Duration>` is sent and parsed as whole milliseconds,
+///     e.g. `timeout=30000`.
+///     A field of a struct type marked with `#[ruma_api(query, flatten)]` is sent as the
+///     struct's own fields, e.g. to share a group of pagination parameters between endpoints.
+///     It is parsed from the whole query string, so its field names must not overlap with those
+///     of other query fields.
 /// *   `#[ruma_api(query_map)]`: Instead of individual query fields, one query_map field, of any
-///     type that implements `IntoIterator<Item = (String, String)>` (e.g.
-///     `HashMap<String, String>`, can be used for cases where an endpoint supports arbitrary query
-///     parameters.
+///     type that implements both `IntoIterator<Item = (String, V)>` and
+///     `FromIterator<(String, V)>` (e.g. `HashMap<String, String>` or `BTreeMap<String, u64>`), can
+///     be used for cases where an endpoint supports arbitrary query parameters. The values are
+///     converted with `V`'s `Display` and `FromStr` implementations.
+///     With a prefix, e.g. `#[ruma_api(query_map, prefix = "x-extra-")]`, the field only holds the
+///     parameters whose key starts with the prefix, and can be combined with regular query fields.
+///     The prefix is added to the keys of outgoing parameters and stripped from the keys of
+///     incoming ones. Regular query fields whose name starts with the prefix are rejected, since
+///     their parameters would end up in both fields. The names of the fields of flattened query
+///     fields are not checked, so they shouldn't start with the prefix either.
+/// *   `#[ruma_api(header_map)]`: Like `query_map`, but for HTTP headers. One field of any type
+///     that implements both `IntoIterator<Item = (HeaderName, String)>` and
+///     `FromIterator<(HeaderName, String)>` (e.g. `Vec<(HeaderName, String)>`) can be used to send
+///     and receive an arbitrary set of headers. Every value of a header that appears multiple times
+///     is kept. Hop-by-hop headers like `Connection` are skipped, as are headers that belong to one
+///     of the request's `header` fields.
 ///
 /// Any field that does not include one of these attributes will be part of the request's JSON
 /// body.
 ///
+/// `Option` body fields are left out of the JSON body when they are `None`, as most Matrix
+/// endpoints expect, and missing fields are parsed as `None`. Fields where the specification
+/// requires an explicit `null` can be marked with `#[ruma_api(explicit_null)]` to send `None` as
+/// `null` instead. This applies to response body fields as well.
+///
+/// One request field can additionally be marked with `timeout_hint`, e.g.
+/// `#[ruma_api(query, timeout_hint)]`, for long-polling endpoints. Its value, which has to
+/// implement `ruma_api::timeout::TimeoutHint`, is returned by `Endpoint::suggested_timeout`.
+/// Multiple arguments can be combined in one `#[ruma_api(...)]` attribute like this.
+///
+/// Body fields and query fields other than `Vec`s can be given a default value for incoming
+/// requests that leave them out with `#[ruma_api(default = path::to::function)]`, where the
+/// function takes no arguments and returns the field's type, like serde's
+/// `#[serde(default = "...")]`.
+///
+/// A path field of type `Option<String>` can be marked with `transaction_id`, e.g.
+/// `#[ruma_api(path, transaction_id)]`, for endpoints that use a client-chosen transaction ID to
+/// make `PUT` requests idempotent. The conversions of the request never generate an ID themselves
+/// and return an `IntoHttpError` while the field is `None`. `Endpoint::ensure_transaction_id`
+/// generates a unique ID and stores it in the request, so that every retry of the request value
+/// reuses it.
+///
+/// Both the request and the response block can contain one field marked with
+/// `#[ruma_api(additional)]`, of a map type like `BTreeMap<String, serde_json::Value>`. It is part
+/// of the JSON body and collects all body fields that are not otherwise declared, so that they are
+/// preserved when converting to HTTP and back.
+///
+/// In addition, any request or response field can be marked with `#[ruma_api(sensitive)]`, next
+/// to its other attributes. The `Debug` output of the generated type then shows `<redacted>`
+/// instead of the field's value, which keeps secrets like passwords and access tokens out of logs.
+///
+/// Besides the conversions to and from `http::Request`, the generated `Request` type has a `url`
+/// method that builds the full request URL, including path parameters and query string, on top of a
+/// given base URL without consuming the request. Fields used in the query string must implement
+/// `Clone` for this. `path_with_params` returns just the path and query string, e.g. for signing
+/// federation requests. Its `try_into_http_request` method converts the request into a
+/// `http::Request` for a given base URL and adds the access token for endpoints that require
+/// authentication, which is what clients usually need. Its `validate` method checks that the
+/// request can be converted into a `http::Request` without serializing the body, by building the
+/// URL and headers, which additionally requires a `header_map` field to implement `Clone`.
+///
+/// The endpoint's metadata is also available from a request value, through the generated
+/// `metadata` method or the `AsRef<Metadata>` implementation. The method and path template are
+/// additionally available as the associated constants `Request::METHOD` and `Request::PATH`.
+/// Both `Request` and `Response` have a `BODY_FIELDS` constant listing the keys of their JSON body,
+/// which respects `#[serde(rename)]`, e.g. for code that signs or canonicalizes bodies.
+///
+/// If the request has no body fields, it can also be converted from `http::request::Parts`, so that
+/// it can be parsed without reading the request body.
+///
+/// Requests with a body can be parsed in two steps instead: `Request::try_from_parts` parses the
+/// path, query string and headers from `&http::request::Parts` into a generated `PartialRequest`,
+/// whose `complete` method then parses the body. This allows a server to reject a request, e.g.
+/// because of failed authentication, before deserializing its body.
+///
 /// ## Response
 ///
 /// Like the request block, the response block consists of normal struct field definitions.
@@ -101,14 +276,48 @@ use std::convert::{TryFrom, TryInto};
 ///
 /// *   `#[ruma_api(header = HEADER_NAME)]`: Fields with this attribute will be treated as HTTP
 ///     headers on the response.
-///     The value must implement `AsRef<str>`.
-///     Generally this is a `String`.
+///     The value must implement `Display` and `FromStr`.
+///     Generally this is a `String`, but a typed value like `url::Url` for `LOCATION` means that a
+///     malformed header results in a `ResponseDeserializationError`.
 ///     The attribute value shown above as `HEADER_NAME` must be a header name constant from
-///     `http::header`, e.g. `CONTENT_TYPE`.
+///     `http::header`, e.g. `CONTENT_TYPE`, or a string literal for custom headers, e.g.
+///     `header = "X-Matrix-Foo"`.
+///     The field can also be an `Option` for optional headers like `ETAG`, or a
+///     `Vec` for headers like `LINK` that can appear multiple times. All occurrences of
+///     the header are collected into the `Vec`, and each element is sent as a separate header.
 ///
 /// Any field that does not include the above attribute will be expected in the response's JSON
 /// body.
 ///
+/// A response with the status `304 Not Modified` is not converted into the `Response` type, but
+/// results in `FromHttpResponseError::NotModified`. Similarly, a `412 Precondition Failed` response
+/// to a request with a header like `IF_MATCH` results in
+/// `FromHttpResponseError::PreconditionFailed`, so clients can implement compare-and-set updates.
+/// A `401 Unauthorized` response asking for user-interactive authentication results in
+/// `FromHttpResponseError::Uiaa` (see the `uiaa` module).
+///
+/// Besides the conversion from `http::Response<Vec<u8>>`, the incoming response type can be
+/// converted from `&http::Response<Vec<u8>>`, e.g. for a response that is kept in a cache. The
+/// status, version, headers and body of the borrowed response are copied for this.
+///
+/// ## Errors
+///
+/// Endpoints whose error bodies depend on the HTTP status can list one type per status in an
+/// optional `errors` block after the response block:
+///
+/// ```ignore
+/// errors {
+///     400 => ValidationError,
+///     403 => ForbiddenError,
+/// }
+/// ```
+///
+/// The macro then generates a `ResponseError` enum with a `Status400`, `Status403`, etc. variant
+/// per listed status and an `Other` variant holding the type of the `error` metadata key for all
+/// other statuses. It is used as the endpoint's error type, so `ServerError::error` of a `400`
+/// response returns a `ResponseError::Status400`. The listed types must implement `ErrorBody` and
+/// `Debug`.
+///
 /// ## Newtype bodies
 ///
 /// Both the request and response block also support "newtype bodies" by using the
@@ -118,9 +327,42 @@ use std::convert::{TryFrom, TryInto};
 /// each struct can be marked with this attribute. It is an error to have a newtype body field and
 /// normal body fields within the same struct.
 ///
+/// For prototyping endpoints or modeling under-specified APIs, the body can be accepted as an
+/// arbitrary JSON value with `#[ruma_api(json_body)]` on a field of type `serde_json::Value`. It
+/// behaves exactly like `body`, so the value is sent and received as-is, without being wrapped in
+/// an object.
+///
 /// There is another kind of newtype body that is enabled with `#[ruma_api(raw_body)]`. It is used
 /// for endpoints in which the request or response body can be arbitrary bytes instead of a JSON
-/// objects. A field with `#[ruma_api(raw_body)]` needs to have the type `Vec<u8>`.
+/// objects. A field with `#[ruma_api(raw_body)]` needs to have the type `Vec<u8>`. The `Vec<u8>`
+/// is moved into the body of the `http::Request` or `http::Response` and back out of it without
+/// being copied, so data read from a file or converted from `bytes::Bytes` only has to be
+/// materialized once.
+///
+/// Since a raw body can be in any format, a response with a `raw_body` field also gets a
+/// `content_type: Option<String>` field holding the value of the `Content-Type` header, unless
+/// another field of the response already has `#[ruma_api(header = CONTENT_TYPE)]`. Responses only
+/// default to a `Content-Type` of `application/json` if they have no such field.
+///
+/// Range requests, e.g. for resuming media downloads, can use a request field of type
+/// `ruma_api::header::ByteRange` with `#[ruma_api(header = RANGE)]`. A response field with
+/// `#[ruma_api(header = CONTENT_RANGE)]`, usually of type `Option<ruma_api::header::ContentRange>`,
+/// makes the response a `206 Partial Content` response whenever it is set, and `206` responses are
+/// accepted in addition to the endpoint's `status`.
+///
+/// Endpoints that support HTTP preferences (RFC 7240) can use a request field of type
+/// `Option<ruma_api::header::Preferences>` with `#[ruma_api(header = "Prefer")]`, e.g. to ask for
+/// `return=minimal`, and a response field of the same type with
+/// `#[ruma_api(header = "Preference-Applied")]` to find out which preferences were honored.
+///
+/// Request fields marked with `#[ruma_api(multipart)]` are sent as the parts of a
+/// `multipart/form-data` body, e.g. for file uploads with JSON metadata, and can't be combined with
+/// other body fields. Each part is named after its field, whose type has to implement
+/// `ruma_api::multipart::MultipartField` (see the `multipart` module).
+///
+/// Responses whose body is plain text can use `#[ruma_api(text_body)]` on a `String` field. The
+/// body is sent with a `Content-Type` of `text/plain; charset=utf-8`, and received bodies that are
+/// not valid UTF-8 result in a deserialization error.
 ///
 /// # Examples
 ///
@@ -204,7 +446,30 @@ pub use ruma_api_macros::ruma_api;
 #[cfg(feature = "with-ruma-api-macros")]
 pub use ruma_api_macros::Outgoing;
 
+pub mod appservice;
+pub mod auth;
+#[doc(hidden)]
+pub mod bounds;
+pub mod canonical_json;
+pub mod compression;
 pub mod error;
+pub mod header;
+pub mod multipart;
+pub mod path;
+pub mod query;
+#[cfg(feature = "rate-limit")]
+pub mod rate_limit;
+pub mod registry;
+#[cfg(feature = "with-ruma-api-macros")]
+#[doc(hidden)]
+pub mod roundtrip;
+#[cfg(feature = "sse")]
+pub mod sse;
+#[cfg(feature = "test-helpers")]
+pub mod test_helpers;
+pub mod timeout;
+pub mod transaction;
+pub mod uiaa;
 /// This module is used to support the generated code from ruma-api-macros.
 /// It is not considered part of ruma-api's public API.
 #[cfg(feature = "with-ruma-api-macros")]
@@ -218,7 +483,7 @@ pub mod exports {
     pub use url;
 }
 
-use error::{FromHttpRequestError, FromHttpResponseError, IntoHttpError};
+use error::{ErrorBody, FromHttpRequestError, FromHttpResponseError, IntoHttpError};
 
 /// A type that can be sent to another party that understands the matrix protocol. If any of the
 /// fields of `Self` don't implement serde's `Deserialize`, you can derive this trait to generate a
@@ -239,13 +504,82 @@ pub trait Endpoint: Outgoing + TryInto<http::Request<Vec<u8>>, Error = IntoHttpE
 where
     <Self as Outgoing>::Incoming: TryFrom<http::Request<Vec<u8>>, Error = FromHttpRequestError>,
     <Self::Response as Outgoing>::Incoming:
-        TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError>,
+        TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError<Self::EndpointError>>,
 {
     /// Data returned in a successful response from the endpoint.
     type Response: Outgoing + TryInto<http::Response<Vec<u8>>, Error = IntoHttpError>;
 
+    /// The type of the error bodies returned by the endpoint, `MatrixError` for most endpoints.
+    type EndpointError: ErrorBody + Debug;
+
     /// Metadata about the endpoint.
     const METADATA: Metadata;
+
+    /// The time the server may wait before responding to this request, for long-polling
+    /// endpoints.
+    ///
+    /// Transport code should use a client timeout somewhat longer than this, so that it doesn't
+    /// abort requests the server is still legitimately holding open. Defaults to `None`; endpoints
+    /// generated by `ruma_api!` return the value of their `#[ruma_api(timeout_hint)]` field.
+    fn suggested_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// The transaction ID field of the request, for endpoints with a
+    /// `#[ruma_api(path, transaction_id)]` field.
+    ///
+    /// Defaults to `None`, i.e. the endpoint has no transaction ID.
+    fn transaction_id_mut(&mut self) -> Option<&mut Option<String>> {
+        None
+    }
+
+    /// Fills in the transaction ID of the request if it is unset, and returns it.
+    ///
+    /// Transport code should call this once before sending a request and send the same request
+    /// value on every retry, so the server can recognize the retries as duplicates. Converting a
+    /// request with an unset transaction ID fails. Returns `None` for endpoints without a
+    /// transaction ID.
+    fn ensure_transaction_id(&mut self) -> Option<&str> {
+        self.transaction_id_mut()
+            .map(|txn_id| txn_id.get_or_insert_with(transaction::generate_transaction_id).as_str())
+    }
+}
+
+/// Statically checks that a request and response type satisfy the bounds of `Endpoint`.
+///
+/// The bounds of `Endpoint` tie together conversions of four types, so when one of them is missing
+/// from a hand-written endpoint, the compiler error is usually about the `Endpoint` trait as a
+/// whole. This macro checks each conversion on its own, so the error names the type and trait that
+/// are missing. Endpoints generated by `ruma_api!` always satisfy these bounds.
+///
+/// The error type defaults to `MatrixError` and can be given as a third argument.
+///
+/// # Example
+///
+/// ```ignore
+/// ruma_api::assert_endpoint!(Request, Response);
+/// ruma_api::assert_endpoint!(Request, Response, MyError);
+/// ```
+#[macro_export]
+macro_rules! assert_endpoint {
+    ($request:ty, $response:ty $(,)?) => {
+        $crate::assert_endpoint!($request, $response, $crate::MatrixError);
+    };
+    ($request:ty, $response:ty, $error:ty $(,)?) => {
+        const _: fn() = || {
+            $crate::bounds::request_implements_outgoing::<$request>();
+            $crate::bounds::request_converts_into_http_request::<$request>();
+            $crate::bounds::incoming_request_converts_from_http_request::<
+                <$request as $crate::Outgoing>::Incoming,
+            >();
+            $crate::bounds::response_implements_outgoing::<$response>();
+            $crate::bounds::response_converts_into_http_response::<$response>();
+            $crate::bounds::incoming_response_converts_from_http_response::<
+                <$response as $crate::Outgoing>::Incoming,
+                $error,
+            >();
+        };
+    };
 }
 
 /// Metadata about an API endpoint.
@@ -269,85 +603,398 @@ pub struct Metadata {
 
     /// Whether or not the server requires an authenticated user for this endpoint.
     pub requires_authentication: bool,
+
+    /// The HTTP status the server responds with when a request to this endpoint succeeds.
+    pub status: http::StatusCode,
+
+    /// Whether or not application services can use this endpoint with identity assertion.
+    ///
+    /// See the `appservice` module for details.
+    pub appservice: bool,
+
+    /// The API this endpoint belongs to.
+    pub api: ApiKind,
+
+    /// The version of the Matrix specification that introduced this endpoint, e.g. `"v1.1"`, if
+    /// declared.
+    pub added_in: Option<&'static str>,
+
+    /// The version of the Matrix specification that removed this endpoint, if declared.
+    pub removed_in: Option<&'static str>,
+}
+
+impl Metadata {
+    /// The path template of this endpoint with path parameters in the given style, e.g.
+    /// `/_matrix/client/r0/rooms/{room_id}/state` with `TemplateStyle::Braces`.
+    ///
+    /// See `path::convert_template`.
+    pub fn path_template(&self, style: path::TemplateStyle) -> String {
+        path::convert_template(self.path, style)
+    }
+}
+
+/// Formats the metadata as a one-line summary of the endpoint, e.g.
+/// `PUT /_matrix/client/r0/directory/room/:room_alias (create_alias)`.
+impl Display for Metadata {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({})", self.method, self.path, self.name)
+    }
 }
 
+/// Serializes the metadata as a map with the same field names, with the method as a string like
+/// `"GET"` and the status as a number, e.g. for exporting a catalog of endpoints as JSON.
+///
+/// There is no `Deserialize` implementation, since the fields borrow from static data.
+impl Serialize for Metadata {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct as _;
+
+        let mut state = serializer.serialize_struct("Metadata", 11)?;
+        state.serialize_field("description", self.description)?;
+        state.serialize_field("method", self.method.as_str())?;
+        state.serialize_field("name", self.name)?;
+        state.serialize_field("path", self.path)?;
+        state.serialize_field("rate_limited", &self.rate_limited)?;
+        state.serialize_field("requires_authentication", &self.requires_authentication)?;
+        state.serialize_field("status", &self.status.as_u16())?;
+        state.serialize_field("appservice", &self.appservice)?;
+        state.serialize_field("api", self.api.as_str())?;
+        state.serialize_field("added_in", &self.added_in)?;
+        state.serialize_field("removed_in", &self.removed_in)?;
+        state.end()
+    }
+}
+
+/// The Matrix API an endpoint belongs to.
+///
+/// The APIs are usually served under different base URLs and use different means of
+/// authentication, e.g. federation requests are sent to the server's federation port and signed
+/// instead of carrying an access token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ApiKind {
+    /// The client-server API.
+    ClientServer,
+
+    /// The server-server (federation) API.
+    ServerServer,
+
+    /// The content repository (media) API.
+    Media,
+
+    /// The identity service API.
+    Identity,
+
+    /// The application service API.
+    AppService,
+}
+
+impl ApiKind {
+    /// The name of the variant, e.g. `"ServerServer"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ClientServer => "ClientServer",
+            Self::ServerServer => "ServerServer",
+            Self::Media => "Media",
+            Self::Identity => "Identity",
+            Self::AppService => "AppService",
+        }
+    }
+}
+
+/// The kind of a Matrix error, as identified by the `errcode` field of the error body.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "errcode")]
-#[derive(Debug, Clone, Serialize, Deserialize)]
-enum MatrixErrorKind {
+#[non_exhaustive]
+pub enum MatrixErrorKind {
+    /// Forbidden access, e.g. joining a room without permission, failed login.
     #[serde(rename = "M_FORBIDDEN")]
     Forbidden,
+    /// The access token specified was not recognised.
     #[serde(rename = "M_UNKNOWN_TOKEN")]
-    UnknownToken,
+    UnknownToken {
+        /// Whether the client can log in again without discarding its local data, because the
+        /// session was ended by a soft logout.
+        #[serde(default, skip_serializing_if = "is_false")]
+        soft_logout: bool,
+    },
+    /// No access token was specified for the request.
     #[serde(rename = "M_MISSING_TOKEN")]
     MissingToken,
+    /// Request contained valid JSON, but it was malformed in some way.
     #[serde(rename = "M_BAD_JSON")]
     BadJson,
+    /// Request did not contain valid JSON.
     #[serde(rename = "M_NOT_JSON")]
     NotJson,
+    /// No resource was found for this request.
     #[serde(rename = "M_NOT_FOUND")]
     NotFound,
+    /// Too many requests have been sent in a short period of time.
     #[serde(rename = "M_LIMIT_EXCEEDED")]
     LimitExceeded,
+    /// An unknown error has occurred.
     #[serde(rename = "M_UNKNOWN")]
     Unknown,
+    /// The server did not understand the request.
     #[serde(rename = "M_UNRECOGNIZED")]
     Unrecognized,
+    /// The request was not correctly authorized.
     #[serde(rename = "M_UNAUTHORIZED")]
     Unauthorized,
+    /// Encountered when trying to register a user ID which has been taken.
     #[serde(rename = "M_USER_IN_USE")]
     UserInUse,
+    /// Encountered when trying to register a user ID which is not valid.
     #[serde(rename = "M_INVALID_USERNAME")]
     InvalidUsername,
+    /// Sent when the room alias given to the `createRoom` API is already in use.
     #[serde(rename = "M_ROOM_IN_USE")]
     RoomInUse,
+    /// Sent when the initial state given to the `createRoom` API is invalid.
     #[serde(rename = "M_INVALID_ROOM_STATE")]
     InvalidRoomState,
+    /// Sent when a threepid given to an API cannot be used because the same threepid is
+    /// already in use.
     #[serde(rename = "M_THREEPID_IN_USE")]
     ThreepidInUse,
+    /// Sent when a threepid given to an API cannot be used because no record matching the
+    /// threepid was found.
     #[serde(rename = "M_THREEPID_NOT_FOUND")]
     ThreepidNotFound,
+    /// Authentication could not be performed on the third party identifier.
     #[serde(rename = "M_THREEPID_AUTH_FAILED")]
     ThreepidAuthFailed,
+    /// The server does not permit this third party identifier.
     #[serde(rename = "M_THREEPID_DENIED")]
     ThreepidDenied,
+    /// The client's request used a third party server that this server does not trust.
     #[serde(rename = "M_SERVER_NOT_TRUSTED")]
     ServerNotTrusted,
+    /// The client's request to create a room used a room version that the server does
+    /// not support.
     #[serde(rename = "M_UNSUPPORTED_ROOM_VERSION")]
     UnsupportedRoomVersion,
+    /// The client attempted to join a room that has a version the server does not
+    /// support.
     #[serde(rename = "M_INCOMPATIBLE_ROOM_VERSION")]
     IncompatibleRoomVersion,
+    /// The state change requested cannot be performed.
     #[serde(rename = "M_BAD_STATE")]
     BadState,
+    /// The room or resource does not permit guests to access it.
     #[serde(rename = "M_GUEST_ACCESS_FORBIDDEN")]
     GuestAccessForbidden,
+    /// A Captcha is required to complete the request.
     #[serde(rename = "M_CAPTCHA_NEEDED")]
     CaptchaNeeded,
+    /// The Captcha provided did not match what was expected.
     #[serde(rename = "M_CAPTCHA_INVALID")]
     CaptchaInvalid,
+    /// A required parameter was missing from the request.
     #[serde(rename = "M_MISSING_PARAM")]
     MissingParam,
+    /// A parameter that was specified has the wrong value.
     #[serde(rename = "M_INVALID_PARAM")]
     InvalidParam,
+    /// The request or entity was too large.
     #[serde(rename = "M_TOO_LARGE")]
     TooLarge,
+    /// The resource being requested is reserved by an application service, or the
+    /// application service making the request has not created the resource.
     #[serde(rename = "M_EXCLUSIVE")]
     Exclusive,
+    /// The user has to agree to the server's terms of service. The `consent_uri` is available
+    /// through `MatrixError::extra`.
+    #[serde(rename = "M_CONSENT_NOT_GIVEN")]
+    ConsentNotGiven,
+    /// A resource limit of the server, like its monthly active user limit, has been exceeded. The
+    /// `admin_contact` is available through `MatrixError::extra`.
+    #[serde(rename = "M_RESOURCE_LIMIT_EXCEEDED")]
+    ResourceLimitExceeded,
+}
+
+impl MatrixErrorKind {
+    /// All known kinds of errors, in declaration order.
+    ///
+    /// Kinds with fields, like `UnknownToken`, are included once with default field values.
+    pub fn all() -> &'static [MatrixErrorKind] {
+        &[
+            Self::Forbidden,
+            Self::UnknownToken { soft_logout: false },
+            Self::MissingToken,
+            Self::BadJson,
+            Self::NotJson,
+            Self::NotFound,
+            Self::LimitExceeded,
+            Self::Unknown,
+            Self::Unrecognized,
+            Self::Unauthorized,
+            Self::UserInUse,
+            Self::InvalidUsername,
+            Self::RoomInUse,
+            Self::InvalidRoomState,
+            Self::ThreepidInUse,
+            Self::ThreepidNotFound,
+            Self::ThreepidAuthFailed,
+            Self::ThreepidDenied,
+            Self::ServerNotTrusted,
+            Self::UnsupportedRoomVersion,
+            Self::IncompatibleRoomVersion,
+            Self::BadState,
+            Self::GuestAccessForbidden,
+            Self::CaptchaNeeded,
+            Self::CaptchaInvalid,
+            Self::MissingParam,
+            Self::InvalidParam,
+            Self::TooLarge,
+            Self::Exclusive,
+            Self::ConsentNotGiven,
+            Self::ResourceLimitExceeded,
+        ]
+    }
+
+    /// The `errcode` of this kind of error, e.g. `"M_FORBIDDEN"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Forbidden => "M_FORBIDDEN",
+            Self::UnknownToken { .. } => "M_UNKNOWN_TOKEN",
+            Self::MissingToken => "M_MISSING_TOKEN",
+            Self::BadJson => "M_BAD_JSON",
+            Self::NotJson => "M_NOT_JSON",
+            Self::NotFound => "M_NOT_FOUND",
+            Self::LimitExceeded => "M_LIMIT_EXCEEDED",
+            Self::Unknown => "M_UNKNOWN",
+            Self::Unrecognized => "M_UNRECOGNIZED",
+            Self::Unauthorized => "M_UNAUTHORIZED",
+            Self::UserInUse => "M_USER_IN_USE",
+            Self::InvalidUsername => "M_INVALID_USERNAME",
+            Self::RoomInUse => "M_ROOM_IN_USE",
+            Self::InvalidRoomState => "M_INVALID_ROOM_STATE",
+            Self::ThreepidInUse => "M_THREEPID_IN_USE",
+            Self::ThreepidNotFound => "M_THREEPID_NOT_FOUND",
+            Self::ThreepidAuthFailed => "M_THREEPID_AUTH_FAILED",
+            Self::ThreepidDenied => "M_THREEPID_DENIED",
+            Self::ServerNotTrusted => "M_SERVER_NOT_TRUSTED",
+            Self::UnsupportedRoomVersion => "M_UNSUPPORTED_ROOM_VERSION",
+            Self::IncompatibleRoomVersion => "M_INCOMPATIBLE_ROOM_VERSION",
+            Self::BadState => "M_BAD_STATE",
+            Self::GuestAccessForbidden => "M_GUEST_ACCESS_FORBIDDEN",
+            Self::CaptchaNeeded => "M_CAPTCHA_NEEDED",
+            Self::CaptchaInvalid => "M_CAPTCHA_INVALID",
+            Self::MissingParam => "M_MISSING_PARAM",
+            Self::InvalidParam => "M_INVALID_PARAM",
+            Self::TooLarge => "M_TOO_LARGE",
+            Self::Exclusive => "M_EXCLUSIVE",
+            Self::ConsentNotGiven => "M_CONSENT_NOT_GIVEN",
+            Self::ResourceLimitExceeded => "M_RESOURCE_LIMIT_EXCEEDED",
+        }
+    }
+
+    /// The kind of error most likely meant by a response with the given HTTP status.
+    ///
+    /// This is used for error responses whose body is not a valid Matrix error.
+    pub fn from_status(status: http::StatusCode) -> Self {
+        match status.as_u16() {
+            401 => Self::Unauthorized,
+            403 => Self::Forbidden,
+            404 => Self::NotFound,
+            405 => Self::Unrecognized,
+            413 => Self::TooLarge,
+            429 => Self::LimitExceeded,
+            _ => Self::Unknown,
+        }
+    }
 }
 
 /// A Matrix Error
 ///
 /// The type implementing this trait contains any data needed to construct a matrix error.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct MatrixError {
+#[derive(Debug, Clone, Serialize)]
+pub struct MatrixError {
     /// A value which can be used to handle an error message
     #[serde(flatten)]
-    kind: MatrixErrorKind,
+    pub kind: MatrixErrorKind,
     /// The http status code
     #[serde(skip)]
-    status_code: http::StatusCode,
+    pub status_code: http::StatusCode,
     /// A human-readable error message, usually a sentence explaining what went wrong.
     #[serde(rename = "error")]
-    message: String,
+    pub message: String,
+    /// Fields of the error body besides `errcode`, `error` and the fields of the error kind.
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl MatrixError {
+    /// Creates a new `MatrixError` without any extra fields.
+    pub fn new(kind: MatrixErrorKind, status_code: http::StatusCode, message: String) -> Self {
+        Self { kind, status_code, message, extra: serde_json::Map::new() }
+    }
+
+    /// The fields of the error body besides `errcode`, `error` and the fields of the error kind,
+    /// like the `consent_uri` of an `M_CONSENT_NOT_GIVEN` error.
+    pub fn extra(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.extra
+    }
+
+    /// Mutable access to the extra fields of the error body, e.g. to add the `admin_contact` to an
+    /// `M_RESOURCE_LIMIT_EXCEEDED` error before sending it.
+    pub fn extra_mut(&mut self) -> &mut serde_json::Map<String, serde_json::Value> {
+        &mut self.extra
+    }
+
+    /// Whether this is an `M_UNKNOWN_TOKEN` error caused by a soft logout.
+    pub fn soft_logout(&self) -> bool {
+        matches!(self.kind, MatrixErrorKind::UnknownToken { soft_logout: true })
+    }
+}
+
+impl<'de> Deserialize<'de> for MatrixError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let extra = serde_json::Map::deserialize(deserializer)?;
+        Self::from_json_map(extra, MatrixErrorKind::Unknown)
+    }
+}
+
+impl MatrixError {
+    /// Creates a `MatrixError` from the fields of an error body.
+    ///
+    /// Some servers don't send an `errcode`, in which case `missing_errcode_kind` is used as the
+    /// kind, so the message isn't lost.
+    pub(crate) fn from_json_map<E: serde::de::Error>(
+        mut extra: serde_json::Map<String, serde_json::Value>,
+        missing_errcode_kind: MatrixErrorKind,
+    ) -> Result<Self, E> {
+        let message = match extra.remove("error") {
+            Some(serde_json::Value::String(message)) => message,
+            Some(_) => return Err(E::custom("invalid type for field `error`")),
+            None => return Err(E::missing_field("error")),
+        };
+        let kind = if extra.contains_key("errcode") {
+            MatrixErrorKind::deserialize(serde_json::Value::Object(extra.clone()))
+                .map_err(E::custom)?
+        } else {
+            missing_errcode_kind
+        };
+
+        extra.remove("errcode");
+        if let MatrixErrorKind::UnknownToken { .. } = kind {
+            extra.remove("soft_logout");
+        }
+
+        Ok(Self { kind, status_code: http::StatusCode::default(), message, extra })
+    }
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
 }
 
 impl From<MatrixError> for http::Response<Vec<u8>> {
@@ -372,10 +1019,10 @@ mod tests {
 
         use crate::{
             error::{
-                FromHttpRequestError, FromHttpResponseError, IntoHttpError,
-                RequestDeserializationError, ServerError,
+                self, DeserializationError, FromHttpRequestError, FromHttpResponseError,
+                IntoHttpError, RequestDeserializationError,
             },
-            Endpoint, Metadata, Outgoing,
+            ApiKind, Endpoint, MatrixError, Metadata, Outgoing,
         };
 
         /// A request to create a new room alias.
@@ -389,8 +1036,11 @@ mod tests {
             type Incoming = Self;
         }
 
+        crate::assert_endpoint!(Request, Response);
+
         impl Endpoint for Request {
             type Response = Response;
+            type EndpointError = MatrixError;
 
             const METADATA: Metadata = Metadata {
                 description: "Add an alias to a room.",
@@ -399,6 +1049,11 @@ mod tests {
                 path: "/_matrix/client/r0/directory/room/:room_alias",
                 rate_limited: false,
                 requires_authentication: true,
+                status: http::StatusCode::OK,
+                appservice: false,
+                api: ApiKind::ClientServer,
+                added_in: None,
+                removed_in: None,
             };
         }
 
@@ -408,10 +1063,15 @@ mod tests {
             fn try_from(request: Request) -> Result<http::Request<Vec<u8>>, Self::Error> {
                 let metadata = Request::METADATA;
 
-                let path = metadata
-                    .path
-                    .to_string()
-                    .replace(":room_alias", &request.room_alias.to_string());
+                let room_alias = request.room_alias.to_string();
+                let path = metadata.path.to_string().replace(
+                    ":room_alias",
+                    &percent_encoding::utf8_percent_encode(
+                        &room_alias,
+                        percent_encoding::NON_ALPHANUMERIC,
+                    )
+                    .to_string(),
+                );
 
                 let request_body = RequestBody { room_id: request.room_id };
 
@@ -442,10 +1102,14 @@ mod tests {
                     room_alias: {
                         let segment = path_segments.get(5).unwrap().as_bytes();
                         let decoded = percent_encoding::percent_decode(segment).decode_utf8_lossy();
-                        match serde_json::from_str(decoded.deref()) {
+                        match RoomAliasId::try_from(decoded.deref()) {
                             Ok(id) => id,
                             Err(err) => {
-                                return Err(RequestDeserializationError::new(err, request).into())
+                                return Err(RequestDeserializationError::new(
+                                    DeserializationError::Path(err.to_string()),
+                                    request,
+                                )
+                                .into())
                             }
                         }
                     },
@@ -469,12 +1133,9 @@ mod tests {
         impl TryFrom<http::Response<Vec<u8>>> for Response {
             type Error = FromHttpResponseError;
 
+            #[allow(clippy::result_large_err)]
             fn try_from(http_response: http::Response<Vec<u8>>) -> Result<Response, Self::Error> {
-                if http_response.status().as_u16() < 400 {
-                    Ok(Response)
-                } else {
-                    Err(FromHttpResponseError::Http(ServerError::new(http_response)))
-                }
+                error::from_http_response(http_response, |_| Ok(Response))
             }
         }
 
@@ -491,4 +1152,25 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn create_request_path_roundtrip() {
+        use std::convert::{TryFrom, TryInto};
+
+        use ruma_identifiers::{RoomAliasId, RoomId};
+
+        let req = create::Request {
+            room_id: RoomId::try_from("!roomid:example.org").unwrap(),
+            room_alias: RoomAliasId::try_from("#alias:example.org").unwrap(),
+        };
+        let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+        assert_eq!(
+            http_req.uri().path(),
+            "/_matrix/client/r0/directory/room/%23alias%3Aexample%2Eorg"
+        );
+
+        let req = create::Request::try_from(http_req).unwrap();
+        assert_eq!(req.room_alias.to_string(), "#alias:example.org");
+        assert_eq!(req.room_id.to_string(), "!roomid:example.org");
+    }
 }
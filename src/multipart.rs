@@ -0,0 +1,300 @@
+//! Types and helpers for `multipart/form-data` request bodies.
+//!
+//! Request fields marked with `#[ruma_api(multipart)]` are sent as one part each, named after the
+//! field. Their types have to implement `MultipartField`, which is implemented for `Part` (arbitrary
+//! bytes with an optional content type and file name), `String` (text), `Json<T>` (a JSON value)
+//! and `Option`s of these, which are left out of the body when `None`.
+
+use http::header::{HeaderMap, CONTENT_TYPE};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::{DeserializationError, IntoHttpError};
+
+/// A single part of a `multipart/form-data` body.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Part {
+    /// The `Content-Type` of the part.
+    pub content_type: Option<String>,
+
+    /// The file name given in the part's `Content-Disposition`.
+    pub filename: Option<String>,
+
+    /// The content of the part.
+    pub data: Vec<u8>,
+}
+
+/// A type that can be used as a field with the `#[ruma_api(multipart)]` attribute.
+pub trait MultipartField: Sized {
+    /// Converts `self` into a part, or `None` if the part should be left out.
+    fn to_part(&self) -> Result<Option<Part>, IntoHttpError>;
+
+    /// Converts the part with the field's name, or `None` if there is no such part.
+    fn from_part(part: Option<Part>) -> Result<Self, String>;
+}
+
+impl MultipartField for Part {
+    fn to_part(&self) -> Result<Option<Part>, IntoHttpError> {
+        Ok(Some(self.clone()))
+    }
+
+    fn from_part(part: Option<Part>) -> Result<Self, String> {
+        part.ok_or_else(|| "missing part".to_owned())
+    }
+}
+
+impl MultipartField for String {
+    fn to_part(&self) -> Result<Option<Part>, IntoHttpError> {
+        Ok(Some(Part {
+            content_type: Some("text/plain; charset=utf-8".to_owned()),
+            filename: None,
+            data: self.as_bytes().to_vec(),
+        }))
+    }
+
+    fn from_part(part: Option<Part>) -> Result<Self, String> {
+        let part = Part::from_part(part)?;
+        String::from_utf8(part.data).map_err(|err| err.to_string())
+    }
+}
+
+/// A part holding a JSON value, e.g. metadata about a file uploaded in another part.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize + DeserializeOwned> MultipartField for Json<T> {
+    fn to_part(&self) -> Result<Option<Part>, IntoHttpError> {
+        Ok(Some(Part {
+            content_type: Some("application/json".to_owned()),
+            filename: None,
+            data: serde_json::to_vec(&self.0)?,
+        }))
+    }
+
+    fn from_part(part: Option<Part>) -> Result<Self, String> {
+        let part = Part::from_part(part)?;
+        serde_json::from_slice(&part.data).map(Json).map_err(|err| err.to_string())
+    }
+}
+
+impl<T: MultipartField> MultipartField for Option<T> {
+    fn to_part(&self) -> Result<Option<Part>, IntoHttpError> {
+        match self {
+            Some(value) => value.to_part(),
+            None => Ok(None),
+        }
+    }
+
+    fn from_part(part: Option<Part>) -> Result<Self, String> {
+        part.map(|part| T::from_part(Some(part))).transpose()
+    }
+}
+
+/// Generates a new boundary for a `multipart/form-data` body.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn generate_boundary() -> String {
+    format!("ruma-api-boundary-{}", crate::transaction::generate_transaction_id())
+}
+
+/// Encodes the given named parts as a `multipart/form-data` body, returning the `Content-Type`
+/// header value and the body.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn encode(boundary: &str, parts: Vec<(&str, Option<Part>)>) -> (String, Vec<u8>) {
+    let mut body = Vec::new();
+
+    for (name, part) in parts {
+        let part = match part {
+            Some(part) => part,
+            None => continue,
+        };
+
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        body.extend_from_slice(
+            format!("Content-Disposition: form-data; name=\"{}\"", escape_param(name)).as_bytes(),
+        );
+        if let Some(filename) = &part.filename {
+            body.extend_from_slice(format!("; filename=\"{}\"", escape_param(filename)).as_bytes());
+        }
+        body.extend_from_slice(b"\r\n");
+        if let Some(content_type) = &part.content_type {
+            body.extend_from_slice(format!("Content-Type: {}\r\n", content_type).as_bytes());
+        }
+        body.extend_from_slice(b"\r\n");
+        body.extend_from_slice(&part.data);
+        body.extend_from_slice(b"\r\n");
+    }
+
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+/// Gets the boundary from the `multipart/form-data` `Content-Type` header of a request.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn boundary_from_headers(headers: &HeaderMap) -> Result<String, DeserializationError> {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| DeserializationError::Multipart("missing Content-Type header".to_owned()))?;
+
+    let mut params = content_type.split(';').map(str::trim);
+    if !matches!(params.next(), Some(mime) if mime.eq_ignore_ascii_case("multipart/form-data")) {
+        return Err(DeserializationError::Multipart(format!(
+            "expected a multipart/form-data body, got `{}`",
+            content_type
+        )));
+    }
+
+    params
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_owned())
+        .filter(|boundary| !boundary.is_empty())
+        .ok_or_else(|| DeserializationError::Multipart("missing multipart boundary".to_owned()))
+}
+
+/// Decodes a `multipart/form-data` body into its named parts.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn decode(boundary: &str, body: &[u8]) -> Result<Vec<(String, Part)>, DeserializationError> {
+    let error = |message: &str| DeserializationError::Multipart(message.to_owned());
+    let delimiter = format!("--{}", boundary).into_bytes();
+
+    let mut rest = match find(body, &delimiter) {
+        Some(pos) => &body[pos + delimiter.len()..],
+        None => return Err(error("missing multipart boundary in body")),
+    };
+    let mut parts = Vec::new();
+
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        rest = rest.strip_prefix(b"\r\n").ok_or_else(|| error("malformed multipart boundary"))?;
+
+        let headers_end = find(rest, b"\r\n\r\n").ok_or_else(|| error("malformed part headers"))?;
+        let headers = std::str::from_utf8(&rest[..headers_end])
+            .map_err(|_| error("part headers are not valid UTF-8"))?;
+        rest = &rest[headers_end + 4..];
+
+        let mut end_delimiter = b"\r\n".to_vec();
+        end_delimiter.extend_from_slice(&delimiter);
+        let data_end = find(rest, &end_delimiter).ok_or_else(|| error("unterminated part"))?;
+        let data = rest[..data_end].to_vec();
+        rest = &rest[data_end + end_delimiter.len()..];
+
+        let mut name = None;
+        let mut part = Part { data, ..Part::default() };
+        for header in headers.split("\r\n") {
+            let (header_name, value) = match header.split_once(':') {
+                Some((header_name, value)) => (header_name.trim(), value.trim()),
+                None => continue,
+            };
+
+            if header_name.eq_ignore_ascii_case("content-type") {
+                part.content_type = Some(value.to_owned());
+            } else if header_name.eq_ignore_ascii_case("content-disposition") {
+                for (key, value) in disposition_params(value) {
+                    match key {
+                        "name" => name = Some(value),
+                        "filename" => part.filename = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let name = name.ok_or_else(|| error("part without a name"))?;
+        parts.push((name, part));
+    }
+}
+
+/// Escapes a `name` or `filename` parameter like browsers do, percent-encoding `"`, CR and LF.
+fn escape_param(value: &str) -> String {
+    value.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Reverses `escape_param`.
+fn unescape_param(value: &str) -> String {
+    value.replace("%22", "\"").replace("%0D", "\r").replace("%0A", "\n")
+}
+
+/// Parses the parameters of a `Content-Disposition` header value, like
+/// `form-data; name="file"; filename="a; b.png"`, unquoting and unescaping their values.
+///
+/// Quoted values may contain `;`, and backslash escapes in them are resolved as well.
+fn disposition_params(value: &str) -> Vec<(&str, String)> {
+    let mut params = Vec::new();
+    let mut rest = match value.find(';') {
+        Some(pos) => &value[pos + 1..],
+        None => return params,
+    };
+
+    loop {
+        rest = rest.trim_start_matches(&[' ', '\t', ';'][..]);
+        let eq = match rest.find(&['=', ';'][..]) {
+            Some(eq) if rest[eq..].starts_with('=') => eq,
+            Some(end) => {
+                rest = &rest[end..];
+                continue;
+            }
+            None => return params,
+        };
+        let key = rest[..eq].trim();
+        rest = rest[eq + 1..].trim_start();
+
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                    '"' => {
+                        end = i + 1;
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+            rest = &quoted[end..];
+            value
+        } else {
+            let end = rest.find(';').unwrap_or(rest.len());
+            let value = rest[..end].trim().to_owned();
+            rest = &rest[end..];
+            value
+        };
+
+        params.push((key, unescape_param(&value)));
+    }
+}
+
+/// Removes the part with the given name from `parts` and converts it to the field type.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn take_part<T: MultipartField>(
+    parts: &mut Vec<(String, Part)>,
+    name: &str,
+) -> Result<T, DeserializationError> {
+    let part = parts.iter().position(|(n, _)| n == name).map(|pos| parts.remove(pos).1);
+    T::from_part(part).map_err(|message| {
+        DeserializationError::Multipart(format!("invalid part `{}`: {}", name, message))
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
@@ -0,0 +1,224 @@
+//! Types and helpers for the path of an endpoint's URL.
+
+#[cfg(feature = "percent-encoding")]
+use std::borrow::Cow;
+use std::{fmt::Display, str::FromStr};
+
+#[cfg(feature = "percent-encoding")]
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+#[cfg(feature = "url")]
+use crate::error::IntoHttpError;
+
+/// A type that can be used as a path parameter, i.e. a field with the `#[ruma_api(path)]`
+/// attribute.
+///
+/// This trait is implemented for all types that implement both `Display` and `FromStr`, in which
+/// case the path segment is the same as the `Display` output. Percent-encoding and -decoding of
+/// the segment is done by the generated code, so implementations don't have to care about it.
+pub trait PathParam: Sized {
+    /// The error returned when parsing a path segment fails.
+    type Error: Display;
+
+    /// Converts `self` into a (not yet percent-encoded) path segment.
+    fn to_path_segment(&self) -> String;
+
+    /// Parses a (percent-decoded) path segment.
+    fn from_path_segment(segment: &str) -> Result<Self, Self::Error>;
+}
+
+/// Percent-decodes a path segment of an incoming request.
+///
+/// Unless `lossy` is set, invalid percent-encoding and invalid UTF-8 result in an error. With
+/// `lossy`, invalid percent-encoding is kept as is and invalid UTF-8 is replaced by `U+FFFD`.
+///
+/// This function is public so it is accessible from `ruma_api!` generated code. It is not
+/// considered part of ruma-api's public API.
+#[cfg(feature = "percent-encoding")]
+#[doc(hidden)]
+pub fn decode_segment(segment: &str, lossy: bool) -> Result<Cow<'_, str>, String> {
+    let decoded = percent_encoding::percent_decode_str(segment);
+    if lossy {
+        return Ok(decoded.decode_utf8_lossy());
+    }
+
+    let bytes = segment.as_bytes();
+    for (i, _) in bytes.iter().enumerate().filter(|(_, b)| **b == b'%') {
+        let is_valid = matches!(
+            bytes.get(i + 1..i + 3),
+            Some([a, b]) if a.is_ascii_hexdigit() && b.is_ascii_hexdigit()
+        );
+
+        if !is_valid {
+            return Err(format!("invalid percent-encoding in path segment `{}`", segment));
+        }
+    }
+
+    decoded
+        .decode_utf8()
+        .map_err(|_| format!("path segment `{}` is not valid UTF-8 when decoded", segment))
+}
+
+/// The characters that are percent-encoded in a path segment, the same ones `url` encodes when
+/// pushing a path segment to a URL with a special scheme like `https`.
+#[cfg(feature = "percent-encoding")]
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// The characters that are percent-encoded in an element of a comma-separated path segment.
+#[cfg(feature = "percent-encoding")]
+const COMMA_SEPARATED_ELEMENT: &AsciiSet = &PATH_SEGMENT.add(b',');
+
+/// Percent-encodes a path segment of an outgoing request.
+///
+/// This function is public so it is accessible from `ruma_api!` generated code. It is not
+/// considered part of ruma-api's public API.
+#[cfg(feature = "percent-encoding")]
+#[doc(hidden)]
+pub fn encode_segment(segment: &str) -> String {
+    utf8_percent_encode(segment, PATH_SEGMENT).to_string()
+}
+
+/// Joins the given values into a single percent-encoded path segment like `a,b,c`.
+///
+/// Commas within the values are percent-encoded, so they can be told apart from the separators.
+///
+/// This function is public so it is accessible from `ruma_api!` generated code. It is not
+/// considered part of ruma-api's public API.
+#[cfg(feature = "percent-encoding")]
+#[doc(hidden)]
+pub fn join_comma_separated<T: PathParam>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(|value| {
+            utf8_percent_encode(&value.to_path_segment(), COMMA_SEPARATED_ELEMENT).to_string()
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Splits a path segment of an incoming request on commas, then percent-decodes and parses each
+/// element.
+///
+/// An empty segment results in an empty list. See `decode_segment` for the meaning of `lossy`.
+///
+/// This function is public so it is accessible from `ruma_api!` generated code. It is not
+/// considered part of ruma-api's public API.
+#[cfg(feature = "percent-encoding")]
+#[doc(hidden)]
+pub fn split_comma_separated<T: PathParam>(segment: &str, lossy: bool) -> Result<Vec<T>, String> {
+    if segment.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    segment
+        .split(',')
+        .map(|element| {
+            let decoded = decode_segment(element, lossy)?;
+            T::from_path_segment(&decoded).map_err(|err| err.to_string())
+        })
+        .collect()
+}
+
+/// Whether the given request path matches the path template of an endpoint, like
+/// `/_matrix/client/r0/rooms/:room_id/state`.
+///
+/// Path parameters in the template match any single non-empty path segment. The request path
+/// must not contain a query string, and isn't percent-decoded.
+pub fn matches_template(template: &str, path: &str) -> bool {
+    let mut template_segments = template.split('/');
+    let mut path_segments = path.split('/');
+
+    loop {
+        match (template_segments.next(), path_segments.next()) {
+            (None, None) => return true,
+            (Some(t), Some(p)) if t.starts_with(':') && !p.is_empty() => {}
+            (Some(t), Some(p)) if t == p => {}
+            _ => return false,
+        }
+    }
+}
+
+/// The syntax of path parameters in the route patterns of a server framework.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TemplateStyle {
+    /// Parameters prefixed with a colon, like `/rooms/:room_id`, as used by `ruma_api!` and by
+    /// axum before version 0.8.
+    Colon,
+
+    /// Parameters in braces, like `/rooms/{room_id}`, as used by `matchit` 0.8 and axum 0.8.
+    Braces,
+}
+
+/// Converts the path template of an endpoint, like `/_matrix/client/r0/rooms/:room_id/state`, to
+/// the given style, e.g. for registering the endpoint with a router.
+///
+/// Segments that aren't path parameters are kept as is.
+pub fn convert_template(template: &str, style: TemplateStyle) -> String {
+    template
+        .split('/')
+        .map(|segment| match (segment.strip_prefix(':'), style) {
+            (Some(name), TemplateStyle::Braces) => format!("{{{}}}", name),
+            _ => segment.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Appends the given path, which may be followed by a query string, to the path of `base_url`.
+///
+/// Unlike `Url::join`, any path of the base URL is kept as a prefix regardless of whether it ends
+/// in a slash, so a base URL of `https://example.org/matrix` and a path of `/_matrix/client/versions`
+/// result in `https://example.org/matrix/_matrix/client/versions`. The query string and fragment of
+/// the base URL are dropped. The path and query string are expected to be percent-encoded
+/// already.
+///
+/// Returns an error if `base_url` can't have a path, like `mailto:` URLs.
+#[cfg(feature = "url")]
+pub fn join_url(base_url: &url::Url, path_and_query: &str) -> Result<url::Url, IntoHttpError> {
+    if base_url.cannot_be_a_base() {
+        return Err(IntoHttpError::cannot_be_a_base_url());
+    }
+
+    let (path, query) = match path_and_query.find('?') {
+        Some(index) => (&path_and_query[..index], Some(&path_and_query[index + 1..])),
+        None => (path_and_query, None),
+    };
+
+    let mut url = base_url.clone();
+    let full_path =
+        format!("{}/{}", base_url.path().trim_end_matches('/'), path.trim_start_matches('/'));
+    url.set_path(&full_path);
+    url.set_query(query);
+    url.set_fragment(None);
+
+    Ok(url)
+}
+
+impl<T> PathParam for T
+where
+    T: Display + FromStr,
+    T::Err: Display,
+{
+    type Error = T::Err;
+
+    fn to_path_segment(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_path_segment(segment: &str) -> Result<Self, Self::Error> {
+        segment.parse()
+    }
+}
@@ -0,0 +1,272 @@
+//! Helpers for serializing the query string of a request.
+//!
+//! `serde_urlencoded` produces `application/x-www-form-urlencoded` output, which encodes spaces as
+//! `+`. For query strings, ruma-api uses the RFC 3986 percent-encoding `%20` instead, since that
+//! is what signing code and many servers expect. Incoming query strings are accepted in either
+//! form.
+//!
+//! `bool` query parameters are always serialized as the lowercase strings `true` and `false`, and
+//! only those two strings are accepted when parsing them. Endpoints that need to accept `1` and `0`
+//! as well can opt into that with `#[serde(deserialize_with = "ruma_api::query::lenient_bool")]`.
+//!
+//! Enum query parameters with unit variants use their serde representation, respecting
+//! `#[serde(rename)]` and `#[serde(rename_all)]`, so e.g. a `Direction` enum with variants renamed
+//! to `b` and `f` is sent as `dir=b`. Their `Debug` output is never used.
+
+use std::{
+    fmt::{self, Display},
+    iter::FromIterator,
+    str::FromStr,
+};
+
+use serde::{
+    de::{self, Deserializer, Visitor},
+    Serialize,
+};
+
+/// Serializes the given value as a query string.
+///
+/// This behaves like `serde_urlencoded::to_string`, except that spaces are encoded as `%20`
+/// rather than `+`.
+pub fn to_string<T: Serialize>(value: T) -> Result<String, serde_urlencoded::ser::Error> {
+    let form_encoded = serde_urlencoded::to_string(value)?;
+
+    // A literal `+` in the input is always percent-encoded as `%2B` by `serde_urlencoded`, so
+    // every `+` remaining in the output stands for a space.
+    Ok(form_encoded.replace('+', "%20"))
+}
+
+/// Appends one `key=value` pair per element of `values` to the query string `query`.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn append_repeated<T: Display>(
+    query: &mut String,
+    key: &str,
+    values: &[T],
+) -> Result<(), serde_urlencoded::ser::Error> {
+    let pairs: Vec<_> = values.iter().map(|value| (key, value.to_string())).collect();
+    push_pairs(query, pairs)
+}
+
+/// Appends the pairs of a `query_map` field with a `prefix` to the query string `query`, adding
+/// the prefix to every key.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn append_prefixed<T, V>(
+    query: &mut String,
+    prefix: &str,
+    map: T,
+) -> Result<(), serde_urlencoded::ser::Error>
+where
+    T: IntoIterator<Item = (String, V)>,
+    V: Display,
+{
+    let pairs: Vec<_> = map
+        .into_iter()
+        .map(|(key, value)| (format!("{}{}", prefix, key), value.to_string()))
+        .collect();
+    push_pairs(query, pairs)
+}
+
+/// Serializes `pairs` and appends them to the query string `query`, separated by `&` from what is
+/// already there.
+fn push_pairs<T: Serialize>(
+    query: &mut String,
+    pairs: T,
+) -> Result<(), serde_urlencoded::ser::Error> {
+    let pairs_str = to_string(pairs)?;
+
+    if !pairs_str.is_empty() {
+        if !query.is_empty() {
+            query.push('&');
+        }
+        query.push_str(&pairs_str);
+    }
+
+    Ok(())
+}
+
+/// Collects the pairs whose key starts with `prefix` into a `query_map` field, stripping the
+/// prefix from the keys and parsing the values with `FromStr`.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn parse_prefixed<T, V>(
+    pairs: &[(String, String)],
+    prefix: &str,
+) -> Result<T, serde_urlencoded::de::Error>
+where
+    T: IntoIterator<Item = (String, V)> + FromIterator<(String, V)>,
+    V: FromStr,
+    V::Err: Display,
+{
+    pairs
+        .iter()
+        .filter_map(|(key, value)| key.strip_prefix(prefix).map(|stripped| (key, stripped, value)))
+        .map(|(key, stripped, value)| match value.parse() {
+            Ok(value) => Ok((stripped.to_owned(), value)),
+            Err(err) => Err(serde::de::Error::custom(format!(
+                "invalid value for query parameter `{}`: {}",
+                key, err,
+            ))),
+        })
+        .collect()
+}
+
+/// Parses the values of all pairs with the given key.
+///
+/// This method is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub fn parse_repeated<T>(
+    pairs: &[(String, String)],
+    key: &str,
+) -> Result<Vec<T>, serde_urlencoded::de::Error>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    pairs
+        .iter()
+        .filter(|(k, _)| k == key)
+        .map(|(_, value)| {
+            value.parse().map_err(|err| {
+                serde::de::Error::custom(format!(
+                    "invalid value for query parameter `{}`: {}",
+                    key, err,
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Deserializes a `bool` query parameter, accepting `1` and `0` in addition to `true` and `false`.
+///
+/// For use with `#[serde(deserialize_with = "ruma_api::query::lenient_bool")]` on a `bool` query
+/// field. Serialization is unaffected and still produces `true` or `false`.
+pub fn lenient_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct LenientBoolVisitor;
+
+    impl<'de> Visitor<'de> for LenientBoolVisitor {
+        type Value = bool;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("`true`, `false`, `1` or `0`")
+        }
+
+        fn visit_bool<E: de::Error>(self, value: bool) -> Result<bool, E> {
+            Ok(value)
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<bool, E> {
+            match value {
+                "true" | "1" => Ok(true),
+                "false" | "0" => Ok(false),
+                _ => Err(E::invalid_value(de::Unexpected::Str(value), &self)),
+            }
+        }
+    }
+
+    deserializer.deserialize_str(LenientBoolVisitor)
+}
+
+/// (De)serialization of `Option<Option<T>>` query fields.
+///
+/// `None` omits the key, `Some(None)` is an empty value and `Some(Some(value))` uses `T`'s
+/// `Display` and `FromStr` implementations.
+///
+/// This module is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub mod tri_state {
+    use std::{borrow::Cow, fmt::Display, str::FromStr};
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T, S>(value: &Option<Option<T>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Display,
+        S: Serializer,
+    {
+        match value {
+            None => serializer.serialize_none(),
+            Some(None) => serializer.serialize_str(""),
+            Some(Some(value)) => serializer.collect_str(value),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let value = Cow::<'de, str>::deserialize(deserializer)?;
+
+        if value.is_empty() {
+            Ok(Some(None))
+        } else {
+            value.parse().map(|value| Some(Some(value))).map_err(de::Error::custom)
+        }
+    }
+}
+
+/// (De)serialization of `Duration` query fields as whole milliseconds.
+///
+/// This module is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub mod duration_ms {
+    use std::{convert::TryFrom, time::Duration};
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        match u64::try_from(value.as_millis()) {
+            Ok(ms) => serializer.serialize_u64(ms),
+            Err(_) => Err(serde::ser::Error::custom("duration is too long")),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let ms = u64::deserialize(deserializer)
+            .map_err(|err| de::Error::custom(format!("expected milliseconds: {}", err)))?;
+        Ok(Duration::from_millis(ms))
+    }
+}
+
+/// (De)serialization of `Option<Duration>` query fields as whole milliseconds.
+///
+/// This module is public so it is accessible from `ruma_api!` generated code. It is not considered
+/// part of ruma-api's public API.
+#[doc(hidden)]
+pub mod opt_duration_ms {
+    use std::time::Duration;
+
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(value) => super::duration_ms::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        super::duration_ms::deserialize(deserializer).map(Some)
+    }
+}
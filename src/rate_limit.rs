@@ -0,0 +1,131 @@
+//! Helpers for transport code to handle rate-limited endpoints.
+//!
+//! Endpoints declare whether they are rate limited in their `Metadata`. When a request to such an
+//! endpoint is rejected with `429 Too Many Requests`, a `RateLimiter` decides whether and after
+//! which delay the request should be retried. Sending the request again and waiting is left to the
+//! transport code, so this works with any HTTP client and async runtime.
+//!
+//! ```
+//! # use std::time::Duration;
+//! # use ruma_api::{error::{FromHttpResponseError, ServerError}, rate_limit::{ExponentialBackoff, RateLimiter}, ApiKind, Metadata};
+//! # let metadata = Metadata {
+//! #     description: "", method: http::Method::POST, name: "send", path: "/send",
+//! #     rate_limited: true, requires_authentication: true, status: http::StatusCode::OK,
+//! #     appservice: false, api: ApiKind::ClientServer, added_in: None, removed_in: None,
+//! # };
+//! # let response = http::Response::builder()
+//! #     .status(429)
+//! #     .body(br#"{"errcode":"M_LIMIT_EXCEEDED","error":"Slow down","retry_after_ms":2000}"#.to_vec())
+//! #     .unwrap();
+//! # let error: FromHttpResponseError = FromHttpResponseError::Http(ServerError::new(response));
+//! let mut limiter = RateLimiter::new(ExponentialBackoff::default());
+//!
+//! // `error` is the result of converting a `429 Too Many Requests` response with
+//! // `"retry_after_ms": 2000` in its body.
+//! assert_eq!(limiter.retry_delay(&metadata, 0, &error), Some(Duration::from_secs(2)));
+//! ```
+
+use std::time::Duration;
+
+use http::StatusCode;
+
+use crate::{error::FromHttpResponseError, Metadata};
+
+/// A strategy deciding how long to wait before retrying a rate-limited request.
+pub trait RateLimitStrategy {
+    /// Returns the delay before the given retry attempt (starting at 0), or `None` if the request
+    /// should not be retried anymore.
+    ///
+    /// `retry_after` is the delay requested by the server, if any.
+    fn delay(
+        &mut self,
+        metadata: &Metadata,
+        attempt: u32,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration>;
+}
+
+/// A strategy that waits as long as the server requests, or doubles the delay with every attempt
+/// if it didn't.
+#[derive(Clone, Copy, Debug)]
+pub struct ExponentialBackoff {
+    /// The delay before the first retry, if the server didn't request one.
+    pub initial_delay: Duration,
+
+    /// The longest delay to wait, even if the server requests a longer one.
+    pub max_delay: Duration,
+
+    /// The number of retries after which the request is given up.
+    pub max_attempts: u32,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            max_attempts: 5,
+        }
+    }
+}
+
+impl RateLimitStrategy for ExponentialBackoff {
+    fn delay(
+        &mut self,
+        _metadata: &Metadata,
+        attempt: u32,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        let delay = retry_after.unwrap_or_else(|| {
+            self.initial_delay.checked_mul(2u32.saturating_pow(attempt)).unwrap_or(self.max_delay)
+        });
+
+        Some(delay.min(self.max_delay))
+    }
+}
+
+/// Decides whether failed requests to rate-limited endpoints should be retried, using a
+/// pluggable `RateLimitStrategy`.
+#[derive(Clone, Debug, Default)]
+pub struct RateLimiter<S = ExponentialBackoff> {
+    strategy: S,
+}
+
+impl<S: RateLimitStrategy> RateLimiter<S> {
+    /// Creates a new `RateLimiter` with the given strategy.
+    pub fn new(strategy: S) -> Self {
+        Self { strategy }
+    }
+
+    /// Returns the delay after which the request that failed with `error` should be retried, or
+    /// `None` if it should not be retried.
+    ///
+    /// Only `429 Too Many Requests` responses of endpoints with `rate_limited: true` in their
+    /// metadata are retried. `attempt` is the number of retries made so far.
+    pub fn retry_delay<E>(
+        &mut self,
+        metadata: &Metadata,
+        attempt: u32,
+        error: &FromHttpResponseError<E>,
+    ) -> Option<Duration> {
+        if !metadata.rate_limited {
+            return None;
+        }
+
+        match error {
+            FromHttpResponseError::Http(err) if err.status() == StatusCode::TOO_MANY_REQUESTS => {
+                self.strategy.delay(metadata, attempt, err.retry_after())
+            }
+            _ => None,
+        }
+    }
+
+    /// The strategy of this `RateLimiter`.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+}
@@ -0,0 +1,90 @@
+//! A registry of endpoints, for dispatching incoming requests in server code.
+
+use http::Method;
+
+use crate::{path::matches_template, Metadata};
+
+/// A dispatch table mapping endpoints, identified by their method and path template, to handlers.
+///
+/// The handler type `H` is up to the server framework, e.g. a boxed trait object or a function
+/// pointer. Endpoints are registered with their `METADATA`:
+///
+/// ```
+/// # use ruma_api::{registry::Registry, Endpoint};
+/// # mod get_foo {
+/// #     ruma_api::ruma_api! {
+/// #         metadata {
+/// #             description: "Gets foo.",
+/// #             method: GET,
+/// #             name: "get_foo",
+/// #             path: "/_matrix/foo/:id",
+/// #             rate_limited: false,
+/// #             requires_authentication: false,
+/// #         }
+/// #         request {
+/// #             #[ruma_api(path)]
+/// #             pub id: String,
+/// #         }
+/// #         response {}
+/// #     }
+/// # }
+/// let mut registry = Registry::new();
+/// registry.register(get_foo::Request::METADATA, "get_foo handler").unwrap();
+///
+/// let (metadata, handler) = registry.find(&http::Method::GET, "/_matrix/foo/bar").unwrap();
+/// assert_eq!(metadata.name, "get_foo");
+/// assert_eq!(*handler, "get_foo handler");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Registry<H> {
+    entries: Vec<(Metadata, H)>,
+}
+
+impl<H> Registry<H> {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    /// Registers the endpoint with the given metadata.
+    ///
+    /// If an endpoint with the same method and path template is already registered, the registry
+    /// is left unchanged and the metadata of the existing endpoint is returned as the error.
+    pub fn register(&mut self, metadata: Metadata, handler: H) -> Result<(), Metadata> {
+        let existing = self
+            .entries
+            .iter()
+            .find(|(m, _)| m.method == metadata.method && m.path == metadata.path);
+
+        if let Some((existing, _)) = existing {
+            return Err(existing.clone());
+        }
+
+        self.entries.push((metadata, handler));
+        Ok(())
+    }
+
+    /// Finds the endpoint matching the given method and request path, along with its handler.
+    ///
+    /// Path parameters in the template match any single non-empty path segment. The request path
+    /// must not contain a query string.
+    pub fn find(&self, method: &Method, path: &str) -> Option<(&Metadata, &H)> {
+        self.entries
+            .iter()
+            .find(|(metadata, _)| {
+                metadata.method == method && matches_template(metadata.path, path)
+            })
+            .map(|(metadata, handler)| (metadata, handler))
+    }
+
+    /// The method and path template of every registered endpoint, in registration order.
+    pub fn routes(&self) -> impl Iterator<Item = (&Method, &'static str)> {
+        self.entries.iter().map(|(metadata, _)| (&metadata.method, metadata.path))
+    }
+}
+
+impl<H> Default for Registry<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,63 @@
+//! The check performed by the `validate-roundtrip` feature.
+//!
+//! With the feature enabled, `ruma_api!` generated code parses every JSON body it serializes back
+//! into the body type before sending it, serializes the result again and panics if that fails or
+//! produces different JSON. This catches `Serialize` implementations that produce output their
+//! `Deserialize` counterpart doesn't accept or only accepts with a loss of information. The check
+//! only runs in builds with debug assertions, in all other builds it compiles to nothing.
+//!
+//! This module is public so it is accessible from `ruma_api!` generated code. It is not considered
+//! part of ruma-api's public API.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Panics if `body` can't be deserialized as `T`, or if serializing the result doesn't produce the
+/// same JSON as `body`, if the `validate-roundtrip` feature is enabled and debug assertions are on.
+#[inline]
+#[cfg_attr(not(all(feature = "validate-roundtrip", debug_assertions)), allow(unused_variables))]
+pub fn check<T: DeserializeOwned + Serialize>(body: &[u8]) {
+    #[cfg(all(feature = "validate-roundtrip", debug_assertions))]
+    {
+        let value = deserialize::<T>(body);
+        let original: serde_json::Value =
+            serde_json::from_slice(body).expect("serialized body is valid JSON");
+        let reserialized = serde_json::to_value(&value).unwrap_or_else(|err| {
+            panic!(
+                "deserialized body of type `{}` doesn't serialize again: {}",
+                std::any::type_name::<T>(),
+                err,
+            )
+        });
+
+        assert_eq!(
+            reserialized,
+            original,
+            "serialized body changes in a roundtrip through `{}`",
+            std::any::type_name::<T>(),
+        );
+    }
+}
+
+/// Panics if `body` can't be deserialized as `T`, if the `validate-roundtrip` feature is enabled
+/// and debug assertions are on.
+///
+/// This is the check for body types whose 'Incoming' type can't be serialized, i.e. bodies with
+/// `#[wrap_incoming]` fields.
+#[inline]
+#[cfg_attr(not(all(feature = "validate-roundtrip", debug_assertions)), allow(unused_variables))]
+pub fn check_deserializes<T: DeserializeOwned>(body: &[u8]) {
+    #[cfg(all(feature = "validate-roundtrip", debug_assertions))]
+    deserialize::<T>(body);
+}
+
+#[cfg(all(feature = "validate-roundtrip", debug_assertions))]
+fn deserialize<T: DeserializeOwned>(body: &[u8]) -> T {
+    serde_json::from_slice(body).unwrap_or_else(|err| {
+        panic!(
+            "serialized body doesn't deserialize as `{}` again: {}\nbody: {}",
+            std::any::type_name::<T>(),
+            err,
+            String::from_utf8_lossy(body),
+        )
+    })
+}
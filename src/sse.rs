@@ -0,0 +1,144 @@
+//! Parsing of `text/event-stream` (Server-Sent Events) response bodies.
+//!
+//! Endpoints that stream events declare their response body as a `#[ruma_api(raw_body)]` field.
+//! `parse` splits a complete body into events. For bodies that arrive in chunks, `Decoder` yields
+//! every event as soon as the blank line terminating it has been received, so the body never has
+//! to be buffered as a whole.
+//!
+//! Like in the SSE specification, lines may be terminated by `\r\n`, `\n` or a lone `\r`.
+
+use std::{mem, time::Duration};
+
+/// A single event of an event stream.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    /// The type of the event, from the `event` field.
+    pub event: Option<String>,
+
+    /// The data of the event. Multiple `data` fields are joined with newlines.
+    pub data: String,
+
+    /// The ID of the event, from the `id` field.
+    pub id: Option<String>,
+
+    /// The reconnection time requested by the server, from the `retry` field.
+    pub retry: Option<Duration>,
+}
+
+/// An incremental parser for event streams.
+#[derive(Clone, Debug, Default)]
+pub struct Decoder {
+    /// The received bytes that are not part of a returned event yet, with line endings
+    /// normalized to `\n`.
+    buffer: Vec<u8>,
+
+    /// Whether the last received byte was a `\r`, so a `\n` at the start of the next chunk
+    /// belongs to the same line ending.
+    after_cr: bool,
+}
+
+impl Decoder {
+    /// Creates a new `Decoder` with an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a chunk of the response body to the buffer.
+    pub fn push(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            let after_cr = mem::replace(&mut self.after_cr, byte == b'\r');
+
+            match byte {
+                b'\r' => self.buffer.push(b'\n'),
+                b'\n' if after_cr => {}
+                _ => self.buffer.push(byte),
+            }
+        }
+    }
+
+    /// Returns the next complete event in the buffer, if any.
+    ///
+    /// Blocks without a `data` field, such as keep-alive comments, are skipped.
+    pub fn next_event(&mut self) -> Option<Event> {
+        loop {
+            let end = self.buffer.windows(2).position(|window| window == b"\n\n")?;
+            let rest = self.buffer.split_off(end + 2);
+            let block = mem::replace(&mut self.buffer, rest);
+
+            if let Some(event) = parse_block(&block[..end]) {
+                return Some(event);
+            }
+        }
+    }
+
+    /// Returns the event at the end of the buffer that was not terminated by a blank line, if
+    /// any, and clears the buffer.
+    ///
+    /// This should be called once the body has been received completely.
+    pub fn finish(&mut self) -> Option<Event> {
+        let block = mem::take(&mut self.buffer);
+        parse_block(&block)
+    }
+}
+
+impl Iterator for Decoder {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.next_event()
+    }
+}
+
+/// Parses a complete event stream body into its events.
+pub fn parse(body: &[u8]) -> Vec<Event> {
+    let mut decoder = Decoder::new();
+    decoder.push(body);
+
+    let mut events: Vec<_> = decoder.by_ref().collect();
+    events.extend(decoder.finish());
+    events
+}
+
+fn parse_block(block: &[u8]) -> Option<Event> {
+    let block = String::from_utf8_lossy(block);
+    let mut event = Event::default();
+    let mut has_data = false;
+
+    for line in block.lines() {
+        if line.starts_with(':') {
+            continue;
+        }
+
+        let (field, value) = match line.find(':') {
+            Some(pos) => {
+                let value = &line[pos + 1..];
+                (&line[..pos], value.strip_prefix(' ').unwrap_or(value))
+            }
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => event.event = Some(value.to_owned()),
+            "data" => {
+                if has_data {
+                    event.data.push('\n');
+                }
+                event.data.push_str(value);
+                has_data = true;
+            }
+            "id" => event.id = Some(value.to_owned()),
+            "retry" => {
+                if let Ok(millis) = value.parse() {
+                    event.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if has_data {
+        Some(event)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,226 @@
+//! Helpers for testing code that uses ruma-api endpoints.
+//!
+//! This module is only available with the `test-helpers` feature.
+
+use std::convert::{TryFrom, TryInto};
+
+use http::header::CONTENT_TYPE;
+
+use crate::{
+    error::{FromHttpRequestError, FromHttpResponseError},
+    Endpoint, MatrixError, MatrixErrorKind, Outgoing,
+};
+
+/// Creates an HTTP response that `E`'s response type parses into the given response.
+///
+/// # Panics
+///
+/// Panics if the response can't be converted into an HTTP response.
+pub fn mock_http_response<E>(response: E::Response) -> http::Response<Vec<u8>>
+where
+    E: Endpoint,
+    <E as Outgoing>::Incoming: TryFrom<http::Request<Vec<u8>>, Error = FromHttpRequestError>,
+    <E::Response as Outgoing>::Incoming:
+        TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError<E::EndpointError>>,
+{
+    match response.try_into() {
+        Ok(http_response) => http_response,
+        Err(err) => panic!("failed to convert response into an HTTP response: {}", err),
+    }
+}
+
+/// Like `mock_http_response`, but replaces the status of the HTTP response with the given one.
+///
+/// This is useful for testing how client code deals with unexpected statuses.
+pub fn mock_http_response_with_status<E>(
+    response: E::Response,
+    status: http::StatusCode,
+) -> http::Response<Vec<u8>>
+where
+    E: Endpoint,
+    <E as Outgoing>::Incoming: TryFrom<http::Request<Vec<u8>>, Error = FromHttpRequestError>,
+    <E::Response as Outgoing>::Incoming:
+        TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError<E::EndpointError>>,
+{
+    let mut http_response = mock_http_response::<E>(response);
+    *http_response.status_mut() = status;
+    http_response
+}
+
+/// Creates an HTTP response containing a Matrix error with the given error kind, status and
+/// message.
+///
+/// Parsing it with any endpoint's response type results in `FromHttpResponseError::Http`.
+pub fn mock_error_response(
+    kind: MatrixErrorKind,
+    status: http::StatusCode,
+    message: &str,
+) -> http::Response<Vec<u8>> {
+    MatrixError::new(kind, status, message.to_owned()).into()
+}
+
+/// Checks that `E` parses a recorded HTTP request or response, and that the parsed value
+/// serializes back to an equivalent one.
+///
+/// `fixture` is an HTTP/1.1 message as it was sent over the wire, e.g. captured traffic of a real
+/// homeserver: a request line like `GET /_matrix/client/r0/... HTTP/1.1` or a status line like
+/// `HTTP/1.1 200 OK`, followed by the headers, an empty line and the body. Lines may end in
+/// `\r\n` or `\n`, and chunked bodies are not supported.
+///
+/// Requests are parsed with `E` and responses with `E::Response`. The messages are considered
+/// equivalent if they have the same method, percent-decoded path and query parameters (in any
+/// order) or the same status, and the same body, compared as JSON if both bodies are JSON. Of the headers, only those
+/// that the re-serialized message has are compared, except for `Content-Type`, since recorded
+/// traffic usually contains headers added by the HTTP client or server.
+///
+/// # Panics
+///
+/// Panics if the fixture is malformed, if it can't be parsed or if the re-serialized message is
+/// not equivalent to it.
+pub fn assert_parses_fixture<E>(fixture: &[u8])
+where
+    E: Endpoint
+        + Outgoing<Incoming = E>
+        + TryFrom<http::Request<Vec<u8>>, Error = FromHttpRequestError>,
+    E::Response: Outgoing<Incoming = E::Response>
+        + TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError<E::EndpointError>>,
+{
+    let (head, body) = split_fixture(fixture);
+    let mut lines = head.lines();
+    let start_line = lines.next().unwrap_or_default();
+    let headers = parse_fixture_headers(lines);
+
+    if start_line.starts_with("HTTP/") {
+        let status = start_line
+            .split(' ')
+            .nth(1)
+            .and_then(|status| http::StatusCode::from_bytes(status.as_bytes()).ok())
+            .unwrap_or_else(|| panic!("invalid status line in fixture: `{}`", start_line));
+
+        let mut recorded = http::Response::new(body.to_vec());
+        *recorded.status_mut() = status;
+        *recorded.headers_mut() = headers.clone();
+
+        let response = match E::Response::try_from(recorded) {
+            Ok(response) => response,
+            Err(err) => panic!("failed to parse response fixture: {}", err),
+        };
+        let reserialized: http::Response<Vec<u8>> = match response.try_into() {
+            Ok(reserialized) => reserialized,
+            Err(err) => panic!("failed to re-serialize response: {}", err),
+        };
+
+        assert_eq!(reserialized.status(), status, "status differs from fixture");
+        assert_equivalent_headers(reserialized.headers(), &headers);
+        assert_equivalent_bodies(reserialized.body(), body);
+    } else {
+        let mut parts = start_line.split(' ');
+        let (method, uri) = match (parts.next(), parts.next()) {
+            (Some(method), Some(uri)) => (method, uri),
+            _ => panic!("invalid request line in fixture: `{}`", start_line),
+        };
+
+        let method: http::Method =
+            method.parse().unwrap_or_else(|_| panic!("invalid method in fixture: `{}`", method));
+        let uri: http::Uri =
+            uri.parse().unwrap_or_else(|_| panic!("invalid request target in fixture: `{}`", uri));
+
+        let mut recorded = http::Request::new(body.to_vec());
+        *recorded.method_mut() = method.clone();
+        *recorded.uri_mut() = uri.clone();
+        *recorded.headers_mut() = headers.clone();
+
+        let request = match E::try_from(recorded) {
+            Ok(request) => request,
+            Err(err) => panic!("failed to parse request fixture: {}", err),
+        };
+        let reserialized: http::Request<Vec<u8>> = match request.try_into() {
+            Ok(reserialized) => reserialized,
+            Err(err) => panic!("failed to re-serialize request: {}", err),
+        };
+
+        assert_eq!(*reserialized.method(), method, "method differs from fixture");
+        assert_eq!(
+            decoded_path(reserialized.uri()),
+            decoded_path(&uri),
+            "path differs from fixture",
+        );
+        assert_eq!(
+            sorted_query_pairs(reserialized.uri()),
+            sorted_query_pairs(&uri),
+            "query parameters differ from fixture",
+        );
+        assert_equivalent_headers(reserialized.headers(), &headers);
+        assert_equivalent_bodies(reserialized.body(), body);
+    }
+}
+
+/// Splits an HTTP message into its head, which has to be UTF-8, and its body.
+fn split_fixture(fixture: &[u8]) -> (&str, &[u8]) {
+    let (head_len, separator_len) = fixture
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|pos| (pos, 4))
+        .or_else(|| fixture.windows(2).position(|window| window == b"\n\n").map(|pos| (pos, 2)))
+        .unwrap_or((fixture.len(), 0));
+
+    match std::str::from_utf8(&fixture[..head_len]) {
+        Ok(head) => (head, &fixture[head_len + separator_len..]),
+        Err(_) => panic!("head of fixture is not valid UTF-8"),
+    }
+}
+
+fn parse_fixture_headers<'a>(lines: impl Iterator<Item = &'a str>) -> http::HeaderMap {
+    let mut headers = http::HeaderMap::new();
+
+    for line in lines {
+        let (name, value) = match line.find(':') {
+            Some(index) => (&line[..index], line[index + 1..].trim()),
+            None => panic!("invalid header line in fixture: `{}`", line),
+        };
+
+        match (name.parse::<http::header::HeaderName>(), value.parse()) {
+            (Ok(name), Ok(value)) => {
+                headers.append(name, value);
+            }
+            _ => panic!("invalid header line in fixture: `{}`", line),
+        }
+    }
+
+    headers
+}
+
+/// The percent-decoded path of the given URI, since clients differ in which characters they
+/// percent-encode.
+fn decoded_path(uri: &http::Uri) -> String {
+    percent_encoding::percent_decode_str(uri.path()).decode_utf8_lossy().into_owned()
+}
+
+fn sorted_query_pairs(uri: &http::Uri) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> =
+        serde_urlencoded::from_str(uri.query().unwrap_or_default())
+            .unwrap_or_else(|err| panic!("invalid query string: {}", err));
+    pairs.sort();
+    pairs
+}
+
+fn assert_equivalent_headers(reserialized: &http::HeaderMap, recorded: &http::HeaderMap) {
+    for name in reserialized.keys().filter(|name| *name != CONTENT_TYPE) {
+        let reserialized_values: Vec<_> = reserialized.get_all(name).iter().collect();
+        let recorded_values: Vec<_> = recorded.get_all(name).iter().collect();
+
+        assert_eq!(reserialized_values, recorded_values, "header `{}` differs from fixture", name);
+    }
+}
+
+fn assert_equivalent_bodies(reserialized: &[u8], recorded: &[u8]) {
+    let reserialized_json = serde_json::from_slice::<serde_json::Value>(reserialized);
+    let recorded_json = serde_json::from_slice::<serde_json::Value>(recorded);
+
+    match (reserialized_json, recorded_json) {
+        (Ok(reserialized), Ok(recorded)) => {
+            assert_eq!(reserialized, recorded, "body differs from fixture")
+        }
+        _ => assert_eq!(reserialized, recorded, "body differs from fixture"),
+    }
+}
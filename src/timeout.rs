@@ -0,0 +1,37 @@
+//! Types and helpers for the timeouts of long-polling endpoints.
+
+use std::time::Duration;
+
+/// A type that can be used as the timeout hint of a long-polling endpoint, i.e. a field with the
+/// `#[ruma_api(timeout_hint)]` attribute.
+///
+/// Integers are interpreted as milliseconds, since that is how the Matrix specification represents
+/// timeouts.
+pub trait TimeoutHint {
+    /// The duration the server might wait before responding, if any.
+    fn to_duration(&self) -> Option<Duration>;
+}
+
+impl TimeoutHint for Duration {
+    fn to_duration(&self) -> Option<Duration> {
+        Some(*self)
+    }
+}
+
+impl TimeoutHint for u64 {
+    fn to_duration(&self) -> Option<Duration> {
+        Some(Duration::from_millis(*self))
+    }
+}
+
+impl TimeoutHint for u32 {
+    fn to_duration(&self) -> Option<Duration> {
+        Some(Duration::from_millis((*self).into()))
+    }
+}
+
+impl<T: TimeoutHint> TimeoutHint for Option<T> {
+    fn to_duration(&self) -> Option<Duration> {
+        self.as_ref().and_then(TimeoutHint::to_duration)
+    }
+}
@@ -0,0 +1,20 @@
+//! Helpers for the transaction IDs of idempotent endpoints.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Generates a new transaction ID.
+///
+/// The ID combines the current time with a process-wide counter, so IDs generated by one process
+/// never repeat and IDs from different processes are very unlikely to collide. It only contains
+/// characters that don't need to be percent-encoded in a path.
+pub fn generate_transaction_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    format!("{:x}.{:x}", nanos, count)
+}
@@ -0,0 +1,98 @@
+//! Types for user-interactive authentication.
+//!
+//! Endpoints like registration or password changes may require the client to complete one or more
+//! authentication stages. The server signals this with a `401 Unauthorized` response whose body
+//! describes the available flows instead of an error. Response conversions generated by
+//! `ruma_api!` return such responses as `FromHttpResponseError::Uiaa` for every endpoint, so
+//! clients can complete a stage and retry the request with the `session`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    header::{self, Challenge},
+    MatrixError,
+};
+
+/// The body of a `401 Unauthorized` response asking for user-interactive authentication.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UiaaResponse {
+    /// The flows of authentication stages the client can complete.
+    pub flows: Vec<AuthFlow>,
+
+    /// The stages the client has already completed in this session.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub completed: Vec<String>,
+
+    /// Parameters for the stages, keyed by stage type, like the public key of a Captcha stage.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub params: serde_json::Map<String, serde_json::Value>,
+
+    /// The session to pass back to the server when retrying the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+
+    /// The error of the previous authentication attempt, e.g. `M_FORBIDDEN` for a wrong password.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub auth_error: Option<MatrixError>,
+
+    /// The challenges of the `WWW-Authenticate` headers of the response, if it had any.
+    ///
+    /// This is not part of the body. It is filled in when converting an HTTP response, so clients
+    /// behind gateways can tell which kinds of authentication are accepted.
+    #[serde(skip)]
+    pub www_authenticate: Vec<Challenge>,
+}
+
+impl UiaaResponse {
+    /// Creates a new `UiaaResponse` with the given flows and no other information.
+    pub fn new(flows: Vec<AuthFlow>) -> Self {
+        Self {
+            flows,
+            completed: Vec::new(),
+            params: serde_json::Map::new(),
+            session: None,
+            auth_error: None,
+            www_authenticate: Vec::new(),
+        }
+    }
+}
+
+/// Converts the response into a `401 Unauthorized` HTTP response, with one `WWW-Authenticate`
+/// header per challenge in `www_authenticate`.
+impl From<UiaaResponse> for http::Response<Vec<u8>> {
+    fn from(response: UiaaResponse) -> http::Response<Vec<u8>> {
+        let mut builder = http::Response::builder()
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .status(http::StatusCode::UNAUTHORIZED);
+        for challenge in &response.www_authenticate {
+            builder = builder.header(http::header::WWW_AUTHENTICATE, challenge.to_string());
+        }
+
+        builder.body(serde_json::to_vec(&response).unwrap()).unwrap()
+    }
+}
+
+/// A sequence of authentication stages that completes user-interactive authentication.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuthFlow {
+    /// The types of the stages, like `m.login.password`, in the order they have to be completed.
+    pub stages: Vec<String>,
+}
+
+impl AuthFlow {
+    /// Creates a new `AuthFlow` with the given stages.
+    pub fn new(stages: Vec<String>) -> Self {
+        Self { stages }
+    }
+}
+
+/// Parses the body of a `401 Unauthorized` response as a `UiaaResponse`, if it is one.
+pub(crate) fn from_http_response(response: &http::Response<Vec<u8>>) -> Option<UiaaResponse> {
+    let mut uiaa: UiaaResponse = serde_json::from_slice(response.body()).ok()?;
+    if let Some(error) = &mut uiaa.auth_error {
+        error.status_code = response.status();
+    }
+    uiaa.www_authenticate = header::www_authenticate(response.headers());
+
+    Some(uiaa)
+}
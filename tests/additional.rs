@@ -0,0 +1,73 @@
+use std::{
+    collections::BTreeMap,
+    convert::{TryFrom, TryInto},
+};
+
+use ruma_api::ruma_api;
+use serde_json::{json, Value as JsonValue};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "some_endpoint",
+        path: "/_matrix/some/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        pub name: String,
+
+        #[ruma_api(additional)]
+        pub additional: BTreeMap<String, JsonValue>,
+    }
+
+    response {
+        pub version: String,
+
+        #[ruma_api(additional)]
+        pub additional: BTreeMap<String, JsonValue>,
+    }
+}
+
+#[test]
+fn response_collects_unknown_fields() {
+    let http_res = http::Response::builder()
+        .body(br#"{ "version": "1.0", "new_field": [1, 2], "other": null }"#.to_vec())
+        .unwrap();
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.version, "1.0");
+    assert_eq!(res.additional.len(), 2);
+    assert_eq!(res.additional["new_field"], json!([1, 2]));
+    assert_eq!(res.additional["other"], JsonValue::Null);
+}
+
+#[test]
+fn response_roundtrips_unknown_fields() {
+    let mut additional = BTreeMap::new();
+    additional.insert("new_field".to_owned(), json!({ "a": true }));
+    let res = Response { version: "1.0".to_owned(), additional };
+
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+    let body: JsonValue = serde_json::from_slice(http_res.body()).unwrap();
+    assert_eq!(body, json!({ "version": "1.0", "new_field": { "a": true } }));
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.additional["new_field"], json!({ "a": true }));
+}
+
+#[test]
+fn request_roundtrips_unknown_fields() {
+    let mut additional = BTreeMap::new();
+    additional.insert("extra".to_owned(), json!(5));
+    let req = Request { name: "foo".to_owned(), additional };
+
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    let req = Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.name, "foo");
+    assert_eq!(req.additional.len(), 1);
+    assert_eq!(req.additional["extra"], json!(5));
+}
@@ -0,0 +1,90 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::{appservice::add_identity_assertion, auth::AccessToken, Endpoint};
+
+mod regular {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "regular_endpoint",
+            path: "/_matrix/some/regular/endpoint",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {}
+
+        response {}
+    }
+}
+
+ruma_api::ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "appservice_endpoint",
+        path: "/_matrix/some/appservice/endpoint",
+        rate_limited: false,
+        requires_authentication: true,
+        appservice: true,
+    }
+
+    request {
+        #[ruma_api(query)]
+        pub limit: u32,
+    }
+
+    response {}
+}
+
+#[test]
+fn metadata_appservice() {
+    let metadata = Request::METADATA;
+    assert!(metadata.appservice);
+}
+
+#[test]
+fn identity_assertion_query_params() {
+    let mut http_req: http::Request<Vec<u8>> = Request { limit: 10 }.try_into().unwrap();
+    add_identity_assertion(
+        &mut http_req,
+        &Request::METADATA,
+        &AccessToken::new("secret token"),
+        Some("@bot:example.org"),
+    )
+    .unwrap();
+
+    assert_eq!(
+        http_req.uri().query(),
+        Some("limit=10&access_token=secret%20token&user_id=%40bot%3Aexample.org")
+    );
+    assert_eq!(http_req.uri().path(), "/_matrix/some/appservice/endpoint");
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.limit, 10);
+}
+
+#[test]
+fn identity_assertion_without_user_id_or_query() {
+    let mut http_req = http::Request::builder().uri("/_matrix/foo").body(Vec::<u8>::new()).unwrap();
+    add_identity_assertion(&mut http_req, &Request::METADATA, &AccessToken::new("token"), None)
+        .unwrap();
+
+    assert_eq!(http_req.uri(), "/_matrix/foo?access_token=token");
+}
+
+#[test]
+fn identity_assertion_requires_appservice_endpoint() {
+    let mut http_req: http::Request<Vec<u8>> = regular::Request {}.try_into().unwrap();
+    let err = add_identity_assertion(
+        &mut http_req,
+        &regular::Request::METADATA,
+        &AccessToken::new("token"),
+        Some("@bot:example.org"),
+    )
+    .unwrap_err();
+
+    assert!(err.to_string().contains("`regular_endpoint`"));
+    assert_eq!(http_req.uri().query(), None);
+}
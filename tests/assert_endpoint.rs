@@ -0,0 +1,23 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "some_endpoint",
+        path: "/_matrix/some/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        pub foo: String,
+    }
+
+    response {
+        pub bar: String,
+    }
+}
+
+ruma_api::assert_endpoint!(Request, Response);
+ruma_api::assert_endpoint!(Request, Response, ruma_api::MatrixError);
@@ -0,0 +1,120 @@
+use ruma_api::auth::{add_bearer_token, AccessToken, XMatrixAuth};
+
+#[test]
+fn access_token_debug_is_redacted() {
+    let token: AccessToken = "secret".parse().unwrap();
+
+    assert_eq!(format!("{:?}", token), "AccessToken(<redacted>)");
+    assert_eq!(token.to_string(), "secret");
+    assert_eq!(token.as_str(), "secret");
+}
+
+#[test]
+fn bearer_token_header() {
+    let mut http_req = http::Request::builder()
+        .uri("/_matrix/foo")
+        .header(http::header::AUTHORIZATION, "Bearer old")
+        .body(Vec::<u8>::new())
+        .unwrap();
+    add_bearer_token(&mut http_req, Some(&AccessToken::new("secret"))).unwrap();
+
+    let values: Vec<_> = http_req.headers().get_all(http::header::AUTHORIZATION).iter().collect();
+    assert_eq!(values, vec!["Bearer secret"]);
+}
+
+#[test]
+fn no_bearer_token() {
+    let mut http_req = http::Request::builder().uri("/_matrix/foo").body(Vec::<u8>::new()).unwrap();
+    add_bearer_token(&mut http_req, None).unwrap();
+
+    assert!(http_req.headers().get(http::header::AUTHORIZATION).is_none());
+}
+
+#[test]
+fn invalid_bearer_token() {
+    let mut http_req = http::Request::builder().uri("/_matrix/foo").body(Vec::<u8>::new()).unwrap();
+
+    assert!(add_bearer_token(&mut http_req, Some(&AccessToken::new("bad\ntoken"))).is_err());
+}
+
+mod federation {
+    use ruma_api::auth::XMatrixAuth;
+
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "federation_endpoint",
+            path: "/_matrix/federation/v1/some/endpoint",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(header = AUTHORIZATION)]
+            pub authorization: XMatrixAuth,
+        }
+
+        response {}
+    }
+}
+
+fn x_matrix_auth() -> XMatrixAuth {
+    XMatrixAuth {
+        origin: "origin.example.org".to_owned(),
+        destination: Some("destination.example.org".to_owned()),
+        key_id: "ed25519:key1".to_owned(),
+        signature: "ABCDEF".to_owned(),
+    }
+}
+
+#[test]
+fn x_matrix_auth_display() {
+    assert_eq!(
+        x_matrix_auth().to_string(),
+        "X-Matrix origin=\"origin.example.org\",destination=\"destination.example.org\",\
+         key=\"ed25519:key1\",sig=\"ABCDEF\""
+    );
+}
+
+#[test]
+fn x_matrix_auth_roundtrip() {
+    let auth = XMatrixAuth { destination: None, ..x_matrix_auth() };
+    assert_eq!(auth.to_string().parse::<XMatrixAuth>().unwrap(), auth);
+
+    let auth = XMatrixAuth { origin: "weird\"name\\".to_owned(), ..x_matrix_auth() };
+    assert_eq!(auth.to_string().parse::<XMatrixAuth>().unwrap(), auth);
+}
+
+#[test]
+fn x_matrix_auth_parse_quirks() {
+    let auth: XMatrixAuth = "x-matrix  Origin=origin.example.org , key=\"ed25519:key1\",\
+                             sig=ABCDEF, destination=\"destination.example.org\" , foo=\"a,b\""
+        .parse()
+        .unwrap();
+
+    assert_eq!(auth, x_matrix_auth());
+}
+
+#[test]
+fn x_matrix_auth_parse_errors() {
+    assert!("Bearer abc".parse::<XMatrixAuth>().is_err());
+    assert!("X-Matrix origin=a,key=b".parse::<XMatrixAuth>().is_err());
+    assert!("X-Matrix origin=\"a,key=b,sig=c".parse::<XMatrixAuth>().is_err());
+    assert!("X-Matrix origin=\"a\" key=b,sig=c".parse::<XMatrixAuth>().is_err());
+}
+
+#[test]
+fn x_matrix_auth_header_field() {
+    use std::convert::{TryFrom, TryInto};
+
+    let req = federation::Request { authorization: x_matrix_auth() };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert!(http_req.headers()[http::header::AUTHORIZATION]
+        .to_str()
+        .unwrap()
+        .starts_with("X-Matrix origin="));
+
+    let req = federation::Request::try_from(http_req).unwrap();
+    assert_eq!(req.authorization, x_matrix_auth());
+}
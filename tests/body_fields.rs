@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "body_fields",
+        path: "/_matrix/some/endpoint/:room_id",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+
+        #[ruma_api(query)]
+        pub limit: u32,
+
+        pub r#type: String,
+
+        #[serde(rename = "m.relates_to")]
+        pub relates_to: Option<String>,
+
+        #[serde(rename(serialize = "ts", deserialize = "timestamp"))]
+        pub ts: u64,
+
+        #[serde(skip)]
+        pub local_only: bool,
+
+        #[ruma_api(additional)]
+        pub other: BTreeMap<String, serde_json::Value>,
+    }
+
+    response {
+        #[ruma_api(header = CONTENT_TYPE)]
+        pub content_type: String,
+
+        pub event_id: String,
+    }
+}
+
+mod newtype_body {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: PUT,
+            name: "newtype_body",
+            path: "/_matrix/some/newtype",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(json_body)]
+            pub content: serde_json::Value,
+        }
+
+        response {
+            #[ruma_api(raw_body)]
+            pub file: Vec<u8>,
+        }
+    }
+}
+
+#[test]
+fn body_field_names() {
+    assert_eq!(Request::BODY_FIELDS, &["type", "m.relates_to", "ts"]);
+    assert_eq!(Response::BODY_FIELDS, &["event_id"]);
+}
+
+#[test]
+fn newtype_body_has_no_body_field_names() {
+    assert!(newtype_body::Request::BODY_FIELDS.is_empty());
+    assert!(newtype_body::Response::BODY_FIELDS.is_empty());
+}
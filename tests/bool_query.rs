@@ -0,0 +1,112 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "sync",
+        path: "/_matrix/some/sync",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query)]
+        pub full_state: bool,
+
+        #[ruma_api(query)]
+        pub set_presence: Option<bool>,
+
+        #[ruma_api(query)]
+        pub flags: Vec<bool>,
+    }
+
+    response {}
+}
+
+fn parses(query: &str) -> bool {
+    let http_req = http::Request::builder()
+        .uri(format!("http://localhost/_matrix/some/sync?{}", query))
+        .body(Vec::new())
+        .unwrap();
+
+    Request::try_from(http_req).is_ok()
+}
+
+#[test]
+fn bool_query_serializes_as_true_false() {
+    let req = Request { full_state: true, set_presence: Some(false), flags: vec![false, true] };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(
+        http_req.uri().query(),
+        Some("full_state=true&set_presence=false&flags=false&flags=true")
+    );
+}
+
+#[test]
+fn bool_query_roundtrip() {
+    let req = Request { full_state: false, set_presence: None, flags: vec![true] };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    let req = Request::try_from(http_req).unwrap();
+
+    assert!(!req.full_state);
+    assert_eq!(req.set_presence, None);
+    assert_eq!(req.flags, vec![true]);
+}
+
+#[test]
+fn bool_query_rejects_numbers() {
+    assert!(!parses("full_state=1"));
+    assert!(!parses("full_state=0"));
+    assert!(!parses("full_state=true&set_presence=1"));
+    assert!(!parses("full_state=true&flags=0"));
+    assert!(!parses("full_state=True"));
+}
+
+mod lenient {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "lenient",
+            path: "/_matrix/some/lenient",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(query)]
+            #[serde(deserialize_with = "ruma_api::query::lenient_bool")]
+            pub full_state: bool,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn lenient_bool_query_accepts_numbers() {
+    for (query, expected) in
+        &[("full_state=1", true), ("full_state=0", false), ("full_state=true", true)]
+    {
+        let http_req = http::Request::builder()
+            .uri(format!("http://localhost/_matrix/some/lenient?{}", query))
+            .body(Vec::new())
+            .unwrap();
+
+        assert_eq!(lenient::Request::try_from(http_req).unwrap().full_state, *expected);
+    }
+
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/lenient?full_state=yes")
+        .body(Vec::new())
+        .unwrap();
+    assert!(lenient::Request::try_from(http_req).is_err());
+
+    let req = lenient::Request { full_state: true };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.uri().query(), Some("full_state=true"));
+}
@@ -0,0 +1,131 @@
+use std::convert::TryFrom;
+
+use ruma_api::error::FromHttpResponseError;
+
+mod with_body {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "with_body",
+            path: "/_matrix/some/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {
+            #[ruma_api(header = ETAG)]
+            pub etag: Option<String>,
+            pub events: Vec<String>,
+        }
+    }
+}
+
+mod empty {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "empty",
+            path: "/_matrix/some/empty",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {}
+    }
+}
+
+mod created {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "created",
+            path: "/_matrix/some/created",
+            rate_limited: false,
+            requires_authentication: false,
+            status: 201,
+        }
+
+        request {}
+
+        response {}
+    }
+}
+
+fn http_response(status: u16, body: &[u8]) -> http::Response<Vec<u8>> {
+    http::Response::builder()
+        .status(status)
+        .header(http::header::ETAG, "\"abc\"")
+        .body(body.to_vec())
+        .unwrap()
+}
+
+#[test]
+fn response_with_body_from_reference() {
+    let http_res = http_response(200, br#"{"events":["a","b"]}"#);
+    let res = with_body::Response::try_from(&http_res).unwrap();
+
+    assert_eq!(res.etag.as_deref(), Some("\"abc\""));
+    assert_eq!(res.events, ["a", "b"]);
+
+    // The borrowed response is left untouched and can be parsed again.
+    let res = with_body::Response::try_from(http_res).unwrap();
+    assert_eq!(res.events, ["a", "b"]);
+}
+
+#[test]
+fn empty_response_from_reference() {
+    let http_res = http_response(200, b"{}");
+    empty::Response::try_from(&http_res).unwrap();
+}
+
+#[test]
+fn not_modified_from_reference() {
+    let http_res = http_response(304, b"");
+
+    match empty::Response::try_from(&http_res) {
+        Err(FromHttpResponseError::NotModified) => {}
+        other => panic!("expected not modified, got {:?}", other),
+    }
+}
+
+#[test]
+fn error_response_from_reference() {
+    let body = br#"{"errcode":"M_NOT_FOUND","error":"Not found"}"#;
+    let http_res = http_response(404, body);
+
+    match with_body::Response::try_from(&http_res) {
+        Err(FromHttpResponseError::Http(err)) => {
+            assert_eq!(err.status(), http::StatusCode::NOT_FOUND);
+        }
+        other => panic!("expected server error, got {:?}", other),
+    }
+    assert_eq!(http_res.body(), body);
+}
+
+#[test]
+fn unexpected_status_from_reference() {
+    let http_res = http_response(200, b"{}");
+    match created::Response::try_from(&http_res) {
+        Err(FromHttpResponseError::UnexpectedStatus(err)) => {
+            assert_eq!(err.expected(), http::StatusCode::CREATED);
+        }
+        other => panic!("expected unexpected status, got {:?}", other),
+    }
+}
+
+#[test]
+fn invalid_body_from_reference() {
+    let http_res = http_response(200, b"not json");
+
+    match with_body::Response::try_from(&http_res) {
+        Err(FromHttpResponseError::Deserialization(_)) => {}
+        other => panic!("expected deserialization error, got {:?}", other),
+    }
+}
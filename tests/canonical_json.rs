@@ -0,0 +1,71 @@
+use std::{collections::BTreeMap, convert::TryInto};
+
+use ruma_api::ruma_api;
+use serde_json::json;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: PUT,
+        name: "send_transaction",
+        path: "/_matrix/federation/v1/send/:txn_id",
+        rate_limited: false,
+        requires_authentication: true,
+        canonical_json: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub txn_id: String,
+
+        pub origin: String,
+        pub pdus: Vec<serde_json::Value>,
+        pub edus: Vec<serde_json::Value>,
+    }
+
+    response {
+        pub pdus: BTreeMap<String, serde_json::Value>,
+    }
+}
+
+#[test]
+fn canonical_json_sorts_keys_without_whitespace() {
+    let value = json!({
+        "one": 1,
+        "two": "Two",
+        "a": { "z": [3, { "y": null, "x": true }], "b": "ü" },
+    });
+
+    assert_eq!(
+        ruma_api::canonical_json::to_string(&value).unwrap(),
+        r#"{"a":{"b":"ü","z":[3,{"x":true,"y":null}]},"one":1,"two":"Two"}"#
+    );
+}
+
+#[test]
+fn request_body_is_canonical() {
+    let req = Request {
+        txn_id: "1".to_owned(),
+        origin: "example.org".to_owned(),
+        pdus: vec![json!({ "b": 1, "a": 2 })],
+        edus: Vec::new(),
+    };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(
+        http_req.body().as_slice(),
+        &br#"{"edus":[],"origin":"example.org","pdus":[{"a":2,"b":1}]}"#[..]
+    );
+}
+
+#[test]
+fn response_body_is_canonical() {
+    let mut pdus = BTreeMap::new();
+    pdus.insert("$event".to_owned(), json!({ "error": "nope", "code": 1 }));
+    let http_res: http::Response<Vec<u8>> = Response { pdus }.try_into().unwrap();
+
+    assert_eq!(
+        http_res.body().as_slice(),
+        &br#"{"pdus":{"$event":{"code":1,"error":"nope"}}}"#[..]
+    );
+}
@@ -0,0 +1,118 @@
+//! Messages with `Transfer-Encoding: chunked` have no `Content-Length` header. Once the body is
+//! assembled, parsing must only depend on its length.
+
+use std::convert::{TryFrom, TryInto};
+
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING};
+use ruma_api::error::FromHttpRequestError;
+
+mod json {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "json",
+            path: "/_matrix/some/json",
+            rate_limited: false,
+            requires_authentication: false,
+            max_body_size: 32,
+        }
+
+        request {
+            pub name: String,
+        }
+
+        response {
+            #[ruma_api(header = CONTENT_TYPE)]
+            pub content_type: String,
+            pub events: Vec<String>,
+        }
+    }
+}
+
+mod download {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "download",
+            path: "/_matrix/some/download",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {
+            #[ruma_api(raw_body)]
+            pub file: Vec<u8>,
+        }
+    }
+}
+
+fn chunked_response(body: &[u8]) -> http::Response<Vec<u8>> {
+    http::Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .header(TRANSFER_ENCODING, "chunked")
+        .body(body.to_vec())
+        .unwrap()
+}
+
+fn chunked_request(body: &[u8]) -> http::Request<Vec<u8>> {
+    http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/json")
+        .header(TRANSFER_ENCODING, "chunked")
+        .body(body.to_vec())
+        .unwrap()
+}
+
+#[test]
+fn json_response_without_content_length() {
+    let res = json::Response::try_from(chunked_response(br#"{"events":["a","b"]}"#)).unwrap();
+
+    assert_eq!(res.content_type, "application/json");
+    assert_eq!(res.events, ["a", "b"]);
+}
+
+#[test]
+fn raw_body_response_without_content_length() {
+    let res = download::Response::try_from(chunked_response(b"\x00\x01\x02")).unwrap();
+    assert_eq!(res.file, b"\x00\x01\x02");
+}
+
+#[test]
+fn empty_raw_body_response_without_content_length() {
+    let res = download::Response::try_from(chunked_response(b"")).unwrap();
+    assert!(res.file.is_empty());
+}
+
+#[test]
+fn request_without_content_length() {
+    let req = json::Request::try_from(chunked_request(br#"{"name":"foo"}"#)).unwrap();
+    assert_eq!(req.name, "foo");
+}
+
+#[test]
+fn body_size_limit_without_content_length() {
+    let body = br#"{"name":"a name that is far too long"}"#;
+
+    match json::Request::try_from(chunked_request(body)) {
+        Err(FromHttpRequestError::TooLarge(err)) => {
+            assert_eq!(err.size(), body.len() as u64);
+            assert_eq!(err.limit(), 32);
+        }
+        other => panic!("expected too large error, got {:?}", other),
+    }
+}
+
+#[test]
+fn outgoing_messages_have_no_content_length() {
+    let req = json::Request { name: "foo".to_owned() };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert!(!http_req.headers().contains_key(CONTENT_LENGTH));
+
+    let res = json::Response { content_type: "application/json".to_owned(), events: Vec::new() };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+    assert!(!http_res.headers().contains_key(CONTENT_LENGTH));
+}
@@ -0,0 +1,76 @@
+mod cloneable {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: PUT,
+            name: "upload",
+            path: "/_matrix/some/upload/:file_name",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub file_name: String,
+
+            #[ruma_api(header = CONTENT_TYPE)]
+            pub content_type: String,
+
+            #[ruma_api(raw_body)]
+            pub file: Vec<u8>,
+        }
+
+        response {
+            #[ruma_api(header = LOCATION)]
+            pub location: String,
+        }
+    }
+}
+
+mod not_cloneable {
+    use std::sync::Mutex;
+
+    #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+    pub struct Counter(pub Mutex<u32>);
+
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "count",
+            path: "/_matrix/some/count",
+            rate_limited: false,
+            requires_authentication: false,
+            clone: false,
+        }
+
+        request {
+            pub counter: Counter,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn requests_and_responses_are_cloneable_by_default() {
+    let req = cloneable::Request {
+        file_name: "cat.png".to_owned(),
+        content_type: "image/png".to_owned(),
+        file: vec![1, 2, 3],
+    };
+    let cloned = req.clone();
+    assert_eq!(cloned.file_name, req.file_name);
+    assert_eq!(cloned.content_type, req.content_type);
+    assert_eq!(cloned.file, req.file);
+
+    let res = cloneable::Response { location: "/cat.png".to_owned() };
+    assert_eq!(res.clone().location, res.location);
+}
+
+#[test]
+fn clone_can_be_disabled() {
+    let req = not_cloneable::Request { counter: not_cloneable::Counter::default() };
+    *req.counter.0.lock().unwrap() += 1;
+    assert_eq!(*req.counter.0.lock().unwrap(), 1);
+}
@@ -0,0 +1,74 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "get_profiles",
+        path: "/_matrix/some/profiles/:user_ids/:field",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path, comma)]
+        pub user_ids: Vec<String>,
+
+        #[ruma_api(path)]
+        pub field: String,
+    }
+
+    response {}
+}
+
+#[test]
+fn comma_separated_path_segment() {
+    let req = Request {
+        user_ids: vec!["@alice:example.org".to_owned(), "@bob:example.org".to_owned()],
+        field: "displayname".to_owned(),
+    };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(
+        http_req.uri().path(),
+        "/_matrix/some/profiles/@alice:example.org,@bob:example.org/displayname"
+    );
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.user_ids, vec!["@alice:example.org".to_owned(), "@bob:example.org".to_owned()]);
+    assert_eq!(req.field, "displayname");
+}
+
+#[test]
+fn comma_separated_path_segment_encoding() {
+    let req =
+        Request { user_ids: vec!["a,b".to_owned(), "c d/e%".to_owned()], field: "a b".to_owned() };
+    let path = req.path_with_params().unwrap();
+    assert_eq!(path, "/_matrix/some/profiles/a%2Cb,c%20d%2Fe%25/a%20b");
+
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.user_ids, vec!["a,b".to_owned(), "c d/e%".to_owned()]);
+    assert_eq!(req.field, "a b");
+}
+
+#[test]
+fn comma_separated_path_segment_with_base_url_path() {
+    let req = Request { user_ids: vec!["x".to_owned()], field: "y".to_owned() };
+    let url = req.url(&"https://example.org/matrix/".parse().unwrap()).unwrap();
+
+    assert_eq!(url.as_str(), "https://example.org/matrix/_matrix/some/profiles/x/y");
+}
+
+#[test]
+fn empty_comma_separated_path_segment() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/profiles//displayname")
+        .body(Vec::new())
+        .unwrap();
+    let req = Request::try_from(http_req).unwrap();
+
+    assert!(req.user_ids.is_empty());
+}
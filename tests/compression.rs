@@ -0,0 +1,119 @@
+use std::convert::TryFrom;
+
+use http::header::CONTENT_ENCODING;
+use ruma_api::{error::FromHttpResponseError, ruma_api};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "directory",
+        path: "/_matrix/some/directory",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        pub rooms: Vec<String>,
+    }
+}
+
+const BODY: &[u8] = br#"{"rooms":["!a:example.org","!b:example.org"]}"#;
+
+#[test]
+fn identity_encoding() {
+    let http_res =
+        http::Response::builder().header(CONTENT_ENCODING, "identity").body(BODY.to_vec()).unwrap();
+    let res = Response::try_from(http_res).unwrap();
+
+    assert_eq!(res.rooms.len(), 2);
+}
+
+#[test]
+fn unsupported_encoding() {
+    let http_res =
+        http::Response::builder().header(CONTENT_ENCODING, "br").body(BODY.to_vec()).unwrap();
+    let err = Response::try_from(http_res).unwrap_err();
+
+    assert_eq!(err.to_string(), "deserialization failed: unsupported content encoding `br`");
+}
+
+#[test]
+fn unsupported_encoding_of_error_response() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::FORBIDDEN)
+        .header(CONTENT_ENCODING, "br")
+        .body(br#"{"errcode":"M_FORBIDDEN","error":"Nope"}"#.to_vec())
+        .unwrap();
+
+    match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Http(err)) => {
+            assert_eq!(err.status(), http::StatusCode::FORBIDDEN);
+            assert_eq!(err.body_bytes(), br#"{"errcode":"M_FORBIDDEN","error":"Nope"}"#);
+        }
+        other => panic!("expected server error, got {:?}", other),
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+#[test]
+fn gzip_without_feature() {
+    let http_res =
+        http::Response::builder().header(CONTENT_ENCODING, "gzip").body(BODY.to_vec()).unwrap();
+    let err = Response::try_from(http_res).unwrap_err();
+
+    assert!(err.to_string().contains("only supported with the `compression` feature"));
+}
+
+#[cfg(feature = "compression")]
+mod with_feature {
+    use std::{convert::TryFrom, io::Write as _};
+
+    use flate2::{
+        write::{GzEncoder, ZlibEncoder},
+        Compression,
+    };
+    use http::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+
+    use super::{Response, BODY};
+
+    #[test]
+    fn gzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(BODY).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let http_res = http::Response::builder()
+            .header(CONTENT_ENCODING, "gzip")
+            .header(CONTENT_LENGTH, compressed.len())
+            .body(compressed)
+            .unwrap();
+        let res = Response::try_from(http_res).unwrap();
+
+        assert_eq!(res.rooms, vec!["!a:example.org".to_owned(), "!b:example.org".to_owned()]);
+    }
+
+    #[test]
+    fn deflate() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(BODY).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let http_res =
+            http::Response::builder().header(CONTENT_ENCODING, "deflate").body(compressed).unwrap();
+        let res = Response::try_from(http_res).unwrap();
+
+        assert_eq!(res.rooms.len(), 2);
+    }
+
+    #[test]
+    fn corrupt_gzip() {
+        let http_res =
+            http::Response::builder().header(CONTENT_ENCODING, "gzip").body(BODY.to_vec()).unwrap();
+        let err = Response::try_from(http_res).unwrap_err();
+
+        assert!(err.to_string().contains("failed to decompress `gzip` body"));
+    }
+}
@@ -0,0 +1,141 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::{error::FromHttpResponseError, ruma_api};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "capabilities",
+        path: "/_matrix/some/capabilities",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(header = IF_NONE_MATCH)]
+        pub if_none_match: Option<String>,
+    }
+
+    response {
+        #[ruma_api(header = ETAG)]
+        pub etag: Option<String>,
+
+        pub capabilities: Vec<String>,
+    }
+}
+
+#[test]
+fn request_with_if_none_match() {
+    let req = Request { if_none_match: Some("\"abc\"".to_owned()) };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.headers().get(http::header::IF_NONE_MATCH).unwrap(), "\"abc\"");
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.if_none_match.as_deref(), Some("\"abc\""));
+}
+
+#[test]
+fn request_without_if_none_match() {
+    let req = Request { if_none_match: None };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert!(http_req.headers().get(http::header::IF_NONE_MATCH).is_none());
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.if_none_match, None);
+}
+
+#[test]
+fn response_etag_roundtrip() {
+    let res = Response { etag: Some("\"abc\"".to_owned()), capabilities: vec!["foo".to_owned()] };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+
+    assert_eq!(http_res.headers().get(http::header::ETAG).unwrap(), "\"abc\"");
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.etag.as_deref(), Some("\"abc\""));
+    assert_eq!(res.capabilities, vec!["foo".to_owned()]);
+}
+
+#[test]
+fn response_without_etag() {
+    let http_res = http::Response::builder().body(b"{ \"capabilities\": [] }".to_vec()).unwrap();
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.etag, None);
+}
+
+#[test]
+fn not_modified_response() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::NOT_MODIFIED)
+        .header(http::header::ETAG, "\"abc\"")
+        .body(Vec::new())
+        .unwrap();
+
+    match Response::try_from(http_res) {
+        Err(FromHttpResponseError::NotModified) => {}
+        other => panic!("expected NotModified, got {:?}", other),
+    }
+}
+
+mod put_state {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: PUT,
+            name: "put_state",
+            path: "/_matrix/some/state/:event_type",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub event_type: String,
+
+            #[ruma_api(header = IF_MATCH)]
+            pub if_match: Option<String>,
+
+            pub content: String,
+        }
+
+        response {
+            #[ruma_api(header = ETAG)]
+            pub etag: Option<String>,
+        }
+    }
+}
+
+#[test]
+fn request_with_if_match() {
+    let req = put_state::Request {
+        event_type: "m.room.topic".to_owned(),
+        if_match: Some("\"v1\"".to_owned()),
+        content: "topic".to_owned(),
+    };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.headers()[http::header::IF_MATCH], "\"v1\"");
+
+    let req = put_state::Request::try_from(http_req).unwrap();
+    assert_eq!(req.if_match.as_deref(), Some("\"v1\""));
+}
+
+#[test]
+fn precondition_failed_response() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::PRECONDITION_FAILED)
+        .body(br#"{ "errcode": "M_UNKNOWN", "error": "State changed" }"#.to_vec())
+        .unwrap();
+
+    match put_state::Response::try_from(http_res) {
+        Err(FromHttpResponseError::PreconditionFailed(err)) => {
+            assert_eq!(err.status(), http::StatusCode::PRECONDITION_FAILED);
+            assert_eq!(err.to_matrix_error().message, "State changed");
+        }
+        other => panic!("expected PreconditionFailed, got {:?}", other),
+    }
+}
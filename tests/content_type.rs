@@ -0,0 +1,101 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "thumbnail",
+        path: "/_matrix/some/thumbnail",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(header = ACCEPT)]
+        pub accept: Option<String>,
+    }
+
+    response {
+        #[ruma_api(raw_body)]
+        pub file: Vec<u8>,
+    }
+}
+
+mod explicit_content_type {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "download",
+            path: "/_matrix/some/download",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {
+            #[ruma_api(header = CONTENT_TYPE)]
+            pub content_type: String,
+
+            #[ruma_api(raw_body)]
+            pub file: Vec<u8>,
+        }
+    }
+}
+
+#[test]
+fn request_with_accept() {
+    let req = Request { accept: Some("image/png".to_owned()) };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.headers().get(http::header::ACCEPT).unwrap(), "image/png");
+}
+
+#[test]
+fn raw_body_response_content_type() {
+    let http_res = http::Response::builder()
+        .header(http::header::CONTENT_TYPE, "image/png")
+        .body(b"PNG".to_vec())
+        .unwrap();
+    let res = Response::try_from(http_res).unwrap();
+
+    assert_eq!(res.content_type.as_deref(), Some("image/png"));
+    assert_eq!(res.file, b"PNG");
+}
+
+#[test]
+fn raw_body_response_without_content_type() {
+    let http_res = http::Response::builder().body(b"data".to_vec()).unwrap();
+    let res = Response::try_from(http_res).unwrap();
+
+    assert_eq!(res.content_type, None);
+}
+
+#[test]
+fn raw_body_response_content_type_roundtrip() {
+    let res = Response { file: b"GIF".to_vec(), content_type: Some("image/gif".to_owned()) };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+
+    let content_types: Vec<_> =
+        http_res.headers().get_all(http::header::CONTENT_TYPE).iter().collect();
+    assert_eq!(content_types, vec!["image/gif"]);
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.content_type.as_deref(), Some("image/gif"));
+}
+
+#[test]
+fn explicit_content_type_header_field() {
+    let res = explicit_content_type::Response {
+        content_type: "video/mp4".to_owned(),
+        file: b"MP4".to_vec(),
+    };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+
+    let content_types: Vec<_> =
+        http_res.headers().get_all(http::header::CONTENT_TYPE).iter().collect();
+    assert_eq!(content_types, vec!["video/mp4"]);
+}
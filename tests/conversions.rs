@@ -0,0 +1,250 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "search",
+        path: "/_matrix/some/search/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query)]
+        pub term: String,
+    }
+
+    response {}
+}
+
+#[test]
+fn query_string_encodes_spaces_as_percent_20() {
+    let req = Request { term: "hello world+more & /?=".to_owned() };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().query(), Some("term=hello%20world%2Bmore%20%26%20%2F%3F%3D"));
+}
+
+#[test]
+fn query_string_spaces_roundtrip() {
+    let req = Request { term: "hello world+more & /?=".to_owned() };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    let req = Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.term, "hello world+more & /?=");
+}
+
+#[test]
+fn query_string_accepts_plus_for_spaces() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/search/endpoint?term=hello+world")
+        .body(Vec::new())
+        .unwrap();
+    let req = Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.term, "hello world");
+}
+
+mod json_body {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "json_body",
+            path: "/_matrix/some/json/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            pub foo: String,
+            pub bar: u32,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn request_deserialization_error_position() {
+    let http_req = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/json/endpoint")
+        .body(b"{\n  \"foo\": \"hello\",\n  \"bar\": \"not a number\"\n}".to_vec())
+        .unwrap();
+
+    let err = match json_body::Request::try_from(http_req) {
+        Err(err) => {
+            assert_eq!(err.endpoint_name(), Some("json_body"));
+            match err {
+                ruma_api::error::FromHttpRequestError::Deserialization(err) => err,
+                other => panic!("expected deserialization error, got {:?}", other),
+            }
+        }
+        Ok(req) => panic!("expected deserialization error, got {:?}", req),
+    };
+
+    assert_eq!(err.line(), Some(3));
+    assert_eq!(err.column(), Some(23));
+    assert_eq!(err.snippet(), Some("bar\": \"not a number\""));
+}
+
+#[test]
+fn request_deserialization_error_position_query() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/search/endpoint?other=1")
+        .body(Vec::new())
+        .unwrap();
+
+    let err = match Request::try_from(http_req) {
+        Err(ruma_api::error::FromHttpRequestError::Deserialization(err)) => err,
+        other => panic!("expected deserialization error, got {:?}", other),
+    };
+
+    assert_eq!(err.line(), None);
+    assert_eq!(err.snippet(), None);
+    assert!(err.error().downcast_ref::<serde_urlencoded::de::Error>().is_some());
+}
+
+#[test]
+fn request_deserialization_error_into_inner() {
+    let body = b"{\"foo\": 1}".to_vec();
+    let http_req = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/json/endpoint")
+        .body(body.clone())
+        .unwrap();
+
+    let err = match json_body::Request::try_from(http_req) {
+        Err(ruma_api::error::FromHttpRequestError::Deserialization(err)) => err,
+        other => panic!("expected deserialization error, got {:?}", other),
+    };
+    assert_eq!(err.http_request().uri().path(), "/_matrix/some/json/endpoint");
+    assert!(err.error().downcast_ref::<serde_json::Error>().is_some());
+
+    let (inner, http_req) = err.into_inner();
+    assert!(inner.downcast_ref::<serde_json::Error>().is_some());
+    assert_eq!(http_req.method(), http::Method::POST);
+    assert_eq!(*http_req.body(), body);
+}
+
+#[test]
+fn request_deserialization_error_keeps_body_of_query_error() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/search/endpoint?other=1")
+        .body(b"unused".to_vec())
+        .unwrap();
+
+    let err = match Request::try_from(http_req) {
+        Err(ruma_api::error::FromHttpRequestError::Deserialization(err)) => err,
+        other => panic!("expected deserialization error, got {:?}", other),
+    };
+
+    let (_, http_req) = err.into_inner();
+    assert_eq!(http_req.uri().query(), Some("other=1"));
+    assert_eq!(http_req.body(), b"unused");
+}
+
+mod failing_query {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// A query value that can never be serialized.
+    #[derive(Clone, Debug)]
+    pub struct Unserializable;
+
+    impl Serialize for Unserializable {
+        fn serialize<S: Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("value can't be serialized"))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Unserializable {
+        fn deserialize<D: Deserializer<'de>>(_deserializer: D) -> Result<Self, D::Error> {
+            Err(de::Error::custom("value can't be deserialized"))
+        }
+    }
+
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "failing_query",
+            path: "/_matrix/some/failing/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(query)]
+            pub value: Unserializable,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn query_serialization_error() {
+    let req = failing_query::Request { value: failing_query::Unserializable };
+    let err = TryInto::<http::Request<Vec<u8>>>::try_into(req).unwrap_err();
+
+    assert_eq!(err.to_string(), "Query parameter serialization failed: value can't be serialized");
+}
+
+mod clone_counting_query {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde::{Deserialize, Serialize};
+
+    pub static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+    /// A query value that counts how often it is cloned.
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct Tracked(pub String);
+
+    impl Clone for Tracked {
+        fn clone(&self) -> Self {
+            CLONES.fetch_add(1, Ordering::SeqCst);
+            Self(self.0.clone())
+        }
+    }
+
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "clone_counting_query",
+            path: "/_matrix/some/tracked/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(query)]
+            pub value: Tracked,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn owned_conversion_moves_query_fields() {
+    use std::sync::atomic::Ordering;
+
+    use clone_counting_query::{Request, Tracked, CLONES};
+
+    let req = Request { value: Tracked("a b".to_owned()) };
+    let base_url = ruma_api::exports::url::Url::parse("https://example.org").unwrap();
+    let url = req.url(&base_url).unwrap();
+    assert_eq!(url.query(), Some("value=a%20b"));
+    assert_eq!(CLONES.load(Ordering::SeqCst), 1);
+
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.uri().query(), Some("value=a%20b"));
+    assert_eq!(CLONES.load(Ordering::SeqCst), 1);
+}
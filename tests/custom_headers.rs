@@ -0,0 +1,56 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "custom_headers",
+        path: "/_matrix/some/custom_headers",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(header = "X-Matrix-Foo")]
+        pub foo: String,
+
+        #[ruma_api(header = "x-matrix-bar")]
+        pub bar: Option<u32>,
+
+        #[ruma_api(header_map)]
+        pub other_headers: Vec<(http::header::HeaderName, String)>,
+    }
+
+    response {
+        #[ruma_api(header = "X-Request-Id")]
+        pub request_id: String,
+    }
+}
+
+#[test]
+fn custom_request_headers_roundtrip() {
+    let req = Request { foo: "foo".to_owned(), bar: Some(5), other_headers: Vec::new() };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.headers().get("x-matrix-foo").unwrap(), "foo");
+    assert_eq!(http_req.headers().get("X-Matrix-Bar").unwrap(), "5");
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.foo, "foo");
+    assert_eq!(req.bar, Some(5));
+    // Headers of `header` fields aren't collected by the `header_map` field.
+    assert!(req.other_headers.iter().all(|(name, _)| name != "x-matrix-foo"));
+}
+
+#[test]
+fn custom_response_header_roundtrip() {
+    let res = Response { request_id: "abc".to_owned() };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+
+    assert_eq!(http_res.headers().get("x-request-id").unwrap(), "abc");
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.request_id, "abc");
+}
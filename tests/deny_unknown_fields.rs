@@ -0,0 +1,126 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::error::FromHttpRequestError;
+
+mod strict {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "strict",
+            path: "/_matrix/some/strict",
+            rate_limited: false,
+            requires_authentication: false,
+            deny_unknown_fields: true,
+        }
+
+        request {
+            pub name: String,
+            pub count: Option<u32>,
+        }
+
+        response {}
+    }
+}
+
+mod strict_fast {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "strict_fast",
+            path: "/_matrix/some/strict_fast",
+            rate_limited: false,
+            requires_authentication: false,
+            deny_unknown_fields: true,
+            fast: true,
+        }
+
+        request {
+            pub name: String,
+        }
+
+        response {}
+    }
+}
+
+mod lenient {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "lenient",
+            path: "/_matrix/some/lenient",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            pub name: String,
+        }
+
+        response {}
+    }
+}
+
+fn http_request(path: &str, body: &[u8]) -> http::Request<Vec<u8>> {
+    http::Request::builder()
+        .method("POST")
+        .uri(format!("http://localhost{}", path))
+        .body(body.to_vec())
+        .unwrap()
+}
+
+#[test]
+fn strict_rejects_unknown_fields() {
+    let http_req = http_request("/_matrix/some/strict", br#"{"name":"foo","other":1}"#);
+
+    match strict::Request::try_from(http_req) {
+        Err(FromHttpRequestError::Deserialization(err)) => {
+            assert!(err.to_string().contains("unknown field `other`"), "{}", err);
+        }
+        other => panic!("expected deserialization error, got {:?}", other),
+    }
+}
+
+#[test]
+fn strict_accepts_known_fields() {
+    let http_req = http_request("/_matrix/some/strict", br#"{"name":"foo","count":2}"#);
+    let req = strict::Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.name, "foo");
+    assert_eq!(req.count, Some(2));
+}
+
+#[test]
+fn strict_roundtrip() {
+    let req = strict::Request { name: "foo".to_owned(), count: None };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    let req = strict::Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.name, "foo");
+    assert_eq!(req.count, None);
+}
+
+#[test]
+fn strict_fast_rejects_unknown_fields() {
+    let http_req = http_request("/_matrix/some/strict_fast", br#"{"name":"foo","other":1}"#);
+
+    match strict_fast::Request::try_from(http_req) {
+        Err(FromHttpRequestError::Deserialization(err)) => {
+            assert!(err.to_string().contains("unknown field `other`"), "{}", err);
+        }
+        other => panic!("expected deserialization error, got {:?}", other),
+    }
+
+    let http_req = http_request("/_matrix/some/strict_fast", br#"{"name":"foo"}"#);
+    assert_eq!(strict_fast::Request::try_from(http_req).unwrap().name, "foo");
+}
+
+#[test]
+fn lenient_ignores_unknown_fields() {
+    let http_req = http_request("/_matrix/some/lenient", br#"{"name":"foo","other":1}"#);
+    let req = lenient::Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.name, "foo");
+}
@@ -0,0 +1,68 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use http::header::{HeaderMap, HeaderValue};
+use ruma_api::header::{deprecation, sunset, Deprecation};
+
+fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.parse().unwrap(), HeaderValue::from_static(value)))
+        .collect()
+}
+
+#[test]
+fn sunset_date() {
+    assert_eq!(
+        sunset(&headers(&[("sunset", "Sun, 06 Nov 1994 08:49:37 GMT")])),
+        Some(UNIX_EPOCH + Duration::from_secs(784_111_777))
+    );
+    assert_eq!(
+        sunset(&headers(&[("sunset", "Sat, 29 Feb 2020 00:00:00 GMT")])),
+        Some(UNIX_EPOCH + Duration::from_secs(1_582_934_400))
+    );
+}
+
+#[test]
+fn invalid_or_missing_sunset() {
+    assert_eq!(sunset(&HeaderMap::new()), None);
+    assert_eq!(sunset(&headers(&[("sunset", "tomorrow")])), None);
+    assert_eq!(sunset(&headers(&[("sunset", "Sun, 06 Foo 1994 08:49:37 GMT")])), None);
+}
+
+#[test]
+fn deprecation_forms() {
+    assert_eq!(deprecation(&HeaderMap::new()), None);
+    assert_eq!(deprecation(&headers(&[("deprecation", "true")])), Some(Deprecation::Deprecated));
+    assert_eq!(
+        deprecation(&headers(&[("deprecation", "@1688169599")])),
+        Some(Deprecation::Since(UNIX_EPOCH + Duration::from_secs(1_688_169_599)))
+    );
+    assert_eq!(
+        deprecation(&headers(&[("deprecation", "Sun, 06 Nov 1994 08:49:37 GMT")])),
+        Some(Deprecation::Since(UNIX_EPOCH + Duration::from_secs(784_111_777)))
+    );
+    assert_eq!(deprecation(&headers(&[("deprecation", "soon")])), None);
+}
+
+#[test]
+fn out_of_range_values_are_ignored() {
+    assert_eq!(deprecation(&headers(&[("deprecation", "@18446744073709551615")])), None);
+    assert_eq!(
+        deprecation(&headers(&[("deprecation", "Sun, 06 Nov 999999999999 08:49:37 GMT")])),
+        None
+    );
+    assert_eq!(sunset(&headers(&[("sunset", "Sun, 06 Nov 99999999999999999 08:49:37 GMT")])), None);
+    assert_eq!(sunset(&headers(&[("sunset", "Sun, 06 Nov 999999999999 08:49:37 GMT")])), None);
+}
+
+#[test]
+fn headers_of_response() {
+    let response = http::Response::builder()
+        .header("Deprecation", "true")
+        .header("Sunset", "Thu, 01 Jan 1970 00:01:00 GMT")
+        .body(Vec::<u8>::new())
+        .unwrap();
+
+    assert_eq!(deprecation(response.headers()), Some(Deprecation::Deprecated));
+    assert_eq!(sunset(response.headers()), Some(UNIX_EPOCH + Duration::from_secs(60)));
+}
@@ -0,0 +1,144 @@
+mod optional_fields {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "optional_fields",
+            path: "/_matrix/some/optional/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+            default: true,
+        }
+
+        request {
+            pub foo: Option<String>,
+
+            #[ruma_api(query)]
+            pub limit: Option<u32>,
+        }
+
+        response {
+            pub list: Vec<String>,
+            pub next_batch: Option<String>,
+        }
+    }
+}
+
+#[test]
+fn default_request_and_response() {
+    let request = optional_fields::Request::default();
+    assert_eq!(request.foo, None);
+    assert_eq!(request.limit, None);
+
+    let response =
+        optional_fields::Response { next_batch: Some("abc".to_owned()), ..Default::default() };
+    assert!(response.list.is_empty());
+    assert_eq!(response.next_batch.as_deref(), Some("abc"));
+}
+
+mod comparable {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "comparable",
+            path: "/_matrix/some/comparable/:id",
+            rate_limited: false,
+            requires_authentication: false,
+            eq: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub id: String,
+
+            pub tags: Vec<String>,
+        }
+
+        response {
+            pub count: u64,
+        }
+    }
+}
+
+mod float_fields {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "float_fields",
+            path: "/_matrix/some/float/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+            partial_eq: true,
+        }
+
+        request {
+            pub weight: f64,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn eq_request_and_response() {
+    use std::convert::{TryFrom, TryInto};
+
+    fn assert_eq_impl<T: Eq>() {}
+    assert_eq_impl::<comparable::Request>();
+    assert_eq_impl::<comparable::Response>();
+
+    let request = comparable::Request { id: "a".to_owned(), tags: vec!["x".to_owned()] };
+    let http_request: http::Request<Vec<u8>> = request.clone().try_into().unwrap();
+    assert_eq!(comparable::Request::try_from(http_request).unwrap(), request);
+
+    assert_ne!(comparable::Response { count: 1 }, comparable::Response { count: 2 });
+}
+
+#[test]
+fn partial_eq_request() {
+    let request = float_fields::Request { weight: 0.5 };
+    assert_eq!(request, float_fields::Request { weight: 0.5 });
+    assert_ne!(request, float_fields::Request { weight: 1.5 });
+}
+
+mod hashable {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "hashable",
+            path: "/_matrix/some/hashable/:id",
+            rate_limited: false,
+            requires_authentication: false,
+            hash: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub id: String,
+
+            #[ruma_api(query)]
+            pub limit: Option<u32>,
+        }
+
+        response {
+            pub weight: f64,
+        }
+    }
+}
+
+#[test]
+fn hash_request() {
+    use std::collections::HashMap;
+
+    let mut cache = HashMap::new();
+    cache.insert(hashable::Request { id: "a".to_owned(), limit: Some(1) }, 1);
+    cache.insert(hashable::Request { id: "a".to_owned(), limit: None }, 2);
+
+    assert_eq!(cache[&hashable::Request { id: "a".to_owned(), limit: Some(1) }], 1);
+    assert_eq!(cache[&hashable::Request { id: "a".to_owned(), limit: None }], 2);
+    // The response has a float field, so it doesn't implement `Hash` or `Eq`.
+    let _ = hashable::Response { weight: 0.5 };
+}
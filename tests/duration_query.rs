@@ -0,0 +1,68 @@
+use std::{
+    convert::{TryFrom, TryInto},
+    time::Duration,
+};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "sync",
+        path: "/_matrix/some/sync",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query)]
+        pub timeout: Duration,
+
+        #[ruma_api(query)]
+        pub delay: Option<Duration>,
+    }
+
+    response {}
+}
+
+#[test]
+fn duration_query_roundtrip() {
+    let req = Request { timeout: Duration::from_millis(30000), delay: None };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.uri().query(), Some("timeout=30000"));
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.timeout, Duration::from_millis(30000));
+    assert_eq!(req.delay, None);
+}
+
+#[test]
+fn optional_duration_query_roundtrip() {
+    let req = Request { timeout: Duration::from_secs(1), delay: Some(Duration::from_millis(250)) };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.uri().query(), Some("timeout=1000&delay=250"));
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.delay, Some(Duration::from_millis(250)));
+}
+
+#[test]
+fn duration_query_truncates_to_milliseconds() {
+    let req = Request { timeout: Duration::from_micros(1500), delay: None };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().query(), Some("timeout=1"));
+}
+
+#[test]
+fn invalid_duration_query() {
+    for query in &["timeout=1.5", "timeout=-1", "timeout=soon", "timeout=1&delay=x"] {
+        let http_req = http::Request::builder()
+            .uri(format!("http://localhost/_matrix/some/sync?{}", query))
+            .body(Vec::new())
+            .unwrap();
+
+        assert!(Request::try_from(http_req).is_err(), "{}", query);
+    }
+}
@@ -0,0 +1,112 @@
+use std::convert::TryFrom;
+
+use ruma_api::{error::FromHttpResponseError, ruma_api, MatrixErrorKind};
+use serde::Deserialize;
+
+/// An error body with the information needed to continue user-interactive authentication.
+#[derive(Debug, Deserialize)]
+pub struct UiaaResponse {
+    pub flows: Vec<AuthFlow>,
+    pub session: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthFlow {
+    pub stages: Vec<String>,
+}
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "delete_device",
+        path: "/_matrix/some/delete_device",
+        rate_limited: false,
+        requires_authentication: true,
+        error: UiaaResponse,
+    }
+
+    request {}
+
+    response {}
+}
+
+mod default_error {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "some_endpoint",
+            path: "/_matrix/some/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {}
+    }
+}
+
+#[test]
+fn typed_endpoint_error() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::BAD_REQUEST)
+        .body(br#"{ "flows": [{ "stages": ["m.login.password"] }], "session": "xyz" }"#.to_vec())
+        .unwrap();
+
+    let err = match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Http(err)) => err,
+        other => panic!("expected server error, got {:?}", other),
+    };
+
+    let uiaa = err.error().unwrap();
+    assert_eq!(uiaa.flows[0].stages, vec!["m.login.password".to_owned()]);
+    assert_eq!(uiaa.session.as_deref(), Some("xyz"));
+}
+
+#[test]
+fn user_interactive_auth_takes_precedence_over_endpoint_error() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::UNAUTHORIZED)
+        .body(br#"{ "flows": [{ "stages": ["m.login.password"] }], "session": "xyz" }"#.to_vec())
+        .unwrap();
+
+    match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Uiaa(uiaa)) => assert_eq!(uiaa.session.as_deref(), Some("xyz")),
+        other => panic!("expected user-interactive authentication, got {:?}", other),
+    }
+}
+
+#[test]
+fn typed_endpoint_error_invalid_body() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::FORBIDDEN)
+        .body(br#"{ "errcode": "M_FORBIDDEN", "error": "nope" }"#.to_vec())
+        .unwrap();
+
+    let err = match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Http(err)) => err,
+        other => panic!("expected server error, got {:?}", other),
+    };
+
+    assert!(err.error().is_none());
+    assert_eq!(err.to_matrix_error().kind, MatrixErrorKind::Forbidden);
+}
+
+#[test]
+fn default_endpoint_error() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::NOT_FOUND)
+        .body(br#"{ "errcode": "M_NOT_FOUND", "error": "no such thing" }"#.to_vec())
+        .unwrap();
+
+    let err = match default_error::Response::try_from(http_res) {
+        Err(FromHttpResponseError::Http(err)) => err,
+        other => panic!("expected server error, got {:?}", other),
+    };
+
+    let error = err.into_error().unwrap();
+    assert_eq!(error.kind, MatrixErrorKind::NotFound);
+    assert_eq!(error.message, "no such thing");
+}
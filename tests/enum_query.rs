@@ -0,0 +1,73 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+use serde::{Deserialize, Serialize};
+
+/// The direction to paginate in.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Direction {
+    #[serde(rename = "b")]
+    Backward,
+    #[serde(rename = "f")]
+    Forward,
+}
+
+/// The membership state to filter by.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Membership {
+    Join,
+    Leave,
+}
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "enum_query",
+        path: "/_matrix/some/messages",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query)]
+        pub dir: Direction,
+
+        #[ruma_api(query)]
+        pub membership: Option<Membership>,
+    }
+
+    response {}
+}
+
+#[test]
+fn enum_query_uses_serde_names() {
+    let req = Request { dir: Direction::Backward, membership: Some(Membership::Join) };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().query(), Some("dir=b&membership=join"));
+}
+
+#[test]
+fn enum_query_roundtrip() {
+    let req = Request { dir: Direction::Forward, membership: None };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.uri().query(), Some("dir=f"));
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.dir, Direction::Forward);
+    assert_eq!(req.membership, None);
+}
+
+#[test]
+fn enum_query_rejects_unknown_and_debug_names() {
+    for query in ["dir=backward", "dir=Backward", "dir=b&membership=Join"] {
+        let http_req = http::Request::builder()
+            .uri(format!("http://localhost/_matrix/some/messages?{}", query))
+            .body(Vec::new())
+            .unwrap();
+
+        assert!(Request::try_from(http_req).is_err(), "accepted `{}`", query);
+    }
+}
@@ -0,0 +1,83 @@
+use std::convert::TryFrom;
+
+use ruma_api::{error::FromHttpResponseError, ruma_api, MatrixErrorKind};
+use serde::Deserialize;
+
+/// The body of a `400 Bad Request` response listing the invalid fields.
+#[derive(Debug, Deserialize)]
+pub struct ValidationError {
+    pub invalid_fields: Vec<String>,
+}
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "create_thing",
+        path: "/_matrix/some/thing",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {}
+
+    errors {
+        400 => ValidationError,
+    }
+}
+
+#[test]
+fn listed_status_uses_its_error_type() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::BAD_REQUEST)
+        .body(br#"{ "invalid_fields": ["name", "size"] }"#.to_vec())
+        .unwrap();
+
+    let err = match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Http(err)) => err,
+        other => panic!("expected server error, got {:?}", other),
+    };
+
+    match err.into_error() {
+        Some(ResponseError::Status400(err)) => {
+            assert_eq!(err.invalid_fields, vec!["name".to_owned(), "size".to_owned()]);
+        }
+        other => panic!("expected validation error, got {:?}", other),
+    }
+}
+
+#[test]
+fn other_status_uses_default_error_type() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::FORBIDDEN)
+        .body(br#"{ "errcode": "M_FORBIDDEN", "error": "Nope" }"#.to_vec())
+        .unwrap();
+
+    let err = match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Http(err)) => err,
+        other => panic!("expected server error, got {:?}", other),
+    };
+
+    match err.error() {
+        Some(ResponseError::Other(err)) => {
+            assert_eq!(err.kind, MatrixErrorKind::Forbidden);
+            assert_eq!(err.status_code, http::StatusCode::FORBIDDEN);
+        }
+        other => panic!("expected Matrix error, got {:?}", other),
+    }
+}
+
+#[test]
+fn listed_status_with_other_body_has_no_error() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::BAD_REQUEST)
+        .body(br#"{ "errcode": "M_BAD_JSON", "error": "Bad JSON" }"#.to_vec())
+        .unwrap();
+
+    match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Http(err)) => assert!(err.error().is_none()),
+        other => panic!("expected server error, got {:?}", other),
+    }
+}
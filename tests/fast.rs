@@ -0,0 +1,122 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::error::FromHttpResponseError;
+
+mod fast {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "sync",
+            path: "/_matrix/some/sync",
+            rate_limited: false,
+            requires_authentication: false,
+            fast: true,
+        }
+
+        request {
+            pub filter: Option<String>,
+            pub r#type: String,
+            pub timeout: u64,
+        }
+
+        response {
+            pub next_batch: String,
+            pub rooms: Vec<String>,
+            pub presence: Option<serde_json::Value>,
+        }
+    }
+}
+
+mod derived {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "sync",
+            path: "/_matrix/some/sync",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            pub filter: Option<String>,
+            pub r#type: String,
+            pub timeout: u64,
+        }
+
+        response {
+            pub next_batch: String,
+            pub rooms: Vec<String>,
+            pub presence: Option<serde_json::Value>,
+        }
+    }
+}
+
+#[test]
+fn fast_request_body_matches_derived() {
+    let fast: http::Request<Vec<u8>> =
+        fast::Request { filter: None, r#type: "full".to_owned(), timeout: 30 }.try_into().unwrap();
+    let derived: http::Request<Vec<u8>> =
+        derived::Request { filter: None, r#type: "full".to_owned(), timeout: 30 }
+            .try_into()
+            .unwrap();
+
+    assert_eq!(fast.body(), derived.body());
+    assert_eq!(fast.body().as_slice(), &br#"{"type":"full","timeout":30}"#[..]);
+}
+
+#[test]
+fn fast_response_body_matches_derived() {
+    let fast: http::Response<Vec<u8>> = fast::Response {
+        next_batch: "s1".to_owned(),
+        rooms: vec!["!a:example.org".to_owned()],
+        presence: Some(serde_json::json!({ "events": [] })),
+    }
+    .try_into()
+    .unwrap();
+    let derived: http::Response<Vec<u8>> = derived::Response {
+        next_batch: "s1".to_owned(),
+        rooms: vec!["!a:example.org".to_owned()],
+        presence: Some(serde_json::json!({ "events": [] })),
+    }
+    .try_into()
+    .unwrap();
+
+    assert_eq!(fast.body(), derived.body());
+}
+
+#[test]
+fn fast_request_roundtrip() {
+    let http_req = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/sync")
+        .body(br#"{ "type": "lazy", "unknown": { "nested": [1] }, "timeout": 5 }"#.to_vec())
+        .unwrap();
+
+    let req = fast::Request::try_from(http_req).unwrap();
+    assert_eq!(req.filter, None);
+    assert_eq!(req.r#type, "lazy");
+    assert_eq!(req.timeout, 5);
+}
+
+#[test]
+fn fast_response_errors() {
+    let missing = http::Response::builder().body(br#"{ "rooms": [] }"#.to_vec()).unwrap();
+    match fast::Response::try_from(missing) {
+        Err(FromHttpResponseError::Deserialization(err)) => {
+            assert!(err.to_string().contains("missing field `next_batch`"))
+        }
+        other => panic!("expected deserialization error, got {:?}", other),
+    }
+
+    let duplicate = http::Response::builder()
+        .body(br#"{ "next_batch": "a", "next_batch": "b", "rooms": [] }"#.to_vec())
+        .unwrap();
+    match fast::Response::try_from(duplicate) {
+        Err(FromHttpResponseError::Deserialization(err)) => {
+            assert!(err.to_string().contains("duplicate field `next_batch`"))
+        }
+        other => panic!("expected deserialization error, got {:?}", other),
+    }
+}
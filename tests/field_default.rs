@@ -0,0 +1,60 @@
+use std::convert::TryFrom;
+
+use ruma_api::ruma_api;
+
+mod defaults {
+    pub fn limit() -> u32 {
+        10
+    }
+}
+
+fn default_direction() -> String {
+    "b".to_owned()
+}
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "messages",
+        path: "/_matrix/some/messages",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query, default = default_direction)]
+        pub dir: String,
+
+        #[ruma_api(default = defaults::limit)]
+        pub limit: u32,
+    }
+
+    response {}
+}
+
+#[test]
+fn absent_fields_use_defaults() {
+    let http_req = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/messages")
+        .body(b"{}".to_vec())
+        .unwrap();
+    let req = Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.dir, "b");
+    assert_eq!(req.limit, 10);
+}
+
+#[test]
+fn present_fields_override_defaults() {
+    let http_req = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/messages?dir=f")
+        .body(br#"{"limit":20}"#.to_vec())
+        .unwrap();
+    let req = Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.dir, "f");
+    assert_eq!(req.limit, 20);
+}
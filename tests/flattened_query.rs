@@ -0,0 +1,67 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pagination {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    pub limit: u32,
+}
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "messages",
+        path: "/_matrix/some/messages",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query)]
+        pub dir: String,
+
+        #[ruma_api(query, flatten)]
+        pub pagination: Pagination,
+    }
+
+    response {}
+}
+
+#[test]
+fn flattened_fields_are_top_level_parameters() {
+    let req = Request {
+        dir: "b".to_owned(),
+        pagination: Pagination { from: Some("s1".to_owned()), to: None, limit: 20 },
+    };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().query(), Some("dir=b&from=s1&limit=20"));
+}
+
+#[test]
+fn flattened_fields_roundtrip() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/messages?limit=5&dir=f&to=s9")
+        .body(Vec::new())
+        .unwrap();
+    let req = Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.dir, "f");
+    assert_eq!(req.pagination, Pagination { from: None, to: Some("s9".to_owned()), limit: 5 });
+}
+
+#[test]
+fn invalid_flattened_field_is_an_error() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/messages?limit=many&dir=f")
+        .body(Vec::new())
+        .unwrap();
+
+    assert!(Request::try_from(http_req).is_err());
+}
@@ -0,0 +1,39 @@
+use std::convert::{TryFrom, TryInto};
+
+use http::header::CONTENT_LENGTH;
+
+ruma_api::ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: HEAD,
+        name: "head_endpoint",
+        path: "/_matrix/some/head/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        #[ruma_api(header = CONTENT_LENGTH)]
+        pub content_length: String,
+    }
+}
+
+#[test]
+fn head_response_without_body() {
+    let http_res =
+        http::Response::builder().header(CONTENT_LENGTH, "1234").body(Vec::new()).unwrap();
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.content_length, "1234");
+}
+
+#[test]
+fn head_response_outgoing() {
+    let http_res: http::Response<Vec<u8>> =
+        Response { content_length: "1234".to_owned() }.try_into().unwrap();
+
+    assert_eq!(http_res.headers().get(CONTENT_LENGTH).unwrap(), "1234");
+    assert!(http_res.body().is_empty());
+}
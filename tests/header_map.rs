@@ -0,0 +1,68 @@
+use std::convert::{TryFrom, TryInto};
+
+use http::header::{HeaderName, HeaderValue, CONNECTION, CONTENT_TYPE};
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "proxy",
+        path: "/_matrix/some/proxy/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(header = CONTENT_TYPE)]
+        pub content_type: String,
+
+        #[ruma_api(header_map)]
+        pub headers: Vec<(HeaderName, String)>,
+    }
+
+    response {}
+}
+
+#[test]
+fn header_map_outgoing() {
+    let req = Request {
+        content_type: "application/json".to_owned(),
+        headers: vec![
+            (HeaderName::from_static("x-foo"), "a".to_owned()),
+            (HeaderName::from_static("x-foo"), "b".to_owned()),
+            (CONNECTION, "close".to_owned()),
+        ],
+    };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    let foo: Vec<_> = http_req.headers().get_all("x-foo").iter().collect();
+    assert_eq!(foo, vec!["a", "b"]);
+    assert!(http_req.headers().get(CONNECTION).is_none());
+    assert_eq!(http_req.headers().get(CONTENT_TYPE).unwrap(), "application/json");
+}
+
+#[test]
+fn header_map_incoming() {
+    let mut http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/proxy/endpoint")
+        .header(CONTENT_TYPE, "application/json")
+        .header("x-foo", "a")
+        .header("x-foo", "b")
+        .header(CONNECTION, "close")
+        .body(Vec::new())
+        .unwrap();
+    http_req.headers_mut().append("x-bar", HeaderValue::from_static("c"));
+
+    let req = Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.content_type, "application/json");
+    assert_eq!(
+        req.headers,
+        vec![
+            (HeaderName::from_static("x-foo"), "a".to_owned()),
+            (HeaderName::from_static("x-foo"), "b".to_owned()),
+            (HeaderName::from_static("x-bar"), "c".to_owned()),
+        ]
+    );
+}
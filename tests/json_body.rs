@@ -0,0 +1,77 @@
+use std::convert::{TryFrom, TryInto};
+
+use serde_json::{json, Value as JsonValue};
+
+mod json_body {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "experimental",
+            path: "/_matrix/some/experimental",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(json_body)]
+            pub content: serde_json::Value,
+        }
+
+        response {
+            #[ruma_api(json_body)]
+            pub content: serde_json::Value,
+        }
+    }
+}
+
+mod value_body {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "experimental",
+            path: "/_matrix/some/experimental",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(body)]
+            pub content: serde_json::Value,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn json_body_request_is_not_wrapped() {
+    let req = json_body::Request { content: json!([1, "two", { "three": 3 }]) };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    let body: JsonValue = serde_json::from_slice(http_req.body()).unwrap();
+    assert_eq!(body, json!([1, "two", { "three": 3 }]));
+
+    let req = json_body::Request::try_from(http_req).unwrap();
+    assert_eq!(req.content, json!([1, "two", { "three": 3 }]));
+}
+
+#[test]
+fn json_body_response_roundtrip() {
+    let res = json_body::Response { content: json!({ "anything": null }) };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+
+    assert_eq!(http_res.body(), br#"{"anything":null}"#);
+
+    let res = json_body::Response::try_from(http_res).unwrap();
+    assert_eq!(res.content, json!({ "anything": null }));
+}
+
+#[test]
+fn body_value_is_not_wrapped() {
+    let req = value_body::Request { content: json!("just a string") };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.body(), br#""just a string""#);
+}
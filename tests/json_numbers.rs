@@ -0,0 +1,79 @@
+#![cfg(any(feature = "arbitrary-precision", feature = "raw-value"))]
+
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+#[cfg(feature = "arbitrary-precision")]
+mod arbitrary_precision {
+    use super::*;
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "stream_position",
+            path: "/_matrix/some/stream/position",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            pub position: serde_json::Value,
+        }
+
+        response {}
+    }
+
+    #[test]
+    fn large_numbers_are_preserved() {
+        let body = br#"{"position":123456789012345678901234567890}"#.to_vec();
+        let http_req = http::Request::builder()
+            .method("POST")
+            .uri("http://localhost/_matrix/some/stream/position")
+            .body(body.clone())
+            .unwrap();
+
+        let req = Request::try_from(http_req).unwrap();
+        assert_eq!(req.position.to_string(), "123456789012345678901234567890");
+
+        let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+        assert_eq!(http_req.body(), &body);
+    }
+}
+
+#[cfg(feature = "raw-value")]
+mod raw_value {
+    use super::*;
+    use serde_json::value::RawValue;
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "send_event",
+            path: "/_matrix/some/send/event",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            pub content: Box<RawValue>,
+        }
+
+        response {}
+    }
+
+    #[test]
+    fn raw_values_are_kept_verbatim() {
+        let body = br#"{"content":{"ts": 1.0e3, "big": 18446744073709551616}}"#.to_vec();
+        let http_req = http::Request::builder()
+            .method("POST")
+            .uri("http://localhost/_matrix/some/send/event")
+            .body(body)
+            .unwrap();
+
+        let req = Request::try_from(http_req).unwrap();
+        assert_eq!(req.content.get(), r#"{"ts": 1.0e3, "big": 18446744073709551616}"#);
+    }
+}
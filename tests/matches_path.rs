@@ -0,0 +1,40 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "get_state",
+        path: "/_matrix/some/rooms/:room_id/state/:event_type",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+
+        #[ruma_api(path)]
+        pub event_type: String,
+    }
+
+    response {}
+}
+
+fn matches(uri: &str) -> bool {
+    Request::matches_path(&uri.parse().unwrap())
+}
+
+#[test]
+fn matching_paths() {
+    assert!(matches("/_matrix/some/rooms/!room:localhost/state/m.room.name"));
+    assert!(matches("https://localhost/_matrix/some/rooms/%21room/state/m.room.name?foo=bar"));
+}
+
+#[test]
+fn non_matching_paths() {
+    assert!(!matches("/_matrix/some/rooms/!room:localhost/state"));
+    assert!(!matches("/_matrix/some/rooms//state/m.room.name"));
+    assert!(!matches("/_matrix/some/rooms/!room:localhost/members/m.room.name"));
+    assert!(!matches("/_matrix/some/rooms/!room:localhost/state/m.room.name/extra"));
+}
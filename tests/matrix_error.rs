@@ -0,0 +1,232 @@
+use http::StatusCode;
+use ruma_api::{error::ServerError, MatrixError, MatrixErrorKind};
+
+fn server_error(status: StatusCode, body: &[u8]) -> ServerError {
+    ServerError::new(http::Response::builder().status(status).body(body.to_vec()).unwrap())
+}
+
+#[test]
+fn matrix_error_from_body() {
+    let error = server_error(
+        StatusCode::BAD_REQUEST,
+        br#"{ "errcode": "M_FORBIDDEN", "error": "You are not invited to this room." }"#,
+    )
+    .to_matrix_error();
+
+    assert_eq!(error.kind, MatrixErrorKind::Forbidden);
+    assert_eq!(error.status_code, StatusCode::BAD_REQUEST);
+    assert_eq!(error.message, "You are not invited to this room.");
+}
+
+#[test]
+fn matrix_error_from_status_with_empty_body() {
+    let error = server_error(StatusCode::NOT_FOUND, b"").to_matrix_error();
+
+    assert_eq!(error.kind, MatrixErrorKind::NotFound);
+    assert_eq!(error.status_code, StatusCode::NOT_FOUND);
+    assert_eq!(error.message, "Not Found");
+}
+
+#[test]
+fn matrix_error_from_status_with_non_json_body() {
+    let error =
+        server_error(StatusCode::TOO_MANY_REQUESTS, b"<html>slow down</html>").to_matrix_error();
+
+    assert_eq!(error.kind, MatrixErrorKind::LimitExceeded);
+    assert_eq!(error.message, "<html>slow down</html>");
+}
+
+#[test]
+fn matrix_error_from_unmapped_status() {
+    let error = server_error(StatusCode::BAD_GATEWAY, b"").to_matrix_error();
+
+    assert_eq!(error.kind, MatrixErrorKind::Unknown);
+}
+
+#[test]
+fn server_error_accessors() {
+    let error = server_error(StatusCode::FORBIDDEN, b"<html>nope</html>");
+
+    assert_eq!(error.status(), StatusCode::FORBIDDEN);
+    assert_eq!(error.body_bytes(), b"<html>nope</html>");
+    assert!(error.try_matrix_error().is_none());
+}
+
+#[test]
+fn try_matrix_error_from_body() {
+    let error = server_error(
+        StatusCode::FORBIDDEN,
+        br#"{ "errcode": "M_FORBIDDEN", "error": "You are not invited to this room." }"#,
+    )
+    .try_matrix_error()
+    .unwrap();
+
+    assert_eq!(error.kind, MatrixErrorKind::Forbidden);
+    assert_eq!(error.status_code, StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn matrix_error_without_errcode() {
+    let error =
+        server_error(StatusCode::NOT_FOUND, br#"{ "error": "No such room." }"#).to_matrix_error();
+
+    assert_eq!(error.kind, MatrixErrorKind::NotFound);
+    assert_eq!(error.status_code, StatusCode::NOT_FOUND);
+    assert_eq!(error.message, "No such room.");
+    assert!(error.extra().is_empty());
+
+    let error: MatrixError = serde_json::from_str(r#"{ "error": "No such room." }"#).unwrap();
+    assert_eq!(error.kind, MatrixErrorKind::Unknown);
+    assert_eq!(error.message, "No such room.");
+}
+
+#[test]
+fn unknown_token_soft_logout() {
+    let error = server_error(
+        StatusCode::UNAUTHORIZED,
+        br#"{ "errcode": "M_UNKNOWN_TOKEN", "error": "Soft logged out", "soft_logout": true }"#,
+    )
+    .to_matrix_error();
+
+    assert_eq!(error.kind, MatrixErrorKind::UnknownToken { soft_logout: true });
+    assert!(error.soft_logout());
+}
+
+#[test]
+fn unknown_token_without_soft_logout() {
+    let error = server_error(
+        StatusCode::UNAUTHORIZED,
+        br#"{ "errcode": "M_UNKNOWN_TOKEN", "error": "Invalid token" }"#,
+    )
+    .to_matrix_error();
+
+    assert_eq!(error.kind, MatrixErrorKind::UnknownToken { soft_logout: false });
+    assert!(!error.soft_logout());
+    assert_eq!(
+        serde_json::to_value(&error).unwrap(),
+        serde_json::json!({ "errcode": "M_UNKNOWN_TOKEN", "error": "Invalid token" })
+    );
+}
+
+#[test]
+fn parsed_error_has_response_status() {
+    let error = server_error(
+        StatusCode::FORBIDDEN,
+        br#"{ "errcode": "M_FORBIDDEN", "error": "You are not invited to this room." }"#,
+    );
+
+    assert_eq!(error.error().unwrap().status_code, StatusCode::FORBIDDEN);
+    assert_eq!(error.into_error().unwrap().status_code, StatusCode::FORBIDDEN);
+}
+
+#[test]
+#[allow(clippy::result_large_err)]
+fn from_http_response_branches_on_status() {
+    use ruma_api::error::{from_http_response, FromHttpResponseError};
+
+    let response = |status| http::Response::builder().status(status).body(b"{}".to_vec()).unwrap();
+    let parse = |response: http::Response<Vec<u8>>| -> Result<_, FromHttpResponseError> {
+        Ok(response.status())
+    };
+
+    assert_eq!(from_http_response(response(StatusCode::OK), parse).unwrap(), StatusCode::OK);
+    assert!(matches!(
+        from_http_response(response(StatusCode::NOT_MODIFIED), parse),
+        Err(FromHttpResponseError::NotModified)
+    ));
+    match from_http_response(response(StatusCode::FORBIDDEN), parse) {
+        Err(FromHttpResponseError::Http(err)) => assert_eq!(err.status(), StatusCode::FORBIDDEN),
+        other => panic!("expected server error, got {:?}", other),
+    }
+}
+
+#[test]
+fn retry_after() {
+    use std::time::Duration;
+
+    let error = server_error(
+        StatusCode::TOO_MANY_REQUESTS,
+        br#"{ "errcode": "M_LIMIT_EXCEEDED", "error": "Too many requests", "retry_after_ms": 1500 }"#,
+    );
+    assert_eq!(error.retry_after(), Some(Duration::from_millis(1500)));
+
+    let error = ServerError::<ruma_api::MatrixError>::new(
+        http::Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .header(http::header::RETRY_AFTER, "120")
+            .body(Vec::new())
+            .unwrap(),
+    );
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(120)));
+
+    assert_eq!(server_error(StatusCode::TOO_MANY_REQUESTS, b"").retry_after(), None);
+}
+
+#[test]
+fn matrix_error_extra_fields() {
+    let error = server_error(
+        StatusCode::FORBIDDEN,
+        br#"{
+            "errcode": "M_CONSENT_NOT_GIVEN",
+            "error": "You must review and agree to our terms and conditions.",
+            "consent_uri": "https://example.org/consent"
+        }"#,
+    )
+    .to_matrix_error();
+
+    assert_eq!(error.kind, MatrixErrorKind::ConsentNotGiven);
+    assert_eq!(error.extra().len(), 1);
+    assert_eq!(error.extra()["consent_uri"], "https://example.org/consent");
+}
+
+#[test]
+fn matrix_error_extra_excludes_kind_fields() {
+    let error = server_error(
+        StatusCode::UNAUTHORIZED,
+        br#"{ "errcode": "M_UNKNOWN_TOKEN", "error": "Expired.", "soft_logout": false }"#,
+    )
+    .to_matrix_error();
+
+    assert_eq!(error.kind, MatrixErrorKind::UnknownToken { soft_logout: false });
+    assert!(error.extra().is_empty());
+}
+
+#[test]
+fn matrix_error_extra_roundtrip() {
+    let mut error = MatrixError::new(
+        MatrixErrorKind::ResourceLimitExceeded,
+        StatusCode::FORBIDDEN,
+        "Monthly active user limit exceeded.".to_owned(),
+    );
+    error.extra_mut().insert("admin_contact".to_owned(), "mailto:admin@example.org".into());
+
+    let http_response: http::Response<Vec<u8>> = error.into();
+    let error = server_error(http_response.status(), http_response.body()).to_matrix_error();
+
+    assert_eq!(error.kind, MatrixErrorKind::ResourceLimitExceeded);
+    assert_eq!(error.message, "Monthly active user limit exceeded.");
+    assert_eq!(error.extra()["admin_contact"], "mailto:admin@example.org");
+}
+
+#[test]
+fn error_kinds_as_str_matches_errcode() {
+    let kinds = MatrixErrorKind::all();
+    assert!(kinds.contains(&MatrixErrorKind::Forbidden));
+    assert!(kinds.contains(&MatrixErrorKind::UnknownToken { soft_logout: false }));
+
+    for kind in kinds {
+        let json = serde_json::to_value(kind).unwrap();
+        assert_eq!(json["errcode"], kind.as_str());
+        assert!(kind.as_str().starts_with("M_"));
+    }
+
+    let mut errcodes: Vec<_> = kinds.iter().map(MatrixErrorKind::as_str).collect();
+    errcodes.sort_unstable();
+    errcodes.dedup();
+    assert_eq!(errcodes.len(), kinds.len());
+}
+
+#[test]
+fn error_kind_as_str_ignores_fields() {
+    assert_eq!(MatrixErrorKind::UnknownToken { soft_logout: true }.as_str(), "M_UNKNOWN_TOKEN");
+}
@@ -0,0 +1,113 @@
+use std::convert::TryFrom;
+
+use ruma_api::error::FromHttpRequestError;
+
+mod upload {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "upload",
+            path: "/_matrix/some/upload",
+            rate_limited: false,
+            requires_authentication: false,
+            max_body_size: 8,
+        }
+
+        request {
+            #[ruma_api(raw_body)]
+            pub file: Vec<u8>,
+        }
+
+        response {}
+    }
+}
+
+mod json {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "json",
+            path: "/_matrix/some/json",
+            rate_limited: false,
+            requires_authentication: false,
+            max_body_size: 16,
+        }
+
+        request {
+            pub name: String,
+        }
+
+        response {}
+    }
+}
+
+fn http_request(path: &str, body: &[u8]) -> http::Request<Vec<u8>> {
+    http::Request::builder()
+        .method("POST")
+        .uri(format!("http://localhost{}", path))
+        .body(body.to_vec())
+        .unwrap()
+}
+
+fn assert_too_large(err: FromHttpRequestError, size: u64, limit: u64) {
+    match err {
+        FromHttpRequestError::TooLarge(err) => {
+            assert_eq!(err.size(), size);
+            assert_eq!(err.limit(), limit);
+        }
+        other => panic!("expected too large error, got {:?}", other),
+    }
+}
+
+#[test]
+fn raw_body_at_limit() {
+    let req = upload::Request::try_from(http_request("/_matrix/some/upload", b"12345678")).unwrap();
+    assert_eq!(req.file, b"12345678");
+}
+
+#[test]
+fn raw_body_over_limit() {
+    let err =
+        upload::Request::try_from(http_request("/_matrix/some/upload", b"123456789")).unwrap_err();
+
+    assert_eq!(err.endpoint_name(), Some("upload"));
+    assert_eq!(
+        err.to_string(),
+        "request to endpoint `upload` rejected: body of 9 bytes exceeds the limit of 8 bytes"
+    );
+    assert_too_large(err, 9, 8);
+}
+
+#[test]
+fn json_body_at_limit() {
+    let body = br#"{"name":"abcde"}"#;
+    assert_eq!(body.len(), 16);
+
+    let req = json::Request::try_from(http_request("/_matrix/some/json", body)).unwrap();
+    assert_eq!(req.name, "abcde");
+}
+
+#[test]
+fn json_body_over_limit() {
+    let err = json::Request::try_from(http_request("/_matrix/some/json", br#"{"name":"abcdef"}"#))
+        .unwrap_err();
+
+    assert_too_large(err, 17, 16);
+}
+
+#[test]
+fn content_length_over_limit_rejected_before_body() {
+    let (parts, _) = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/json")
+        .header(http::header::CONTENT_LENGTH, "1000")
+        .body(())
+        .unwrap()
+        .into_parts();
+
+    let err = json::Request::try_from_parts(&parts).unwrap_err();
+    assert_eq!(err.endpoint_name(), Some("json"));
+    assert_too_large(err, 1000, 16);
+}
@@ -0,0 +1,102 @@
+use ruma_api::{ruma_api, Endpoint, Metadata};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "some_endpoint",
+        path: "/_matrix/some/endpoint",
+        rate_limited: true,
+        requires_authentication: true,
+    }
+
+    request {
+        pub foo: String,
+    }
+
+    response {}
+}
+
+fn log_request(request: &impl AsRef<Metadata>) -> String {
+    let metadata = request.as_ref();
+    format!("{} {}", metadata.method, metadata.path)
+}
+
+#[test]
+fn metadata_from_value() {
+    let req = Request { foo: "foo".to_owned() };
+    let metadata = req.metadata();
+
+    assert_eq!(metadata.name, Request::METADATA.name);
+    assert!(metadata.rate_limited);
+    assert!(metadata.requires_authentication);
+    assert_eq!(log_request(&req), "POST /_matrix/some/endpoint");
+}
+
+mod versioned {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "versioned_endpoint",
+            path: "/_matrix/some/versioned",
+            rate_limited: false,
+            requires_authentication: false,
+            api: ServerServer,
+            added_in: "v1.1",
+            removed_in: "v1.3",
+        }
+
+        request {}
+
+        response {}
+    }
+}
+
+#[test]
+fn metadata_spec_versions() {
+    assert_eq!(versioned::Request::METADATA.added_in, Some("v1.1"));
+    assert_eq!(versioned::Request::METADATA.removed_in, Some("v1.3"));
+
+    assert_eq!(Request::METADATA.added_in, None);
+    assert_eq!(Request::METADATA.removed_in, None);
+}
+
+#[test]
+fn method_and_path_consts() {
+    assert_eq!(Request::METHOD, http::Method::POST);
+    assert_eq!(Request::PATH, "/_matrix/some/endpoint");
+    assert_eq!(versioned::Request::METHOD, versioned::Request::METADATA.method);
+    assert_eq!(versioned::Request::PATH, versioned::Request::METADATA.path);
+}
+
+#[test]
+fn metadata_display() {
+    assert_eq!(Request::METADATA.to_string(), "POST /_matrix/some/endpoint (some_endpoint)");
+}
+
+#[test]
+fn metadata_serialization() {
+    assert_eq!(
+        serde_json::to_value(&versioned::Request::METADATA).unwrap(),
+        serde_json::json!({
+            "description": "Does something.",
+            "method": "GET",
+            "name": "versioned_endpoint",
+            "path": "/_matrix/some/versioned",
+            "rate_limited": false,
+            "requires_authentication": false,
+            "status": 200,
+            "appservice": false,
+            "api": "ServerServer",
+            "added_in": "v1.1",
+            "removed_in": "v1.3",
+        })
+    );
+}
+
+#[test]
+fn metadata_api_kind() {
+    assert_eq!(versioned::Request::METADATA.api, ruma_api::ApiKind::ServerServer);
+    assert_eq!(Request::METADATA.api, ruma_api::ApiKind::ClientServer);
+}
@@ -0,0 +1,64 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "some_endpoint",
+        path: "/_matrix/some/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        #[ruma_api(header = LINK)]
+        pub links: Vec<String>,
+
+        #[ruma_api(header = WARNING)]
+        pub warnings: Vec<String>,
+    }
+}
+
+#[test]
+fn response_collects_all_header_values() {
+    let http_res = http::Response::builder()
+        .header(http::header::LINK, "<https://example.org/1>; rel=\"next\"")
+        .header(http::header::LINK, "<https://example.org/0>; rel=\"prev\"")
+        .body(Vec::new())
+        .unwrap();
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(
+        res.links,
+        vec![
+            "<https://example.org/1>; rel=\"next\"".to_owned(),
+            "<https://example.org/0>; rel=\"prev\"".to_owned(),
+        ]
+    );
+    assert!(res.warnings.is_empty());
+}
+
+#[test]
+fn response_emits_one_header_per_value() {
+    let res = Response {
+        links: Vec::new(),
+        warnings: vec!["199 - \"first\"".to_owned(), "199 - \"second\"".to_owned()],
+    };
+
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+    let warnings: Vec<_> = http_res.headers().get_all(http::header::WARNING).iter().collect();
+    assert_eq!(warnings, vec!["199 - \"first\"", "199 - \"second\""]);
+    assert!(http_res.headers().get(http::header::LINK).is_none());
+}
+
+#[test]
+fn invalid_header_value_is_an_error() {
+    let res = Response { links: vec!["a\nb".to_owned()], warnings: Vec::new() };
+
+    let err = http::Response::<Vec<u8>>::try_from(res).unwrap_err();
+    assert!(err.to_string().starts_with("Invalid header value"));
+}
@@ -0,0 +1,155 @@
+use std::convert::{TryFrom, TryInto};
+
+use http::header::CONTENT_TYPE;
+use ruma_api::{
+    error::FromHttpRequestError,
+    multipart::{Json, Part},
+    ruma_api,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileInfo {
+    pub size: u64,
+}
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "upload",
+        path: "/_matrix/some/upload/:server",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub server: String,
+
+        #[ruma_api(multipart)]
+        pub info: Json<FileInfo>,
+
+        #[ruma_api(multipart)]
+        pub file: Part,
+
+        #[ruma_api(multipart)]
+        pub comment: Option<String>,
+    }
+
+    response {}
+}
+
+fn request() -> Request {
+    Request {
+        server: "localhost".to_owned(),
+        info: Json(FileInfo { size: 3 }),
+        file: Part {
+            content_type: Some("image/png".to_owned()),
+            filename: Some("cat.png".to_owned()),
+            data: vec![0, 13, 10],
+        },
+        comment: None,
+    }
+}
+
+#[test]
+fn multipart_roundtrip() {
+    let http_req: http::Request<Vec<u8>> = request().try_into().unwrap();
+
+    let content_type = http_req.headers()[CONTENT_TYPE].to_str().unwrap();
+    assert!(content_type.starts_with("multipart/form-data; boundary="));
+    let body = String::from_utf8_lossy(http_req.body());
+    assert!(body.contains("Content-Disposition: form-data; name=\"file\"; filename=\"cat.png\""));
+    assert!(!body.contains("name=\"comment\""));
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.server, "localhost");
+    assert_eq!(req.info, Json(FileInfo { size: 3 }));
+    assert_eq!(req.file, request().file);
+    assert_eq!(req.comment, None);
+}
+
+#[test]
+fn optional_part_is_sent_when_set() {
+    let req = Request { comment: Some("a cat".to_owned()), ..request() };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(Request::try_from(http_req).unwrap().comment.as_deref(), Some("a cat"));
+}
+
+#[test]
+fn missing_part_is_an_error() {
+    let body = b"--xyz\r\n\
+        Content-Disposition: form-data; name=\"info\"\r\n\
+        \r\n\
+        {\"size\":3}\r\n\
+        --xyz--\r\n";
+    let http_req = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/upload/localhost")
+        .header(CONTENT_TYPE, "multipart/form-data; boundary=\"xyz\"")
+        .body(body.to_vec())
+        .unwrap();
+
+    match Request::try_from(http_req) {
+        Err(FromHttpRequestError::Deserialization(err)) => {
+            assert_eq!(
+                err.to_string(),
+                "invalid multipart body: invalid part `file`: missing part"
+            );
+        }
+        other => panic!("expected deserialization error, got {:?}", other),
+    }
+}
+
+#[test]
+fn non_multipart_content_type_is_an_error() {
+    let http_req = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/upload/localhost")
+        .header(CONTENT_TYPE, "application/json")
+        .body(b"{}".to_vec())
+        .unwrap();
+
+    assert!(Request::try_from(http_req).is_err());
+}
+
+#[test]
+fn filename_with_quotes_and_semicolons_roundtrip() {
+    let filename = "my \"cat\"; final\r\nversion.png";
+    let req = Request {
+        file: Part { filename: Some(filename.to_owned()), ..request().file },
+        ..request()
+    };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    let body = String::from_utf8_lossy(http_req.body());
+    assert!(body.contains("filename=\"my %22cat%22; final%0D%0Aversion.png\"\r\n"));
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.file.filename.as_deref(), Some(filename));
+}
+
+#[test]
+fn quoted_filename_with_backslash_escapes() {
+    let body = b"--xyz\r\n\
+        Content-Disposition: form-data; name=\"info\"\r\n\
+        \r\n\
+        {\"size\":3}\r\n\
+        --xyz\r\n\
+        Content-Disposition: form-data; filename=\"a \\\"b\\\"; c.png\"; name=file\r\n\
+        \r\n\
+        abc\r\n\
+        --xyz--\r\n";
+    let http_req = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/upload/localhost")
+        .header(CONTENT_TYPE, "multipart/form-data; boundary=xyz")
+        .body(body.to_vec())
+        .unwrap();
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.file.filename.as_deref(), Some("a \"b\"; c.png"));
+    assert_eq!(req.file.data, b"abc");
+}
@@ -0,0 +1,96 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: PUT,
+        name: "set_profile",
+        path: "/_matrix/some/profile",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        pub displayname: Option<String>,
+
+        #[ruma_api(explicit_null)]
+        pub avatar_url: Option<String>,
+
+        #[serde(skip_serializing_if = "Option::is_none", rename = "m.status")]
+        pub status: Option<String>,
+    }
+
+    response {
+        pub displayname: Option<String>,
+
+        #[ruma_api(explicit_null)]
+        pub avatar_url: Option<String>,
+    }
+}
+
+mod fast {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: PUT,
+            name: "set_profile",
+            path: "/_matrix/some/profile",
+            rate_limited: false,
+            requires_authentication: false,
+            fast: true,
+        }
+
+        request {
+            pub displayname: Option<String>,
+
+            #[ruma_api(explicit_null)]
+            pub avatar_url: Option<String>,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn none_body_fields_are_omitted() {
+    let req = Request { displayname: None, avatar_url: None, status: None };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.body(), br#"{"avatar_url":null}"#);
+
+    let res = Response { displayname: None, avatar_url: None };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+    assert_eq!(http_res.body(), br#"{"avatar_url":null}"#);
+
+    let req = fast::Request { displayname: None, avatar_url: None };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.body(), br#"{"avatar_url":null}"#);
+}
+
+#[test]
+fn some_body_fields_are_sent() {
+    let req = Request {
+        displayname: Some("Alice".to_owned()),
+        avatar_url: Some("mxc://example.org/abc".to_owned()),
+        status: Some("busy".to_owned()),
+    };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(
+        http_req.body(),
+        br#"{"displayname":"Alice","avatar_url":"mxc://example.org/abc","m.status":"busy"}"#
+    );
+
+    let req = fast::Request { displayname: Some("Alice".to_owned()), avatar_url: None };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.body(), br#"{"displayname":"Alice","avatar_url":null}"#);
+}
+
+#[test]
+fn omitted_body_fields_are_parsed_as_none() {
+    let http_res = http::Response::builder().body(b"{}".to_vec()).unwrap();
+    let res = Response::try_from(http_res).unwrap();
+
+    assert_eq!(res.displayname, None);
+    assert_eq!(res.avatar_url, None);
+}
@@ -0,0 +1,51 @@
+use std::convert::{TryFrom, TryInto};
+
+use http::header::{ALLOW, CONTENT_TYPE};
+use ruma_api::{ruma_api, Endpoint};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: OPTIONS,
+        name: "preflight",
+        path: "/_matrix/some/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        #[ruma_api(header = ALLOW)]
+        pub allow: String,
+    }
+}
+
+#[test]
+fn metadata_method() {
+    assert_eq!(Request::METADATA.method, http::Method::OPTIONS);
+
+    let http_req: http::Request<Vec<u8>> = Request.try_into().unwrap();
+    assert_eq!(http_req.method(), http::Method::OPTIONS);
+}
+
+#[test]
+fn header_only_response_roundtrip() {
+    let res = Response { allow: "GET, PUT, OPTIONS".to_owned() };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+
+    assert_eq!(http_res.headers()[ALLOW], "GET, PUT, OPTIONS");
+    assert!(http_res.headers().get(CONTENT_TYPE).is_none());
+    assert!(http_res.body().is_empty());
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.allow, "GET, PUT, OPTIONS");
+}
+
+#[test]
+fn response_body_is_ignored() {
+    let http_res =
+        http::Response::builder().header(ALLOW, "GET").body(b"not json".to_vec()).unwrap();
+
+    assert_eq!(Response::try_from(http_res).unwrap().allow, "GET");
+}
@@ -0,0 +1,124 @@
+use std::convert::TryFrom;
+
+use ruma_api::Outgoing;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Outgoing, Serialize)]
+pub struct Borrowed<'a> {
+    pub data: &'a str,
+    pub list: &'a [u32],
+    pub opt: Option<&'a str>,
+}
+
+#[derive(Debug, Outgoing, Serialize)]
+pub struct Generic<T> {
+    pub value: T,
+}
+
+#[derive(Debug, Outgoing, Serialize)]
+pub struct BorrowedGeneric<'a, T: Serialize> {
+    pub value: &'a T,
+}
+
+#[derive(Debug, Outgoing, Serialize)]
+pub struct BorrowedBounded<'a, T: Serialize + 'a> {
+    pub value: &'a T,
+}
+
+#[derive(Debug, Outgoing, Serialize)]
+pub struct BorrowedWhere<'a, 'b, T>
+where
+    T: 'a + 'b + Serialize,
+    'b: 'a,
+{
+    pub value: &'a T,
+    pub other: &'b T,
+}
+
+#[test]
+fn borrowed_fields_become_owned() {
+    let borrowed = Borrowed { data: "hello", list: &[1, 2], opt: Some("opt") };
+    let json = serde_json::to_string(&borrowed).unwrap();
+
+    let incoming: IncomingBorrowed = serde_json::from_str(&json).unwrap();
+    let _: String = incoming.data;
+    assert_eq!(incoming.list, vec![1, 2]);
+    assert_eq!(incoming.opt, Some("opt".to_owned()));
+}
+
+#[test]
+fn generic_params_are_kept() {
+    fn assert_incoming_self<T: Outgoing<Incoming = T>>() {}
+    assert_incoming_self::<Generic<u32>>();
+
+    let value = 5u64;
+    let json = serde_json::to_string(&BorrowedGeneric { value: &value }).unwrap();
+    let incoming: <BorrowedGeneric<'_, u64> as Outgoing>::Incoming =
+        serde_json::from_str(&json).unwrap();
+    assert_eq!(incoming.value, 5);
+}
+
+#[test]
+fn lifetime_bounds_are_removed() {
+    let value = 5u64;
+
+    let json = serde_json::to_string(&BorrowedBounded { value: &value }).unwrap();
+    let incoming: IncomingBorrowedBounded<u64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(incoming.value, 5);
+
+    let json = serde_json::to_string(&BorrowedWhere { value: &value, other: &value }).unwrap();
+    let incoming: IncomingBorrowedWhere<u64> = serde_json::from_str(&json).unwrap();
+    assert_eq!((incoming.value, incoming.other), (5, 5));
+}
+
+/// A value that is only checked when converting back from the 'Incoming' type.
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub struct Unchecked<T>(pub T);
+
+impl TryFrom<Unchecked<String>> for String {
+    type Error = String;
+
+    fn try_from(value: Unchecked<String>) -> Result<Self, Self::Error> {
+        if value.0.is_empty() {
+            Err("empty string".to_owned())
+        } else {
+            Ok(value.0)
+        }
+    }
+}
+
+#[derive(Debug, Outgoing, Serialize)]
+#[incoming_try_into_outgoing]
+pub struct Inner {
+    #[wrap_incoming(with Unchecked)]
+    pub name: String,
+}
+
+#[derive(Debug, Outgoing, Serialize)]
+#[incoming_try_into_outgoing]
+pub struct Outer {
+    pub id: u32,
+    #[wrap_incoming]
+    pub inner: Inner,
+}
+
+#[test]
+fn incoming_try_into_outgoing() {
+    let outer = Outer { id: 1, inner: Inner { name: "foo".to_owned() } };
+    let json = serde_json::to_string(&outer).unwrap();
+
+    let incoming: IncomingOuter = serde_json::from_str(&json).unwrap();
+    let outer = Outer::try_from(incoming).unwrap();
+    assert_eq!(outer.id, 1);
+    assert_eq!(outer.inner.name, "foo");
+
+    let incoming: IncomingOuter =
+        serde_json::from_str(r#"{ "id": 2, "inner": { "name": "" } }"#).unwrap();
+    let err = Outer::try_from(incoming).unwrap_err();
+    assert_eq!(err.field(), "inner");
+    assert_eq!(
+        err.to_string(),
+        "failed to convert field `inner`: failed to convert field `name`: empty string"
+    );
+}
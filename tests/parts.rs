@@ -0,0 +1,163 @@
+use std::convert::TryFrom;
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "some_endpoint",
+        path: "/_matrix/some/endpoint/:room",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room: String,
+
+        #[ruma_api(query)]
+        pub limit: u32,
+
+        #[ruma_api(header = USER_AGENT)]
+        pub user_agent: String,
+    }
+
+    response {}
+}
+
+#[test]
+fn request_from_parts() {
+    let (parts, _body) = http::Request::builder()
+        .uri("http://localhost/_matrix/some/endpoint/%21room%3Aexample.org?limit=5")
+        .header(http::header::USER_AGENT, "test")
+        .body(())
+        .unwrap()
+        .into_parts();
+
+    let req = Request::try_from(parts).unwrap();
+    assert_eq!(req.room, "!room:example.org");
+    assert_eq!(req.limit, 5);
+    assert_eq!(req.user_agent, "test");
+}
+
+#[test]
+fn request_from_parts_invalid_query() {
+    let (parts, _body) = http::Request::builder()
+        .uri("http://localhost/_matrix/some/endpoint/room?limit=many")
+        .header(http::header::USER_AGENT, "test")
+        .body(())
+        .unwrap()
+        .into_parts();
+
+    assert!(Request::try_from(parts).is_err());
+}
+
+mod with_body {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "some_endpoint",
+            path: "/_matrix/some/endpoint/:room",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room: String,
+
+            #[ruma_api(header = AUTHORIZATION)]
+            pub authorization: String,
+
+            pub content: String,
+        }
+
+        response {}
+    }
+}
+
+mod with_raw_body {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "upload",
+            path: "/_matrix/some/upload",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(raw_body)]
+            pub file: Vec<u8>,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn two_phase_request_parsing() {
+    let (parts, body) = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/endpoint/room")
+        .header(http::header::AUTHORIZATION, "Bearer abc")
+        .body(br#"{ "content": "hello" }"#.to_vec())
+        .unwrap()
+        .into_parts();
+
+    let partial = with_body::Request::try_from_parts(&parts).unwrap();
+    assert_eq!(partial.room, "room");
+    assert_eq!(partial.authorization, "Bearer abc");
+
+    let req = partial.complete(&body).unwrap();
+    assert_eq!(req.room, "room");
+    assert_eq!(req.authorization, "Bearer abc");
+    assert_eq!(req.content, "hello");
+}
+
+#[test]
+fn two_phase_request_parsing_invalid_body() {
+    let (parts, _body) = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/endpoint/room")
+        .header(http::header::AUTHORIZATION, "Bearer abc")
+        .body(())
+        .unwrap()
+        .into_parts();
+
+    let partial = with_body::Request::try_from_parts(&parts).unwrap();
+    let err = match partial.complete(b"{ \"content\": 5 }") {
+        Err(ruma_api::error::FromHttpRequestError::Deserialization(err)) => err,
+        other => panic!("expected deserialization error, got {:?}", other),
+    };
+
+    assert_eq!(err.column(), Some(14));
+}
+
+#[test]
+fn two_phase_request_parsing_rejects_head_first() {
+    let (parts, _body) = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/endpoint/room")
+        .body(())
+        .unwrap()
+        .into_parts();
+
+    assert!(with_body::Request::try_from_parts(&parts).is_err());
+}
+
+#[test]
+fn two_phase_raw_body() {
+    let (parts, _body) = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/upload")
+        .body(())
+        .unwrap()
+        .into_parts();
+
+    let req = with_raw_body::Request::try_from_parts(&parts).unwrap().complete(b"data").unwrap();
+    assert_eq!(req.file, b"data");
+}
@@ -0,0 +1,200 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::{path::PathParam, Endpoint as _};
+
+/// A type with a path representation that differs from its `Debug` output and has no `Display`
+/// implementation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Visibility {
+    Public,
+    Private,
+}
+
+impl PathParam for Visibility {
+    type Error = &'static str;
+
+    fn to_path_segment(&self) -> String {
+        match self {
+            Visibility::Public => "pub".to_owned(),
+            Visibility::Private => "priv".to_owned(),
+        }
+    }
+
+    fn from_path_segment(segment: &str) -> Result<Self, Self::Error> {
+        match segment {
+            "pub" => Ok(Visibility::Public),
+            "priv" => Ok(Visibility::Private),
+            _ => Err("unknown visibility"),
+        }
+    }
+}
+
+mod integer_path {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "integer_path",
+            path: "/_matrix/some/endpoint/:version/:offset",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub version: u8,
+
+            #[ruma_api(path)]
+            pub offset: i64,
+        }
+
+        response {}
+    }
+}
+
+ruma_api::ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "path_params",
+        path: "/_matrix/some/:name/endpoint/:visibility",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub name: String,
+
+        #[ruma_api(path)]
+        pub visibility: Visibility,
+    }
+
+    response {}
+}
+
+#[test]
+fn path_params_roundtrip() {
+    let req = Request { name: "some name/with slash".to_owned(), visibility: Visibility::Private };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().path(), "/_matrix/some/some%20name%2Fwith%20slash/endpoint/priv");
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.name, "some name/with slash");
+    assert_eq!(req.visibility, Visibility::Private);
+}
+
+#[test]
+fn invalid_path_param() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/name/endpoint/protected")
+        .body(Vec::new())
+        .unwrap();
+
+    let err = Request::try_from(http_req).unwrap_err();
+    assert_eq!(err.endpoint_name(), Some("path_params"));
+    assert_eq!(
+        err.to_string(),
+        "deserialization failed for endpoint `path_params`: invalid path parameter: unknown \
+         visibility"
+    );
+}
+
+#[test]
+fn integer_path_params_roundtrip() {
+    let req = integer_path::Request { version: 3, offset: -20 };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().path(), "/_matrix/some/endpoint/3/-20");
+
+    let req = integer_path::Request::try_from(http_req).unwrap();
+    assert_eq!(req.version, 3);
+    assert_eq!(req.offset, -20);
+}
+
+#[test]
+fn integer_path_param_not_quoted() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/endpoint/%223%22/0")
+        .body(Vec::new())
+        .unwrap();
+
+    assert!(integer_path::Request::try_from(http_req).is_err());
+}
+
+mod lossy {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "lossy",
+            path: "/_matrix/some/lossy/:name",
+            rate_limited: false,
+            requires_authentication: false,
+            lossy_path_decoding: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub name: String,
+        }
+
+        response {}
+    }
+}
+
+fn request_with_path(path: &str) -> http::Request<Vec<u8>> {
+    http::Request::builder().uri(format!("http://localhost{}", path)).body(Vec::new()).unwrap()
+}
+
+#[test]
+fn invalid_percent_encoding_in_path() {
+    let err =
+        Request::try_from(request_with_path("/_matrix/some/na%zzme/endpoint/pub")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "deserialization failed for endpoint `path_params`: invalid path parameter: invalid \
+         percent-encoding in path segment `na%zzme`"
+    );
+
+    assert!(Request::try_from(request_with_path("/_matrix/some/name%2/endpoint/pub")).is_err());
+}
+
+#[test]
+fn invalid_utf8_in_path() {
+    let err =
+        Request::try_from(request_with_path("/_matrix/some/%FF%FE/endpoint/pub")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "deserialization failed for endpoint `path_params`: invalid path parameter: path segment \
+         `%FF%FE` is not valid UTF-8 when decoded"
+    );
+}
+
+#[test]
+fn lossy_path_decoding() {
+    let req = lossy::Request::try_from(request_with_path("/_matrix/some/lossy/a%zz%FFb")).unwrap();
+    assert_eq!(req.name, "a%zz\u{FFFD}b");
+}
+
+#[test]
+fn path_template_styles() {
+    use ruma_api::path::{convert_template, TemplateStyle};
+
+    let template = "/_matrix/client/r0/rooms/:room_id/state/:event_type/:state_key";
+
+    assert_eq!(convert_template(template, TemplateStyle::Colon), template);
+    assert_eq!(
+        convert_template(template, TemplateStyle::Braces),
+        "/_matrix/client/r0/rooms/{room_id}/state/{event_type}/{state_key}"
+    );
+    assert_eq!(
+        convert_template("/_matrix/client/versions", TemplateStyle::Braces),
+        "/_matrix/client/versions"
+    );
+    assert_eq!(
+        integer_path::Request::METADATA.path_template(TemplateStyle::Braces),
+        "/_matrix/some/endpoint/{version}/{offset}"
+    );
+}
@@ -0,0 +1,65 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "capabilities",
+        path: "/_matrix/some/:room_id/capabilities/:version",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+
+        #[ruma_api(path, path_default = "v1")]
+        pub version: Option<String>,
+    }
+
+    response {}
+}
+
+fn parse(uri: &str) -> Request {
+    Request::try_from(http::Request::builder().uri(uri).body(Vec::new()).unwrap()).unwrap()
+}
+
+#[test]
+fn default_is_used_when_unset() {
+    let req = Request { room_id: "room".to_owned(), version: None };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().path(), "/_matrix/some/room/capabilities/v1");
+}
+
+#[test]
+fn explicit_value_is_used() {
+    let req = Request { room_id: "room".to_owned(), version: Some("v2".to_owned()) };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().path(), "/_matrix/some/room/capabilities/v2");
+}
+
+#[test]
+fn incoming_path_with_segment() {
+    let req = parse("http://localhost/_matrix/some/room/capabilities/v2");
+
+    assert_eq!(req.room_id, "room");
+    assert_eq!(req.version.as_deref(), Some("v2"));
+}
+
+#[test]
+fn incoming_path_without_segment() {
+    assert_eq!(parse("http://localhost/_matrix/some/room/capabilities").version, None);
+    assert_eq!(parse("http://localhost/_matrix/some/room/capabilities/").version, None);
+}
+
+#[test]
+fn matches_path_with_and_without_segment() {
+    assert!(Request::matches_path(&"/_matrix/some/room/capabilities/v2".parse().unwrap()));
+    assert!(Request::matches_path(&"/_matrix/some/room/capabilities".parse().unwrap()));
+    assert!(!Request::matches_path(&"/_matrix/some/room".parse().unwrap()));
+}
@@ -0,0 +1,97 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::{
+    header::{Preference, Preferences},
+    ruma_api,
+};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: PUT,
+        name: "update",
+        path: "/_matrix/some/update",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(header = "Prefer")]
+        pub prefer: Option<Preferences>,
+
+        pub name: String,
+    }
+
+    response {
+        #[ruma_api(header = "Preference-Applied")]
+        pub preference_applied: Option<Preferences>,
+    }
+}
+
+#[test]
+fn preferences_roundtrip() {
+    let other = || Preference::Other { name: "foo".to_owned(), value: Some("a, b".to_owned()) };
+
+    for (preferences, string) in [
+        (vec![Preference::ReturnMinimal], "return=minimal"),
+        (vec![Preference::ReturnRepresentation], "return=representation"),
+        (vec![Preference::RespondAsync, Preference::Wait(10)], "respond-async, wait=10"),
+        (vec![Preference::HandlingStrict], "handling=strict"),
+        (vec![Preference::HandlingLenient], "handling=lenient"),
+        (vec![Preference::Other { name: "foo".to_owned(), value: None }], "foo"),
+        (vec![other(), Preference::ReturnMinimal], "foo=\"a, b\", return=minimal"),
+    ] {
+        let preferences = Preferences(preferences);
+        assert_eq!(preferences.to_string(), string);
+        assert_eq!(string.parse::<Preferences>().unwrap(), preferences);
+    }
+}
+
+#[test]
+fn parse_lenient_syntax() {
+    let preferences: Preferences =
+        "RETURN = minimal;foo=bar, , Wait=\"5\", x-custom=\"say \\\"hi\\\"\"".parse().unwrap();
+
+    assert_eq!(
+        preferences,
+        Preferences(vec![
+            Preference::ReturnMinimal,
+            Preference::Wait(5),
+            Preference::Other { name: "x-custom".to_owned(), value: Some("say \"hi\"".to_owned()) },
+        ])
+    );
+    assert!(preferences.contains(&Preference::Wait(5)));
+    assert!(!preferences.contains(&Preference::RespondAsync));
+}
+
+#[test]
+fn parse_invalid_preferences() {
+    for invalid in &["=minimal", "wait=soon", "foo=\"unterminated", "foo=a b", "a b"] {
+        assert!(invalid.parse::<Preferences>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn prefer_request_header() {
+    let req = Request { prefer: Some(Preference::ReturnMinimal.into()), name: "foo".to_owned() };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.headers()["prefer"], "return=minimal");
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.prefer, Some(Preferences(vec![Preference::ReturnMinimal])));
+}
+
+#[test]
+fn preference_applied_response_header() {
+    let http_res = http::Response::builder()
+        .header("Preference-Applied", "return=minimal")
+        .body(b"{}".to_vec())
+        .unwrap();
+    let res = Response::try_from(http_res).unwrap();
+
+    let applied = res.preference_applied.unwrap();
+    assert!(applied.contains(&Preference::ReturnMinimal));
+
+    let http_res = http::Response::builder().body(b"{}".to_vec()).unwrap();
+    assert_eq!(Response::try_from(http_res).unwrap().preference_applied, None);
+}
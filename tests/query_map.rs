@@ -0,0 +1,173 @@
+use std::{
+    collections::BTreeMap,
+    convert::{TryFrom, TryInto},
+    fmt,
+    str::FromStr,
+};
+
+use ruma_api::ruma_api;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Forward => f.write_str("f"),
+            Self::Backward => f.write_str("b"),
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f" => Ok(Self::Forward),
+            "b" => Ok(Self::Backward),
+            _ => Err(format!("unknown direction `{}`", s)),
+        }
+    }
+}
+
+mod numbers {
+    use std::collections::BTreeMap;
+
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "numbers",
+            path: "/_matrix/some/numbers",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(query_map)]
+            pub limits: BTreeMap<String, i64>,
+        }
+
+        response {}
+    }
+}
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "directions",
+        path: "/_matrix/some/directions",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query_map)]
+        pub directions: Vec<(String, Direction)>,
+    }
+
+    response {}
+}
+
+mod prefixed {
+    use std::collections::BTreeMap;
+
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "prefixed",
+            path: "/_matrix/some/prefixed",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(query)]
+            pub limit: u32,
+
+            #[ruma_api(query)]
+            pub tags: Vec<String>,
+
+            #[ruma_api(query_map, prefix = "x-extra-")]
+            pub extra: BTreeMap<String, String>,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn numeric_query_map_roundtrip() {
+    let mut limits = BTreeMap::new();
+    limits.insert("rooms".to_owned(), 10);
+    limits.insert("users".to_owned(), -1);
+    let req = numbers::Request { limits: limits.clone() };
+
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.uri().query(), Some("rooms=10&users=-1"));
+
+    let req = numbers::Request::try_from(http_req).unwrap();
+    assert_eq!(req.limits, limits);
+}
+
+#[test]
+fn enum_query_map_roundtrip() {
+    let directions =
+        vec![("a".to_owned(), Direction::Forward), ("b".to_owned(), Direction::Backward)];
+    let req = Request { directions: directions.clone() };
+
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.uri().query(), Some("a=f&b=b"));
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.directions, directions);
+}
+
+#[test]
+fn invalid_query_map_value() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/numbers?rooms=ten")
+        .body(Vec::new())
+        .unwrap();
+
+    let err = numbers::Request::try_from(http_req).unwrap_err();
+    assert!(err.to_string().contains("invalid value for query parameter `rooms`"));
+}
+
+#[test]
+fn prefixed_query_map_roundtrip() {
+    let mut extra = BTreeMap::new();
+    extra.insert("limit".to_owned(), "ignored by the server".to_owned());
+    extra.insert("trace".to_owned(), "1".to_owned());
+    let req = prefixed::Request { limit: 5, tags: vec!["a".to_owned()], extra: extra.clone() };
+
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(
+        http_req.uri().query(),
+        Some("limit=5&tags=a&x-extra-limit=ignored%20by%20the%20server&x-extra-trace=1")
+    );
+
+    let req = prefixed::Request::try_from(http_req).unwrap();
+    assert_eq!(req.limit, 5);
+    assert_eq!(req.tags, vec!["a".to_owned()]);
+    assert_eq!(req.extra, extra);
+}
+
+#[test]
+fn prefixed_query_map_ignores_other_params() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/prefixed?limit=1&other=2&x-extra-=3")
+        .body(Vec::new())
+        .unwrap();
+    let req = prefixed::Request::try_from(http_req).unwrap();
+
+    assert_eq!(req.limit, 1);
+    assert!(req.tags.is_empty());
+    assert_eq!(req.extra.into_iter().collect::<Vec<_>>(), [("".to_owned(), "3".to_owned())]);
+}
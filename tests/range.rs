@@ -0,0 +1,119 @@
+use std::convert::{TryFrom, TryInto};
+
+use http::{header::CONTENT_RANGE, StatusCode};
+use ruma_api::{
+    error::FromHttpResponseError,
+    header::{ByteRange, ContentRange},
+    ruma_api,
+};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "download",
+        path: "/_matrix/media/r0/download/:server_name/:media_id",
+        rate_limited: false,
+        requires_authentication: false,
+        status: 200,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub server_name: String,
+
+        #[ruma_api(path)]
+        pub media_id: String,
+
+        #[ruma_api(header = RANGE)]
+        pub range: Option<ByteRange>,
+    }
+
+    response {
+        #[ruma_api(header = CONTENT_RANGE)]
+        pub content_range: Option<ContentRange>,
+
+        #[ruma_api(raw_body)]
+        pub file: Vec<u8>,
+    }
+}
+
+#[test]
+fn byte_range_roundtrip() {
+    for (range, string) in &[
+        (ByteRange::Bounded(0, 1023), "bytes=0-1023"),
+        (ByteRange::FromOffset(1024), "bytes=1024-"),
+        (ByteRange::Suffix(500), "bytes=-500"),
+    ] {
+        assert_eq!(range.to_string(), *string);
+        assert_eq!(string.parse::<ByteRange>().unwrap(), *range);
+    }
+
+    for invalid in &["bytes=", "bytes=-", "bytes=5-1", "items=0-1", "bytes=a-b", "bytes=0-1,3-4"] {
+        assert!(invalid.parse::<ByteRange>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn content_range_roundtrip() {
+    let range = ContentRange { first: 0, last: 1023, complete_length: Some(146_515) };
+    assert_eq!(range.to_string(), "bytes 0-1023/146515");
+    assert_eq!("bytes 0-1023/146515".parse::<ContentRange>().unwrap(), range);
+
+    let range = ContentRange { first: 10, last: 20, complete_length: None };
+    assert_eq!(range.to_string(), "bytes 10-20/*");
+    assert_eq!("bytes 10-20/*".parse::<ContentRange>().unwrap(), range);
+
+    for invalid in &["bytes 5-1/10", "bytes 0-10/10", "bytes */10", "0-1/2"] {
+        assert!(invalid.parse::<ContentRange>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn range_request_header() {
+    let req = Request {
+        server_name: "example.org".to_owned(),
+        media_id: "abc".to_owned(),
+        range: Some(ByteRange::FromOffset(100)),
+    };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.headers()[http::header::RANGE], "bytes=100-");
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.range, Some(ByteRange::FromOffset(100)));
+}
+
+#[test]
+fn partial_content_response() {
+    let range = ContentRange { first: 0, last: 2, complete_length: Some(10) };
+    let res = Response { content_range: Some(range), file: b"abc".to_vec(), content_type: None };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+
+    assert_eq!(http_res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(http_res.headers()[CONTENT_RANGE], "bytes 0-2/10");
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.content_range, Some(range));
+    assert_eq!(res.file, b"abc");
+}
+
+#[test]
+fn full_content_response() {
+    let res = Response { content_range: None, file: b"abc".to_vec(), content_type: None };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+
+    assert_eq!(http_res.status(), StatusCode::OK);
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.content_range, None);
+}
+
+#[test]
+fn other_success_status_is_rejected() {
+    let http_res = http::Response::builder().status(StatusCode::CREATED).body(Vec::new()).unwrap();
+
+    match Response::try_from(http_res) {
+        Err(FromHttpResponseError::UnexpectedStatus(_)) => {}
+        other => panic!("expected unexpected status error, got {:?}", other),
+    }
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "rate-limit")]
+
+use std::time::Duration;
+
+use http::StatusCode;
+use ruma_api::{
+    error::{FromHttpResponseError, ServerError},
+    rate_limit::{ExponentialBackoff, RateLimitStrategy, RateLimiter},
+    ruma_api, Endpoint, Metadata,
+};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "send",
+        path: "/_matrix/some/send",
+        rate_limited: true,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {}
+}
+
+fn error(status: StatusCode, retry_after: Option<&str>) -> FromHttpResponseError {
+    let mut builder = http::Response::builder().status(status);
+    if let Some(retry_after) = retry_after {
+        builder = builder.header(http::header::RETRY_AFTER, retry_after);
+    }
+
+    FromHttpResponseError::Http(ServerError::new(builder.body(Vec::new()).unwrap()))
+}
+
+#[test]
+fn retry_after_header_is_respected() {
+    let mut limiter = RateLimiter::new(ExponentialBackoff::default());
+    let error = error(StatusCode::TOO_MANY_REQUESTS, Some("3"));
+
+    assert_eq!(limiter.retry_delay(&Request::METADATA, 0, &error), Some(Duration::from_secs(3)));
+}
+
+#[test]
+fn backoff_without_retry_after() {
+    let mut limiter = RateLimiter::new(ExponentialBackoff::default());
+    let error = error(StatusCode::TOO_MANY_REQUESTS, None);
+
+    assert_eq!(
+        limiter.retry_delay(&Request::METADATA, 0, &error),
+        Some(Duration::from_millis(500))
+    );
+    assert_eq!(limiter.retry_delay(&Request::METADATA, 2, &error), Some(Duration::from_secs(2)));
+    assert_eq!(limiter.retry_delay(&Request::METADATA, 5, &error), None);
+}
+
+#[test]
+fn only_rate_limited_endpoints_and_429_are_retried() {
+    let mut limiter = RateLimiter::new(ExponentialBackoff::default());
+    let not_rate_limited = Metadata { rate_limited: false, ..Request::METADATA };
+
+    assert_eq!(
+        limiter.retry_delay(&not_rate_limited, 0, &error(StatusCode::TOO_MANY_REQUESTS, None)),
+        None
+    );
+    assert_eq!(
+        limiter.retry_delay(&Request::METADATA, 0, &error(StatusCode::FORBIDDEN, None)),
+        None
+    );
+}
+
+#[test]
+fn custom_strategy() {
+    struct Never;
+
+    impl RateLimitStrategy for Never {
+        fn delay(&mut self, _: &Metadata, _: u32, _: Option<Duration>) -> Option<Duration> {
+            None
+        }
+    }
+
+    let mut limiter = RateLimiter::new(Never);
+    let error = error(StatusCode::TOO_MANY_REQUESTS, Some("1"));
+
+    assert_eq!(limiter.retry_delay(&Request::METADATA, 0, &error), None);
+}
@@ -0,0 +1,67 @@
+//! Raw bodies are moved between the request and response types and `http` types, not copied.
+
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "upload",
+        path: "/_matrix/some/upload",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(raw_body)]
+        pub file: Vec<u8>,
+    }
+
+    response {
+        #[ruma_api(raw_body)]
+        pub file: Vec<u8>,
+    }
+}
+
+#[test]
+fn outgoing_request_body_is_moved() {
+    let file = b"some file contents".to_vec();
+    let ptr = file.as_ptr();
+
+    let http_req: http::Request<Vec<u8>> = Request { file }.try_into().unwrap();
+    assert_eq!(http_req.body().as_ptr(), ptr);
+}
+
+#[test]
+fn incoming_request_body_is_moved() {
+    let http_req = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/upload")
+        .body(b"some file contents".to_vec())
+        .unwrap();
+    let ptr = http_req.body().as_ptr();
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.file.as_ptr(), ptr);
+}
+
+#[test]
+fn outgoing_response_body_is_moved() {
+    let file = b"some file contents".to_vec();
+    let ptr = file.as_ptr();
+
+    let http_res: http::Response<Vec<u8>> =
+        Response { file, content_type: None }.try_into().unwrap();
+    assert_eq!(http_res.body().as_ptr(), ptr);
+}
+
+#[test]
+fn incoming_response_body_is_moved() {
+    let http_res = http::Response::new(b"some file contents".to_vec());
+    let ptr = http_res.body().as_ptr();
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.file.as_ptr(), ptr);
+}
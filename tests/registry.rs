@@ -0,0 +1,111 @@
+use ruma_api::{registry::Registry, Endpoint};
+
+mod get_state {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "get_state",
+            path: "/_matrix/some/rooms/:room_id/state/:event_type",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room_id: String,
+            #[ruma_api(path)]
+            pub event_type: String,
+        }
+
+        response {}
+    }
+}
+
+mod send_state {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: PUT,
+            name: "send_state",
+            path: "/_matrix/some/rooms/:room_id/state/:event_type",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room_id: String,
+            #[ruma_api(path)]
+            pub event_type: String,
+        }
+
+        response {}
+    }
+}
+
+mod versions {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "versions",
+            path: "/_matrix/some/versions",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {}
+    }
+}
+
+fn registry() -> Registry<u8> {
+    let mut registry = Registry::new();
+    registry.register(get_state::Request::METADATA, 1).unwrap();
+    registry.register(send_state::Request::METADATA, 2).unwrap();
+    registry.register(versions::Request::METADATA, 3).unwrap();
+    registry
+}
+
+#[test]
+fn find_by_method_and_path() {
+    let registry = registry();
+
+    let (metadata, handler) =
+        registry.find(&http::Method::PUT, "/_matrix/some/rooms/!a:b/state/m.room.name").unwrap();
+    assert_eq!(metadata.name, "send_state");
+    assert_eq!(*handler, 2);
+
+    let (metadata, _) = registry.find(&http::Method::GET, "/_matrix/some/versions").unwrap();
+    assert_eq!(metadata.name, "versions");
+
+    assert!(registry.find(&http::Method::POST, "/_matrix/some/versions").is_none());
+    assert!(registry.find(&http::Method::GET, "/_matrix/some/rooms//state/m.room.name").is_none());
+    assert!(registry.find(&http::Method::GET, "/_matrix/some/versions/extra").is_none());
+}
+
+#[test]
+fn duplicate_route() {
+    let mut registry = registry();
+
+    let existing = registry.register(get_state::Request::METADATA, 4).unwrap_err();
+    assert_eq!(existing.name, "get_state");
+    assert_eq!(registry.routes().count(), 3);
+}
+
+#[test]
+fn routes() {
+    let registry = registry();
+    let routes: Vec<_> = registry.routes().collect();
+
+    assert_eq!(
+        routes,
+        vec![
+            (&http::Method::GET, "/_matrix/some/rooms/:room_id/state/:event_type"),
+            (&http::Method::PUT, "/_matrix/some/rooms/:room_id/state/:event_type"),
+            (&http::Method::GET, "/_matrix/some/versions"),
+        ]
+    );
+}
@@ -0,0 +1,91 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "search",
+        path: "/_matrix/some/search",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query)]
+        pub filter: Vec<String>,
+
+        #[ruma_api(query)]
+        pub limit: Option<u32>,
+    }
+
+    response {}
+}
+
+mod only_repeated {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "members",
+            path: "/_matrix/some/members",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(query)]
+            pub membership: Vec<u8>,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn repeated_query_param() {
+    let req = Request { filter: vec!["a".to_owned(), "b c".to_owned()], limit: Some(10) };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().query(), Some("limit=10&filter=a&filter=b%20c"));
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.filter, vec!["a".to_owned(), "b c".to_owned()]);
+    assert_eq!(req.limit, Some(10));
+}
+
+#[test]
+fn empty_repeated_query_param() {
+    let req = Request { filter: Vec::new(), limit: Some(10) };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().query(), Some("limit=10"));
+
+    let req = Request::try_from(http_req).unwrap();
+    assert!(req.filter.is_empty());
+}
+
+#[test]
+fn only_repeated_query_param() {
+    let req = only_repeated::Request { membership: vec![1, 2] };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().query(), Some("membership=1&membership=2"));
+
+    let req = only_repeated::Request { membership: Vec::new() };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.uri().query(), None);
+}
+
+#[test]
+fn invalid_repeated_query_value() {
+    let http_req = http::Request::builder()
+        .uri("http://localhost/_matrix/some/members?membership=1&membership=x")
+        .body(Vec::new())
+        .unwrap();
+
+    let err = only_repeated::Request::try_from(http_req).unwrap_err();
+    assert!(err.to_string().contains("invalid value for query parameter `membership`"));
+}
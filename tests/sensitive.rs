@@ -0,0 +1,94 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "login",
+        path: "/_matrix/some/login",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        pub user: String,
+
+        #[ruma_api(sensitive)]
+        pub password: String,
+
+        #[ruma_api(sensitive)]
+        #[ruma_api(header = AUTHORIZATION)]
+        pub authorization: String,
+    }
+
+    response {
+        #[ruma_api(sensitive)]
+        pub access_token: String,
+
+        pub device_id: String,
+    }
+}
+
+#[test]
+fn request_debug_redacts_sensitive_fields() {
+    let req = Request {
+        user: "alice".to_owned(),
+        password: "hunter2".to_owned(),
+        authorization: "Bearer secret".to_owned(),
+    };
+    let debug = format!("{:?}", req);
+
+    assert_eq!(
+        debug,
+        "Request { user: \"alice\", password: <redacted>, authorization: <redacted> }"
+    );
+}
+
+#[test]
+fn response_debug_redacts_sensitive_fields() {
+    let res = Response { access_token: "secret".to_owned(), device_id: "ABCDEF".to_owned() };
+    let debug = format!("{:?}", res);
+
+    assert!(!debug.contains("secret"));
+    assert!(debug.contains("ABCDEF"));
+}
+
+mod incoming {
+    use ruma_api::ruma_api;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct Secret {
+        pub value: String,
+    }
+
+    pub type IncomingSecret = Secret;
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "incoming",
+            path: "/_matrix/some/incoming",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(sensitive)]
+            #[wrap_incoming]
+            pub secret: Secret,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn incoming_request_debug_redacts_sensitive_fields() {
+    let req = incoming::IncomingRequest {
+        secret: ruma_api::exports::serde_json::from_str("{ \"value\": \"hunter2\" }").unwrap(),
+    };
+
+    assert!(!format!("{:?}", req).contains("hunter2"));
+}
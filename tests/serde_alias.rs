@@ -0,0 +1,62 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "serde_alias",
+        path: "/_matrix/some/alias/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[serde(rename = "m.new_name", alias = "org.example.old_name", alias = "old_name")]
+        pub name: String,
+    }
+
+    response {
+        #[serde(rename = "m.count", alias = "count")]
+        pub count: u32,
+    }
+}
+
+fn http_request(body: &str) -> http::Request<Vec<u8>> {
+    http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/_matrix/some/alias/endpoint")
+        .body(body.as_bytes().to_vec())
+        .unwrap()
+}
+
+#[test]
+fn request_serializes_canonical_name() {
+    let req = Request { name: "foo".to_owned() };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.body(), br#"{"m.new_name":"foo"}"#);
+}
+
+#[test]
+fn request_deserializes_canonical_name_and_aliases() {
+    for body in
+        [r#"{"m.new_name":"foo"}"#, r#"{"org.example.old_name":"foo"}"#, r#"{"old_name":"foo"}"#]
+    {
+        let req = Request::try_from(http_request(body)).unwrap();
+        assert_eq!(req.name, "foo");
+    }
+}
+
+#[test]
+fn response_deserializes_canonical_name_and_alias() {
+    for body in [r#"{"m.count":3}"#, r#"{"count":3}"#] {
+        let http_res = http::Response::new(body.as_bytes().to_vec());
+        let res = Response::try_from(http_res).unwrap();
+        assert_eq!(res.count, 3);
+    }
+
+    let http_res: http::Response<Vec<u8>> = Response { count: 3 }.try_into().unwrap();
+    assert_eq!(http_res.body(), br#"{"m.count":3}"#);
+}
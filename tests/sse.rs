@@ -0,0 +1,97 @@
+#![cfg(feature = "sse")]
+
+use std::{convert::TryFrom, time::Duration};
+
+use ruma_api::{
+    ruma_api,
+    sse::{self, Decoder, Event},
+};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "sync_stream",
+        path: "/_matrix/some/sync/stream",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {}
+
+    response {
+        #[ruma_api(raw_body)]
+        pub events: Vec<u8>,
+    }
+}
+
+const BODY: &[u8] = b": keep-alive\n\nevent: sync\nid: 1\ndata: {\"a\":1}\n\nretry: 3000\r\ndata: first\r\ndata: second\r\n\r\ndata:last";
+
+#[test]
+fn parse_response_body() {
+    let http_response = http::Response::builder()
+        .header(http::header::CONTENT_TYPE, "text/event-stream")
+        .body(BODY.to_vec())
+        .unwrap();
+    let response = Response::try_from(http_response).unwrap();
+
+    assert_eq!(
+        sse::parse(&response.events),
+        vec![
+            Event {
+                event: Some("sync".to_owned()),
+                data: "{\"a\":1}".to_owned(),
+                id: Some("1".to_owned()),
+                retry: None,
+            },
+            Event {
+                data: "first\nsecond".to_owned(),
+                retry: Some(Duration::from_millis(3000)),
+                ..Event::default()
+            },
+            Event { data: "last".to_owned(), ..Event::default() },
+        ]
+    );
+}
+
+#[test]
+fn decoder_yields_events_as_chunks_arrive() {
+    let mut decoder = Decoder::new();
+
+    decoder.push(b"data: one\n");
+    assert_eq!(decoder.next_event(), None);
+
+    decoder.push(b"\ndata: tw");
+    assert_eq!(decoder.next_event().unwrap().data, "one");
+    assert_eq!(decoder.next_event(), None);
+
+    decoder.push(b"o\r");
+    decoder.push(b"\n\r\n");
+    assert_eq!(decoder.next_event().unwrap().data, "two");
+    assert_eq!(decoder.finish(), None);
+}
+
+#[test]
+fn lone_cr_is_a_line_ending() {
+    let events = sse::parse(b"data: a\rb\rdata: c\r\rdata: d");
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].data, "a\nc");
+    assert_eq!(events[1].data, "d");
+}
+
+#[test]
+fn crlf_split_across_chunks() {
+    let mut decoder = Decoder::new();
+
+    decoder.push(b"data: one\r");
+    decoder.push(b"\ndata: two\r");
+    assert_eq!(decoder.next_event(), None);
+
+    decoder.push(b"\n\r");
+    assert_eq!(decoder.next_event().unwrap().data, "one\ntwo");
+
+    decoder.push(b"\ndata: three\r\n\r\n");
+    assert_eq!(decoder.next_event().unwrap().data, "three");
+    assert_eq!(decoder.finish(), None);
+}
@@ -0,0 +1,133 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::{
+    error::{FromHttpResponseError, TransportError, TransportErrorKind},
+    Endpoint,
+};
+
+mod no_content {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: PUT,
+            name: "no_content",
+            path: "/_matrix/some/no_content/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+            status: 204,
+        }
+
+        request {
+            pub foo: String,
+        }
+
+        response {}
+    }
+}
+
+mod default_status {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "default_status",
+            path: "/_matrix/some/default/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {}
+    }
+}
+
+mod with_body {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "with_body",
+            path: "/_matrix/some/body/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {
+            pub foo: String,
+        }
+    }
+}
+
+#[test]
+fn metadata_status() {
+    assert_eq!(no_content::Request::METADATA.status, http::StatusCode::NO_CONTENT);
+    assert_eq!(default_status::Request::METADATA.status, http::StatusCode::OK);
+}
+
+#[test]
+fn outgoing_response_uses_declared_status() {
+    let http_res: http::Response<Vec<u8>> = no_content::Response.try_into().unwrap();
+
+    assert_eq!(http_res.status(), http::StatusCode::NO_CONTENT);
+    assert!(http_res.body().is_empty());
+}
+
+#[test]
+fn no_content_response_with_empty_body() {
+    let http_res =
+        http::Response::builder().status(http::StatusCode::NO_CONTENT).body(Vec::new()).unwrap();
+
+    assert!(no_content::Response::try_from(http_res).is_ok());
+}
+
+#[test]
+fn unexpected_success_status() {
+    let http_res =
+        http::Response::builder().status(http::StatusCode::OK).body(b"{}".to_vec()).unwrap();
+
+    match no_content::Response::try_from(http_res) {
+        Err(FromHttpResponseError::UnexpectedStatus(err)) => {
+            assert_eq!(err.expected(), http::StatusCode::NO_CONTENT);
+            assert_eq!(err.into_raw_response().status(), http::StatusCode::OK);
+        }
+        other => panic!("expected UnexpectedStatus error, got {:?}", other),
+    }
+}
+
+#[test]
+fn any_success_status_without_declared_status() {
+    let http_res =
+        http::Response::builder().status(http::StatusCode::ACCEPTED).body(b"{}".to_vec()).unwrap();
+
+    assert!(default_status::Response::try_from(http_res).is_ok());
+}
+
+#[test]
+fn error_status() {
+    let http_res = http::Response::builder()
+        .status(http::StatusCode::BAD_GATEWAY)
+        .body(b"Bad Gateway".to_vec())
+        .unwrap();
+    let err = default_status::Response::try_from(http_res).unwrap_err();
+    assert_eq!(err.status(), Some(http::StatusCode::BAD_GATEWAY));
+
+    let http_res =
+        http::Response::builder().status(http::StatusCode::OK).body(b"{}".to_vec()).unwrap();
+    let err = with_body::Response::try_from(http_res).unwrap_err();
+    assert!(matches!(err, FromHttpResponseError::Deserialization(_)));
+    assert_eq!(err.status(), Some(http::StatusCode::OK));
+
+    let http_res =
+        http::Response::builder().status(http::StatusCode::OK).body(b"{}".to_vec()).unwrap();
+    let err = no_content::Response::try_from(http_res).unwrap_err();
+    assert_eq!(err.status(), Some(http::StatusCode::OK));
+
+    let err = FromHttpResponseError::<ruma_api::MatrixError>::from(TransportError::new(
+        TransportErrorKind::Timeout,
+        "timed out",
+    ));
+    assert_eq!(err.status(), None);
+}
@@ -0,0 +1,142 @@
+#![cfg(feature = "test-helpers")]
+
+use std::convert::TryFrom;
+
+use ruma_api::{
+    error::FromHttpResponseError,
+    ruma_api,
+    test_helpers::{
+        assert_parses_fixture, mock_error_response, mock_http_response,
+        mock_http_response_with_status,
+    },
+    MatrixErrorKind,
+};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "some_endpoint",
+        path: "/_matrix/some/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+        status: 200,
+    }
+
+    request {}
+
+    response {
+        pub value: String,
+    }
+}
+
+mod send_message {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: PUT,
+            name: "send_message",
+            path: "/_matrix/some/rooms/:room_id/send/:txn_id",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room_id: String,
+
+            #[ruma_api(path)]
+            pub txn_id: String,
+
+            #[ruma_api(query)]
+            pub format: Option<String>,
+
+            #[ruma_api(query)]
+            pub limit: u32,
+
+            pub body: String,
+            pub msgtype: String,
+        }
+
+        response {
+            pub event_id: String,
+        }
+    }
+}
+
+#[test]
+fn mock_response_parses() {
+    let http_response = mock_http_response::<Request>(Response { value: "foo".to_owned() });
+
+    let response = Response::try_from(http_response).unwrap();
+    assert_eq!(response.value, "foo");
+}
+
+#[test]
+fn mock_response_with_status() {
+    let http_response = mock_http_response_with_status::<Request>(
+        Response { value: "foo".to_owned() },
+        http::StatusCode::ACCEPTED,
+    );
+
+    match Response::try_from(http_response) {
+        Err(FromHttpResponseError::UnexpectedStatus(err)) => {
+            assert_eq!(err.expected(), http::StatusCode::OK)
+        }
+        other => panic!("expected unexpected status error, got {:?}", other),
+    }
+}
+
+#[test]
+fn mock_error() {
+    let http_response = mock_error_response(
+        MatrixErrorKind::NotFound,
+        http::StatusCode::NOT_FOUND,
+        "no such thing",
+    );
+
+    match Response::try_from(http_response) {
+        Err(FromHttpResponseError::Http(err)) => {
+            let error = err.to_matrix_error();
+            assert_eq!(error.kind, MatrixErrorKind::NotFound);
+            assert_eq!(error.status_code, http::StatusCode::NOT_FOUND);
+            assert_eq!(error.message, "no such thing");
+        }
+        other => panic!("expected server error, got {:?}", other),
+    }
+}
+
+#[test]
+fn request_fixture() {
+    assert_parses_fixture::<send_message::Request>(
+        b"PUT /_matrix/some/rooms/!room%3Aexample.org/send/1?limit=10&format=plain HTTP/1.1\r\n\
+          Host: example.org\r\n\
+          Authorization: Bearer secret\r\n\
+          Content-Type: application/json; charset=utf-8\r\n\
+          \r\n\
+          { \"msgtype\": \"m.text\", \"body\": \"Hello\" }",
+    );
+}
+
+#[test]
+fn response_fixture() {
+    assert_parses_fixture::<send_message::Request>(
+        b"HTTP/1.1 200 OK\nContent-Type: application/json\n\n{\"event_id\":\"$abc\"}",
+    );
+}
+
+#[test]
+#[should_panic(expected = "body differs from fixture")]
+fn request_fixture_with_unknown_field() {
+    assert_parses_fixture::<send_message::Request>(
+        b"PUT /_matrix/some/rooms/!room%3Aexample.org/send/1?limit=10 HTTP/1.1\n\
+          \n\
+          { \"msgtype\": \"m.text\", \"body\": \"Hello\", \"extra\": true }",
+    );
+}
+
+#[test]
+#[should_panic(expected = "failed to parse response fixture")]
+fn invalid_response_fixture() {
+    assert_parses_fixture::<send_message::Request>(b"HTTP/1.1 200 OK\n\n{}");
+}
@@ -0,0 +1,46 @@
+use std::convert::{TryFrom, TryInto};
+
+use http::header::CONTENT_TYPE;
+use ruma_api::{error::FromHttpResponseError, ruma_api};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "version",
+        path: "/_matrix/some/version",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        #[ruma_api(text_body)]
+        pub version: String,
+    }
+}
+
+#[test]
+fn text_body_roundtrip() {
+    let res = Response { version: "synapse 1.0 ✓".to_owned() };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+
+    assert_eq!(http_res.headers()[CONTENT_TYPE], "text/plain; charset=utf-8");
+    assert_eq!(http_res.body(), "synapse 1.0 ✓".as_bytes());
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.version, "synapse 1.0 ✓");
+}
+
+#[test]
+fn invalid_utf8_is_an_error() {
+    let http_res = http::Response::builder().body(vec![b'v', 0xff, 0xfe]).unwrap();
+
+    match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Deserialization(err)) => {
+            assert!(err.to_string().contains("not valid UTF-8"));
+        }
+        other => panic!("expected deserialization error, got {:?}", other),
+    }
+}
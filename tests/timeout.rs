@@ -0,0 +1,62 @@
+use std::{convert::TryInto, time::Duration};
+
+use ruma_api::{ruma_api, Endpoint};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "sync",
+        path: "/_matrix/some/sync",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(query)]
+        pub since: Option<String>,
+
+        #[ruma_api(query, timeout_hint)]
+        pub timeout: Option<u64>,
+    }
+
+    response {}
+}
+
+mod no_hint {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "no_hint",
+            path: "/_matrix/some/endpoint",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(query)]
+            pub timeout: Option<u64>,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn suggested_timeout_from_hint_field() {
+    let req = Request { since: None, timeout: Some(30_000) };
+    assert_eq!(req.suggested_timeout(), Some(Duration::from_secs(30)));
+
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.uri().query(), Some("timeout=30000"));
+
+    let req = Request { since: None, timeout: None };
+    assert_eq!(req.suggested_timeout(), None);
+}
+
+#[test]
+fn no_suggested_timeout_without_hint_field() {
+    let req = no_hint::Request { timeout: Some(30_000) };
+    assert_eq!(req.suggested_timeout(), None);
+}
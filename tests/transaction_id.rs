@@ -0,0 +1,104 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::{ruma_api, Endpoint};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: PUT,
+        name: "send",
+        path: "/_matrix/some/:room_id/send/:txn_id",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+
+        #[ruma_api(path, transaction_id)]
+        pub txn_id: Option<String>,
+
+        pub body: String,
+    }
+
+    response {}
+}
+
+fn request(txn_id: Option<&str>) -> Request {
+    Request {
+        room_id: "!room:localhost".to_owned(),
+        txn_id: txn_id.map(ToOwned::to_owned),
+        body: "hello".to_owned(),
+    }
+}
+
+fn txn_id_of(http_req: &http::Request<Vec<u8>>) -> String {
+    http_req.uri().path().rsplit('/').next().unwrap().to_owned()
+}
+
+#[test]
+fn explicit_transaction_id_is_used() {
+    let http_req: http::Request<Vec<u8>> = request(Some("abc")).try_into().unwrap();
+    assert_eq!(http_req.uri().path(), "/_matrix/some/!room:localhost/send/abc");
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.txn_id.as_deref(), Some("abc"));
+}
+
+#[test]
+fn missing_transaction_id_is_rejected() {
+    let base_url = ruma_api::exports::url::Url::parse("https://example.org").unwrap();
+    let req = request(None);
+
+    assert!(http::Request::<Vec<u8>>::try_from(req.clone()).is_err());
+    assert!(req.url(&base_url).is_err());
+    assert!(req.path_with_params().is_err());
+    assert!(req.try_into_http_request_with_base_url(&base_url).is_err());
+}
+
+#[test]
+fn ensure_transaction_id_is_reused_on_retry() {
+    let mut req = request(None);
+    let txn_id = req.ensure_transaction_id().unwrap().to_owned();
+    assert_eq!(req.txn_id.as_deref(), Some(txn_id.as_str()));
+    assert_eq!(req.ensure_transaction_id(), Some(txn_id.as_str()));
+
+    let first: http::Request<Vec<u8>> = req.clone().try_into().unwrap();
+    let second: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(txn_id_of(&first), txn_id);
+    assert_eq!(txn_id_of(&second), txn_id);
+}
+
+#[test]
+fn ensure_transaction_id_keeps_explicit_id() {
+    let mut req = request(Some("abc"));
+    assert_eq!(req.ensure_transaction_id(), Some("abc"));
+}
+
+#[test]
+fn ensured_transaction_id_with_base_url() {
+    let base_url = ruma_api::exports::url::Url::parse("https://example.org/matrix").unwrap();
+    let mut req = request(None);
+    let txn_id = req.ensure_transaction_id().unwrap().to_owned();
+    let http_req = req.try_into_http_request_with_base_url(&base_url).unwrap();
+
+    let uri = http_req.uri().to_string();
+    assert!(uri.starts_with("https://example.org/matrix/_matrix/some/!room:localhost/send/"));
+    assert_eq!(txn_id_of(&http_req), txn_id);
+}
+
+#[test]
+fn conversions_agree_after_ensure_transaction_id() {
+    let mut req = request(None);
+    req.ensure_transaction_id();
+
+    let target = req.path_with_params().unwrap();
+    assert_eq!(req.path_with_params().unwrap(), target);
+
+    let base_url = ruma_api::exports::url::Url::parse("https://example.org").unwrap();
+    assert_eq!(req.url(&base_url).unwrap().path(), target);
+
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    assert_eq!(http_req.uri().path(), target);
+}
@@ -0,0 +1,29 @@
+use std::{error::Error as _, io};
+
+use ruma_api::error::{FromHttpResponseError, TransportError, TransportErrorKind};
+
+#[test]
+fn timeout_error() {
+    let io_err = io::Error::new(io::ErrorKind::TimedOut, "deadline elapsed");
+    let err: FromHttpResponseError =
+        TransportError::new(TransportErrorKind::Timeout, io_err).into();
+
+    let transport_err = match &err {
+        FromHttpResponseError::Transport(err) => err,
+        other => panic!("expected transport error, got {:?}", other),
+    };
+
+    assert!(transport_err.is_timeout());
+    assert_eq!(transport_err.kind(), TransportErrorKind::Timeout);
+    assert_eq!(transport_err.source().unwrap().to_string(), "deadline elapsed");
+    assert_eq!(err.to_string(), "no response received: request timed out: deadline elapsed");
+}
+
+#[test]
+fn connect_error() {
+    let err = TransportError::new(TransportErrorKind::Connect, "connection refused");
+
+    assert!(!err.is_timeout());
+    assert_eq!(err.to_string(), "failed to connect: connection refused");
+    assert_eq!(err.into_inner().to_string(), "connection refused");
+}
@@ -0,0 +1,77 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "filter",
+        path: "/_matrix/some/filter",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query)]
+        pub since: Option<Option<String>>,
+
+        #[ruma_api(query)]
+        pub limit: Option<Option<u32>>,
+    }
+
+    response {}
+}
+
+fn query_of(req: Request) -> Option<String> {
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    http_req.uri().query().map(ToOwned::to_owned)
+}
+
+fn parse(query: &str) -> Request {
+    let http_req = http::Request::builder()
+        .uri(format!("http://localhost/_matrix/some/filter?{}", query))
+        .body(Vec::new())
+        .unwrap();
+
+    Request::try_from(http_req).unwrap()
+}
+
+#[test]
+fn tri_state_query_serialization() {
+    assert_eq!(query_of(Request { since: None, limit: None }), None);
+    assert_eq!(query_of(Request { since: Some(None), limit: None }), Some("since=".to_owned()));
+    assert_eq!(
+        query_of(Request { since: Some(Some("s72594".to_owned())), limit: Some(Some(10)) }),
+        Some("since=s72594&limit=10".to_owned())
+    );
+}
+
+#[test]
+fn tri_state_query_deserialization() {
+    let req = parse("");
+    assert_eq!(req.since, None);
+    assert_eq!(req.limit, None);
+
+    let req = parse("since=&limit=");
+    assert_eq!(req.since, Some(None));
+    assert_eq!(req.limit, Some(None));
+
+    let req = parse("since=s72594&limit=10");
+    assert_eq!(req.since, Some(Some("s72594".to_owned())));
+    assert_eq!(req.limit, Some(Some(10)));
+}
+
+#[test]
+fn tri_state_query_roundtrip() {
+    for (since, limit) in
+        &[(None, Some(Some(5))), (Some(None), None), (Some(Some("a b")), Some(None))]
+    {
+        let req = Request { since: since.map(|s| s.map(ToOwned::to_owned)), limit: *limit };
+        let http_req: http::Request<Vec<u8>> = req.clone().try_into().unwrap();
+        let parsed = Request::try_from(http_req).unwrap();
+
+        assert_eq!(parsed.since, req.since);
+        assert_eq!(parsed.limit, req.limit);
+    }
+}
@@ -0,0 +1,104 @@
+use ruma_api::{auth::AccessToken, ruma_api};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: PUT,
+        name: "send",
+        path: "/_matrix/some/send/:room_id",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+
+        #[ruma_api(query)]
+        pub limit: u32,
+
+        pub body: String,
+    }
+
+    response {}
+}
+
+mod unauthenticated {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "versions",
+            path: "/_matrix/client/versions",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {}
+    }
+}
+
+fn request() -> Request {
+    Request { room_id: "!room:example.org".to_owned(), limit: 5, body: "hi".to_owned() }
+}
+
+#[test]
+fn http_request_with_base_url_and_token() {
+    let http_req = request()
+        .try_into_http_request(
+            "https://example.org:8448/matrix/",
+            Some(&AccessToken::new("secret")),
+        )
+        .unwrap();
+
+    assert_eq!(http_req.method(), http::Method::PUT);
+    assert_eq!(
+        http_req.uri().to_string(),
+        "https://example.org:8448/matrix/_matrix/some/send/!room:example.org?limit=5"
+    );
+    assert_eq!(http_req.headers()[http::header::AUTHORIZATION], "Bearer secret");
+    assert_eq!(http_req.body(), br#"{"body":"hi"}"#);
+}
+
+#[test]
+fn http_request_missing_token() {
+    let err = request().try_into_http_request("https://example.org", None).unwrap_err();
+
+    assert_eq!(err.to_string(), "This endpoint has to be accessed with an access token");
+}
+
+#[test]
+fn http_request_invalid_base_url() {
+    let err = request()
+        .try_into_http_request("not a url", Some(&AccessToken::new("secret")))
+        .unwrap_err();
+
+    assert!(err.to_string().starts_with("Invalid base URL: "));
+}
+
+#[test]
+fn http_request_without_authentication() {
+    let http_req = unauthenticated::Request {}
+        .try_into_http_request("https://example.org", Some(&AccessToken::new("secret")))
+        .unwrap();
+
+    assert_eq!(http_req.uri().to_string(), "https://example.org/_matrix/client/versions");
+    assert!(http_req.headers().get(http::header::AUTHORIZATION).is_none());
+
+    assert!(unauthenticated::Request {}.try_into_http_request("https://example.org", None).is_ok());
+}
+
+#[test]
+fn http_request_with_absolute_uri() {
+    let base_url = url::Url::parse("https://example.org").unwrap();
+    let http_req = request().try_into_http_request_with_base_url(&base_url).unwrap();
+
+    assert_eq!(http_req.uri().scheme_str(), Some("https"));
+    assert_eq!(http_req.uri().authority().unwrap(), "example.org");
+    assert_eq!(http_req.uri().path(), "/_matrix/some/send/!room:example.org");
+    assert_eq!(http_req.uri().query(), Some("limit=5"));
+    // Unlike `try_into_http_request`, this doesn't deal with authentication.
+    assert!(http_req.headers().get(http::header::AUTHORIZATION).is_none());
+}
@@ -0,0 +1,115 @@
+use std::convert::{TryFrom, TryInto};
+
+use ruma_api::{exports::url::Url, ruma_api};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "create",
+        path: "/_matrix/some/create",
+        rate_limited: false,
+        requires_authentication: false,
+        status: 201,
+    }
+
+    request {
+        #[ruma_api(header = REFERER)]
+        pub referer: Option<http::Uri>,
+    }
+
+    response {
+        #[ruma_api(header = LOCATION)]
+        pub location: Url,
+
+        #[ruma_api(header = CONTENT_LOCATION)]
+        pub content_location: Option<Url>,
+    }
+}
+
+#[test]
+fn location_header_roundtrip() {
+    let res = Response {
+        location: Url::parse("https://example.org/_matrix/some/resource/1").unwrap(),
+        content_location: None,
+    };
+    let http_res: http::Response<Vec<u8>> = res.try_into().unwrap();
+
+    assert_eq!(
+        http_res.headers().get(http::header::LOCATION).unwrap(),
+        "https://example.org/_matrix/some/resource/1"
+    );
+
+    let res = Response::try_from(http_res).unwrap();
+    assert_eq!(res.location.path(), "/_matrix/some/resource/1");
+    assert_eq!(res.content_location, None);
+}
+
+#[test]
+fn malformed_location_header() {
+    let http_res = http::Response::builder()
+        .status(201)
+        .header(http::header::LOCATION, "not a url")
+        .body(Vec::new())
+        .unwrap();
+
+    let err = Response::try_from(http_res).unwrap_err();
+    assert!(err.to_string().contains("invalid value for header `location`"));
+}
+
+#[test]
+fn missing_location_header() {
+    let http_res = http::Response::builder().status(201).body(Vec::new()).unwrap();
+
+    let err = Response::try_from(http_res).unwrap_err();
+    assert!(err.to_string().contains("missing header `location`"));
+}
+
+#[test]
+fn uri_request_header_roundtrip() {
+    let req = Request { referer: Some("https://example.org/app".parse().unwrap()) };
+    let http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    assert_eq!(http_req.headers().get(http::header::REFERER).unwrap(), "https://example.org/app");
+
+    let req = Request::try_from(http_req).unwrap();
+    assert_eq!(req.referer.unwrap().host(), Some("example.org"));
+}
+
+mod plain {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "plain",
+            path: "/_matrix/some/plain",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {
+            #[ruma_api(header = CONTENT_DISPOSITION)]
+            pub content_disposition: String,
+
+            #[ruma_api(header = CONTENT_LANGUAGE)]
+            pub content_language: Option<String>,
+        }
+    }
+}
+
+#[test]
+fn invalid_response_header_value_is_an_error() {
+    let res = plain::Response {
+        content_disposition: "inline\r\nX-Injected: 1".to_owned(),
+        content_language: None,
+    };
+    assert!(http::Response::<Vec<u8>>::try_from(res).is_err());
+
+    let res = plain::Response {
+        content_disposition: "inline".to_owned(),
+        content_language: Some("en\n".to_owned()),
+    };
+    assert!(http::Response::<Vec<u8>>::try_from(res).is_err());
+}
@@ -0,0 +1,40 @@
+use std::convert::TryFrom;
+
+use ruma_api::{
+    error::{FromHttpRequestError, IntoHttpError},
+    Outgoing,
+};
+
+pub struct Request;
+
+impl Outgoing for Request {
+    type Incoming = Self;
+}
+
+impl TryFrom<Request> for http::Request<Vec<u8>> {
+    type Error = IntoHttpError;
+
+    fn try_from(_: Request) -> Result<Self, Self::Error> {
+        Ok(http::Request::new(Vec::new()))
+    }
+}
+
+impl TryFrom<http::Request<Vec<u8>>> for Request {
+    type Error = FromHttpRequestError;
+
+    fn try_from(_: http::Request<Vec<u8>>) -> Result<Self, Self::Error> {
+        Ok(Request)
+    }
+}
+
+pub struct Response;
+
+impl Outgoing for Response {
+    type Incoming = Self;
+}
+
+// Missing: the conversions between `Response` and `http::Response<Vec<u8>>`.
+
+ruma_api::assert_endpoint!(Request, Response);
+
+fn main() {}
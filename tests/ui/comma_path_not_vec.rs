@@ -0,0 +1,21 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "comma_path_not_vec",
+        path: "/_matrix/some/endpoint/:user_id",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path, comma)]
+        pub user_id: String,
+    }
+
+    response {}
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "multiple_newtype_bodies",
+        path: "/_matrix/some/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(body)]
+        pub first: Vec<u32>,
+        #[ruma_api(raw_body)]
+        pub second: Vec<u8>,
+    }
+
+    response {}
+}
+
+fn main() {}
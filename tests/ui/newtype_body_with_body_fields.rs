@@ -0,0 +1,23 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "newtype_body_with_body_fields",
+        path: "/_matrix/some/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        #[ruma_api(body)]
+        pub content: Vec<u32>,
+        pub foo: String,
+        pub bar: u32,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "path_default_not_trailing",
+        path: "/_matrix/some/:version/endpoint/:id",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path, path_default = "v1")]
+        pub version: Option<String>,
+
+        #[ruma_api(path)]
+        pub id: String,
+    }
+
+    response {}
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "query_map_prefix_collision",
+        path: "/_matrix/some/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query)]
+        pub x_limit: u32,
+
+        #[ruma_api(query_map, prefix = "x_")]
+        pub extra: std::collections::BTreeMap<String, String>,
+    }
+
+    response {}
+}
+
+fn main() {}
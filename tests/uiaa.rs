@@ -0,0 +1,111 @@
+use std::convert::{TryFrom, TryInto};
+
+use http::StatusCode;
+use ruma_api::{
+    error::FromHttpResponseError,
+    ruma_api,
+    uiaa::{AuthFlow, UiaaResponse},
+    MatrixErrorKind,
+};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "register",
+        path: "/_matrix/some/register",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        pub username: String,
+    }
+
+    response {
+        pub user_id: String,
+    }
+}
+
+fn http_response(status: StatusCode, body: &[u8]) -> http::Response<Vec<u8>> {
+    http::Response::builder().status(status).body(body.to_vec()).unwrap()
+}
+
+#[test]
+fn uiaa_response() {
+    let body = br#"{
+        "flows": [
+            { "stages": ["m.login.password"] },
+            { "stages": ["m.login.recaptcha", "m.login.email.identity"] }
+        ],
+        "params": { "m.login.recaptcha": { "public_key": "abc" } },
+        "session": "xxxxxx",
+        "completed": ["m.login.recaptcha"]
+    }"#;
+
+    let uiaa = match Response::try_from(http_response(StatusCode::UNAUTHORIZED, body)) {
+        Err(FromHttpResponseError::Uiaa(uiaa)) => uiaa,
+        other => panic!("expected user-interactive authentication, got {:?}", other),
+    };
+
+    assert_eq!(uiaa.flows.len(), 2);
+    assert_eq!(uiaa.flows[1].stages, ["m.login.recaptcha", "m.login.email.identity"]);
+    assert_eq!(uiaa.completed, ["m.login.recaptcha"]);
+    assert_eq!(uiaa.params["m.login.recaptcha"]["public_key"], "abc");
+    assert_eq!(uiaa.session.as_deref(), Some("xxxxxx"));
+    assert!(uiaa.auth_error.is_none());
+}
+
+#[test]
+fn uiaa_response_with_auth_error() {
+    let body = br#"{
+        "errcode": "M_FORBIDDEN",
+        "error": "Invalid password",
+        "flows": [{ "stages": ["m.login.password"] }],
+        "session": "xxxxxx"
+    }"#;
+
+    let uiaa = match Response::try_from(http_response(StatusCode::UNAUTHORIZED, body)) {
+        Err(FromHttpResponseError::Uiaa(uiaa)) => uiaa,
+        other => panic!("expected user-interactive authentication, got {:?}", other),
+    };
+
+    let error = uiaa.auth_error.unwrap();
+    assert_eq!(error.kind, MatrixErrorKind::Forbidden);
+    assert_eq!(error.message, "Invalid password");
+    assert_eq!(error.status_code, StatusCode::UNAUTHORIZED);
+    assert!(error.extra().is_empty());
+}
+
+#[test]
+fn unauthorized_without_flows_is_server_error() {
+    let body = br#"{ "errcode": "M_UNKNOWN_TOKEN", "error": "Unknown access token" }"#;
+
+    match Response::try_from(http_response(StatusCode::UNAUTHORIZED, body)) {
+        Err(FromHttpResponseError::Http(err)) => assert_eq!(err.status(), StatusCode::UNAUTHORIZED),
+        other => panic!("expected server error, got {:?}", other),
+    }
+}
+
+#[test]
+fn uiaa_response_roundtrip() {
+    let uiaa = UiaaResponse {
+        session: Some("abc".to_owned()),
+        ..UiaaResponse::new(vec![AuthFlow::new(vec!["m.login.dummy".to_owned()])])
+    };
+    let http_res: http::Response<Vec<u8>> = uiaa.into();
+
+    assert_eq!(http_res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(http_res.body(), br#"{"flows":[{"stages":["m.login.dummy"]}],"session":"abc"}"#);
+
+    match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Uiaa(uiaa)) => {
+            assert_eq!(uiaa.flows, [AuthFlow::new(vec!["m.login.dummy".to_owned()])]);
+            assert_eq!(uiaa.session.as_deref(), Some("abc"));
+        }
+        other => panic!("expected user-interactive authentication, got {:?}", other),
+    }
+
+    let res: http::Response<Vec<u8>> = Response { user_id: "@a:b".to_owned() }.try_into().unwrap();
+    assert_eq!(Response::try_from(res).unwrap().user_id, "@a:b");
+}
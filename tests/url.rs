@@ -0,0 +1,149 @@
+use ruma_api::{exports::url::Url, path::join_url, ruma_api};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "some_endpoint",
+        path: "/_matrix/some/endpoint/:user",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub user: String,
+
+        #[ruma_api(query)]
+        pub limit: u32,
+    }
+
+    response {}
+}
+
+#[test]
+fn url_keeps_base_path() {
+    let req = Request { user: "alice".to_owned(), limit: 10 };
+    let base = Url::parse("https://example.org/matrix/").unwrap();
+
+    assert_eq!(
+        req.url(&base).unwrap().as_str(),
+        "https://example.org/matrix/_matrix/some/endpoint/alice?limit=10"
+    );
+}
+
+#[test]
+fn url_without_trailing_slash() {
+    let req = Request { user: "alice".to_owned(), limit: 10 };
+    let base = Url::parse("https://example.org").unwrap();
+
+    assert_eq!(
+        req.url(&base).unwrap().as_str(),
+        "https://example.org/_matrix/some/endpoint/alice?limit=10"
+    );
+}
+
+#[test]
+fn url_encodes_path_params() {
+    let req = Request { user: "@alice:example.org/x".to_owned(), limit: 1 };
+    let base = Url::parse("https://example.org").unwrap();
+
+    assert_eq!(
+        req.url(&base).unwrap().as_str(),
+        "https://example.org/_matrix/some/endpoint/@alice:example.org%2Fx?limit=1"
+    );
+}
+
+#[test]
+fn url_cannot_be_a_base() {
+    let req = Request { user: "alice".to_owned(), limit: 10 };
+    let base = Url::parse("mailto:alice@example.org").unwrap();
+
+    assert!(req.url(&base).is_err());
+}
+
+#[test]
+fn url_drops_base_query_and_fragment() {
+    let req = Request { user: "alice".to_owned(), limit: 10 };
+    let base = Url::parse("https://example.org:8448/matrix?foo=bar#frag").unwrap();
+
+    assert_eq!(
+        req.url(&base).unwrap().as_str(),
+        "https://example.org:8448/matrix/_matrix/some/endpoint/alice?limit=10"
+    );
+}
+
+#[test]
+fn join_url_keeps_base_path() {
+    let cases = [
+        ("https://example.org", "https://example.org/_matrix/client/versions"),
+        ("https://example.org/", "https://example.org/_matrix/client/versions"),
+        ("https://example.org/matrix", "https://example.org/matrix/_matrix/client/versions"),
+        ("https://example.org/matrix/", "https://example.org/matrix/_matrix/client/versions"),
+        ("https://example.org/a/b//", "https://example.org/a/b/_matrix/client/versions"),
+        (
+            "https://example.org:8448/matrix",
+            "https://example.org:8448/matrix/_matrix/client/versions",
+        ),
+        ("http://[::1]:8008", "http://[::1]:8008/_matrix/client/versions"),
+        ("https://[2001:db8::1]/proxy/", "https://[2001:db8::1]/proxy/_matrix/client/versions"),
+        (
+            "https://example.org/matrix?foo=bar#frag",
+            "https://example.org/matrix/_matrix/client/versions",
+        ),
+    ];
+
+    for (base, expected) in &cases {
+        let base = Url::parse(base).unwrap();
+        assert_eq!(join_url(&base, "/_matrix/client/versions").unwrap().as_str(), *expected);
+    }
+}
+
+#[test]
+fn join_url_with_query() {
+    let base = Url::parse("https://example.org/matrix/").unwrap();
+
+    assert_eq!(
+        join_url(&base, "_matrix/some/endpoint/%40alice?limit=10").unwrap().as_str(),
+        "https://example.org/matrix/_matrix/some/endpoint/%40alice?limit=10"
+    );
+}
+
+#[test]
+fn join_url_cannot_be_a_base() {
+    let base = Url::parse("mailto:alice@example.org").unwrap();
+
+    assert!(join_url(&base, "/_matrix/client/versions").is_err());
+}
+
+mod without_query {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "without_query",
+            path: "/_matrix/some/endpoint/:user/profile",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub user: String,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn path_with_params() {
+    let req = Request { user: "@alice:example.org/x".to_owned(), limit: 1 };
+    assert_eq!(
+        req.path_with_params().unwrap(),
+        "/_matrix/some/endpoint/@alice:example.org%2Fx?limit=1"
+    );
+
+    let req = without_query::Request { user: "bob smith".to_owned() };
+    assert_eq!(req.path_with_params().unwrap(), "/_matrix/some/endpoint/bob%20smith/profile");
+}
@@ -0,0 +1,69 @@
+use std::convert::TryInto;
+
+use http::header::{HeaderMap, HeaderValue, USER_AGENT};
+use ruma_api::{header::insert_default, ruma_api};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "user_agent",
+        path: "/_matrix/some/user_agent/endpoint",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(header = USER_AGENT)]
+        pub user_agent: Option<String>,
+
+        #[ruma_api(header_map)]
+        pub other_headers: Vec<(http::header::HeaderName, String)>,
+    }
+
+    response {}
+}
+
+/// Converts the request like a transport that sends `transport/1.0` as its default user agent.
+fn send(req: Request) -> http::Request<Vec<u8>> {
+    let mut http_req: http::Request<Vec<u8>> = req.try_into().unwrap();
+    insert_default(http_req.headers_mut(), USER_AGENT, HeaderValue::from_static("transport/1.0"));
+    http_req
+}
+
+#[test]
+fn field_overrides_transport_default() {
+    let req = Request { user_agent: Some("client/2.0".to_owned()), other_headers: Vec::new() };
+    let http_req = send(req);
+
+    let values: Vec<_> = http_req.headers().get_all(USER_AGENT).iter().collect();
+    assert_eq!(values, ["client/2.0"]);
+}
+
+#[test]
+fn transport_default_without_field() {
+    let req = Request { user_agent: None, other_headers: Vec::new() };
+    let http_req = send(req);
+
+    assert_eq!(http_req.headers().get(USER_AGENT).unwrap(), "transport/1.0");
+}
+
+#[test]
+fn field_overrides_header_map() {
+    let req = Request {
+        user_agent: Some("client/2.0".to_owned()),
+        other_headers: vec![(USER_AGENT, "other/3.0".to_owned())],
+    };
+    let http_req = send(req);
+
+    let values: Vec<_> = http_req.headers().get_all(USER_AGENT).iter().collect();
+    assert_eq!(values, ["client/2.0"]);
+}
+
+#[test]
+fn insert_default_keeps_existing_value() {
+    let mut headers = HeaderMap::new();
+    assert!(insert_default(&mut headers, USER_AGENT, HeaderValue::from_static("a")));
+    assert!(!insert_default(&mut headers, USER_AGENT, HeaderValue::from_static("b")));
+    assert_eq!(headers.get(USER_AGENT).unwrap(), "a");
+}
@@ -0,0 +1,83 @@
+use std::convert::TryInto;
+
+use http::header::HeaderName;
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: PUT,
+        name: "validate",
+        path: "/_matrix/some/endpoint/:room_id",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+
+        #[ruma_api(header = "X-Custom")]
+        pub custom: String,
+
+        pub content: Vec<u8>,
+    }
+
+    response {}
+}
+
+mod with_header_map {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "with_header_map",
+            path: "/_matrix/some/header_map",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(header_map)]
+            pub headers: Vec<(http::header::HeaderName, String)>,
+        }
+
+        response {}
+    }
+}
+
+#[test]
+fn validate_valid_request() {
+    let req = Request {
+        room_id: "!room:example.org".to_owned(),
+        custom: "value".to_owned(),
+        content: vec![0; 1024],
+    };
+
+    assert!(req.validate().is_ok());
+}
+
+#[test]
+fn validate_invalid_header() {
+    let req = Request {
+        room_id: "!room:example.org".to_owned(),
+        custom: "line\nbreak".to_owned(),
+        content: Vec::new(),
+    };
+
+    assert!(req.validate().is_err());
+
+    let err = TryInto::<http::Request<Vec<u8>>>::try_into(req).unwrap_err();
+    assert!(err.to_string().starts_with("Invalid header value"));
+}
+
+#[test]
+fn validate_invalid_header_map_value() {
+    let name = HeaderName::from_static("x-custom");
+    let req = with_header_map::Request { headers: vec![(name.clone(), "ok".to_owned())] };
+    assert!(req.validate().is_ok());
+
+    let req = with_header_map::Request { headers: vec![(name, "bad\r\nvalue".to_owned())] };
+    assert!(req.validate().is_err());
+    assert!(TryInto::<http::Request<Vec<u8>>>::try_into(req).is_err());
+}
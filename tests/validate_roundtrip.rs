@@ -0,0 +1,150 @@
+#![cfg(all(feature = "validate-roundtrip", debug_assertions))]
+
+use std::convert::TryInto;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A number that is serialized as a string but only deserialized from a number.
+#[derive(Clone, Debug)]
+pub struct Inconsistent(pub u32);
+
+impl Serialize for Inconsistent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Inconsistent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u32::deserialize(deserializer).map(Self)
+    }
+}
+
+/// A number that is rounded down to an even number when deserialized.
+#[derive(Clone, Debug)]
+pub struct Lossy(pub u32);
+
+impl Serialize for Lossy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Lossy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u32::deserialize(deserializer).map(|n| Self(n / 2 * 2))
+    }
+}
+
+mod consistent {
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "consistent",
+            path: "/_matrix/some/consistent",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            pub value: u32,
+        }
+
+        response {
+            pub value: u32,
+        }
+    }
+}
+
+mod inconsistent {
+    use super::Inconsistent;
+
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "inconsistent",
+            path: "/_matrix/some/inconsistent",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            pub value: Inconsistent,
+        }
+
+        response {
+            pub value: Inconsistent,
+        }
+    }
+}
+
+mod lossy {
+    use super::Lossy;
+
+    ruma_api::ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "lossy",
+            path: "/_matrix/some/lossy",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            pub value: Lossy,
+        }
+
+        response {
+            pub value: Lossy,
+        }
+    }
+}
+
+#[test]
+fn consistent_bodies_pass() {
+    let req = consistent::Request { value: 1 };
+    let _: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    let res = consistent::Response { value: 1 };
+    let _: http::Response<Vec<u8>> = res.try_into().unwrap();
+}
+
+#[test]
+#[should_panic(expected = "serialized body doesn't deserialize as")]
+fn inconsistent_request_body_panics() {
+    let req = inconsistent::Request { value: Inconsistent(1) };
+    let _: Result<http::Request<Vec<u8>>, _> = req.try_into();
+}
+
+#[test]
+#[should_panic(expected = "serialized body doesn't deserialize as")]
+fn inconsistent_response_body_panics() {
+    let res = inconsistent::Response { value: Inconsistent(1) };
+    let _: Result<http::Response<Vec<u8>>, _> = res.try_into();
+}
+
+#[test]
+fn lossless_values_of_lossy_type_pass() {
+    let req = lossy::Request { value: Lossy(2) };
+    let _: http::Request<Vec<u8>> = req.try_into().unwrap();
+
+    let res = lossy::Response { value: Lossy(2) };
+    let _: http::Response<Vec<u8>> = res.try_into().unwrap();
+}
+
+#[test]
+#[should_panic(expected = "serialized body changes in a roundtrip")]
+fn lossy_request_body_panics() {
+    let req = lossy::Request { value: Lossy(3) };
+    let _: Result<http::Request<Vec<u8>>, _> = req.try_into();
+}
+
+#[test]
+#[should_panic(expected = "serialized body changes in a roundtrip")]
+fn lossy_response_body_panics() {
+    let res = lossy::Response { value: Lossy(3) };
+    let _: Result<http::Response<Vec<u8>>, _> = res.try_into();
+}
@@ -0,0 +1,117 @@
+use std::convert::TryFrom;
+
+use http::{header::WWW_AUTHENTICATE, HeaderMap, StatusCode};
+use ruma_api::{
+    error::FromHttpResponseError,
+    header::{www_authenticate, Challenge},
+    ruma_api,
+    uiaa::{AuthFlow, UiaaResponse},
+};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "delete_device",
+        path: "/_matrix/some/delete_device",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {}
+
+    response {}
+}
+
+fn headers(values: &[&str]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for value in values {
+        headers.append(WWW_AUTHENTICATE, value.parse().unwrap());
+    }
+    headers
+}
+
+#[test]
+fn parse_challenges() {
+    let challenges = www_authenticate(&headers(&[
+        r#"Bearer realm="example.org", error="invalid_token", Basic realm="gateway""#,
+        r#"Negotiate YWJj==, Custom A=b, c="quoted \"value\"""#,
+    ]));
+
+    assert_eq!(challenges.len(), 4);
+    assert!(challenges[0].is_bearer());
+    assert_eq!(challenges[0].param("realm"), Some("example.org"));
+    assert_eq!(challenges[0].param("Error"), Some("invalid_token"));
+    assert_eq!(challenges[1].scheme, "Basic");
+    assert_eq!(challenges[1].params, [("realm".to_owned(), "gateway".to_owned())]);
+    assert_eq!(challenges[2].scheme, "Negotiate");
+    assert!(challenges[2].params.is_empty());
+    assert_eq!(
+        challenges[3].params,
+        [("a".to_owned(), "b".to_owned()), ("c".to_owned(), r#"quoted "value""#.to_owned())]
+    );
+}
+
+#[test]
+fn invalid_challenges() {
+    assert!(www_authenticate(&HeaderMap::new()).is_empty());
+    assert!(www_authenticate(&headers(&["realm=\"x\""])).is_empty());
+
+    let challenges = www_authenticate(&headers(&[r#"Bearer realm="unterminated"#]));
+    assert_eq!(challenges, [Challenge { scheme: "Bearer".to_owned(), params: Vec::new() }]);
+}
+
+#[test]
+fn challenge_display() {
+    let challenge = Challenge {
+        scheme: "Bearer".to_owned(),
+        params: vec![
+            ("realm".to_owned(), "example.org".to_owned()),
+            ("error".to_owned(), r#"a "b""#.to_owned()),
+        ],
+    };
+
+    assert_eq!(challenge.to_string(), r#"Bearer realm="example.org", error="a \"b\"""#);
+    assert_eq!(www_authenticate(&headers(&[&challenge.to_string()])), [challenge]);
+}
+
+#[test]
+fn uiaa_response_challenges() {
+    let uiaa = UiaaResponse {
+        www_authenticate: vec![Challenge {
+            scheme: "Bearer".to_owned(),
+            params: vec![("realm".to_owned(), "example.org".to_owned())],
+        }],
+        ..UiaaResponse::new(vec![AuthFlow::new(vec!["m.login.password".to_owned()])])
+    };
+    let http_res: http::Response<Vec<u8>> = uiaa.into();
+
+    assert_eq!(http_res.headers()[WWW_AUTHENTICATE], r#"Bearer realm="example.org""#);
+    assert_eq!(http_res.body(), br#"{"flows":[{"stages":["m.login.password"]}]}"#);
+
+    match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Uiaa(uiaa)) => {
+            assert!(uiaa.www_authenticate[0].is_bearer());
+            assert_eq!(uiaa.www_authenticate[0].param("realm"), Some("example.org"));
+        }
+        other => panic!("expected user-interactive authentication, got {:?}", other),
+    }
+}
+
+#[test]
+fn server_error_challenges() {
+    let http_res = http::Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header(WWW_AUTHENTICATE, r#"Bearer error="invalid_token""#)
+        .body(br#"{ "errcode": "M_UNKNOWN_TOKEN", "error": "Unknown access token" }"#.to_vec())
+        .unwrap();
+
+    match Response::try_from(http_res) {
+        Err(FromHttpResponseError::Http(err)) => {
+            let challenges = err.www_authenticate();
+            assert!(challenges[0].is_bearer());
+            assert_eq!(challenges[0].param("error"), Some("invalid_token"));
+        }
+        other => panic!("expected server error, got {:?}", other),
+    }
+}